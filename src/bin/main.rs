@@ -1,5 +1,4 @@
 use clap::Parser;
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 #[derive(Parser, Debug)]
 #[command(name = "jellofin-server")]
@@ -14,16 +13,21 @@ struct Args {
 
 #[tokio::main]
 async fn main() {
-    tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "jellofin_rs=info,tower_http=info".into()),
-        )
-        .with(tracing_subscriber::fmt::layer().json())
-        .init();
-
     let args = Args::parse();
 
+    // The config is read before logging is set up, as it says where logs go.
+    let config = match jellofin_rs::config::Config::from_file(&args.config) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+    if let Err(e) = jellofin_rs::logging::init(&config) {
+        eprintln!("Error: cannot open log file: {}", e);
+        std::process::exit(1);
+    }
+
     if let Err(e) = jellofin_rs::run(&args.config, args.debug_logs).await {
         eprintln!("Error: {}", e);
         std::process::exit(1);
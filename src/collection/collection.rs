@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::PathBuf;
+use std::sync::Arc;
 
 use super::item::*;
 
@@ -12,8 +13,30 @@ pub struct Collection {
     pub directory: PathBuf,
     pub base_url: Option<String>,
     pub hls_server: Option<String>,
-    pub movies: HashMap<String, Movie>,
-    pub shows: HashMap<String, Show>,
+    /// Serve generated artwork for items without a primary image.
+    pub placeholder_images: bool,
+    pub movies: HashMap<String, Arc<Movie>>,
+    pub shows: HashMap<String, Arc<Show>>,
+    /// Where each movie, show, season and episode lives, by item ID.
+    /// Rebuilt by `build_index` whenever the collection is published.
+    #[serde(skip)]
+    index: HashMap<String, ItemLocation>,
+}
+
+/// Position of an item inside a collection's movie and show maps.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ItemLocation {
+    Movie,
+    Show,
+    Season {
+        show_id: String,
+        season: i32,
+    },
+    Episode {
+        show_id: String,
+        season: i32,
+        episode: i32,
+    },
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -48,6 +71,7 @@ impl Collection {
         directory: PathBuf,
         base_url: Option<String>,
         hls_server: Option<String>,
+        placeholder_images: bool,
     ) -> Self {
         Self {
             id,
@@ -56,33 +80,135 @@ impl Collection {
             directory,
             base_url,
             hls_server,
+            placeholder_images,
             movies: HashMap::new(),
             shows: HashMap::new(),
+            index: HashMap::new(),
         }
     }
 
-    pub fn get_item(&self, id: &str) -> Option<ItemRef<'_>> {
-        if let Some(movie) = self.movies.get(id) {
-            return Some(ItemRef::Movie(movie));
+    pub fn build_index(&mut self) {
+        let mut index = HashMap::new();
+
+        for id in self.movies.keys() {
+            index.insert(id.clone(), ItemLocation::Movie);
         }
 
         for show in self.shows.values() {
-            if show.id == id {
-                return Some(ItemRef::Show(show));
-            }
-            for season in show.seasons.values() {
-                if season.id == id {
-                    return Some(ItemRef::Season(season));
+            index.insert(show.id.clone(), ItemLocation::Show);
+            for (season_num, season) in &show.seasons {
+                index.insert(
+                    season.id.clone(),
+                    ItemLocation::Season {
+                        show_id: show.id.clone(),
+                        season: *season_num,
+                    },
+                );
+                for (episode_num, episode) in &season.episodes {
+                    index.insert(
+                        episode.id.clone(),
+                        ItemLocation::Episode {
+                            show_id: show.id.clone(),
+                            season: *season_num,
+                            episode: *episode_num,
+                        },
+                    );
                 }
-                for episode in season.episodes.values() {
-                    if episode.id == id {
-                        return Some(ItemRef::Episode(episode));
-                    }
+            }
+        }
+
+        self.index = index;
+    }
+
+    /// Copy the `placeholder_images` setting to the images of every item.
+    /// Movies and shows that already have it stay shared.
+    pub fn apply_placeholder_images(&mut self) {
+        let enabled = self.placeholder_images;
+        for movie in self.movies.values_mut() {
+            if movie.images.placeholder != enabled {
+                Arc::make_mut(movie).images.placeholder = enabled;
+            }
+        }
+        for show in self.shows.values_mut() {
+            let differs = show.images.placeholder != enabled
+                || show.seasons.values().any(|season| {
+                    season.images.placeholder != enabled
+                        || season
+                            .episodes
+                            .values()
+                            .any(|episode| episode.images.placeholder != enabled)
+                });
+            if !differs {
+                continue;
+            }
+            let show = Arc::make_mut(show);
+            show.images.placeholder = enabled;
+            for season in show.seasons.values_mut() {
+                let season = Arc::make_mut(season);
+                season.images.placeholder = enabled;
+                for episode in season.episodes.values_mut() {
+                    Arc::make_mut(episode).images.placeholder = enabled;
                 }
             }
         }
+    }
+
+    /// IDs of all movies, shows, seasons and episodes in the collection.
+    pub fn item_ids(&self) -> impl Iterator<Item = &String> {
+        self.index.keys()
+    }
 
-        None
+    pub fn get_item(&self, id: &str) -> Option<ItemRef<'_>> {
+        match self.index.get(id)? {
+            ItemLocation::Movie => self.movies.get(id).map(|m| ItemRef::Movie(m)),
+            ItemLocation::Show => self.shows.get(id).map(|s| ItemRef::Show(s)),
+            ItemLocation::Season { show_id, season } => self
+                .shows
+                .get(show_id)?
+                .seasons
+                .get(season)
+                .map(|s| ItemRef::Season(s)),
+            ItemLocation::Episode {
+                show_id,
+                season,
+                episode,
+            } => self
+                .shows
+                .get(show_id)?
+                .seasons
+                .get(season)?
+                .episodes
+                .get(episode)
+                .map(|e| ItemRef::Episode(e)),
+        }
+    }
+
+    /// Like `get_item`, but shares the item instead of borrowing it.
+    pub fn get_shared_item(&self, id: &str) -> Option<Item> {
+        match self.index.get(id)? {
+            ItemLocation::Movie => self.movies.get(id).cloned().map(Item::Movie),
+            ItemLocation::Show => self.shows.get(id).cloned().map(Item::Show),
+            ItemLocation::Season { show_id, season } => self
+                .shows
+                .get(show_id)?
+                .seasons
+                .get(season)
+                .cloned()
+                .map(Item::Season),
+            ItemLocation::Episode {
+                show_id,
+                season,
+                episode,
+            } => self
+                .shows
+                .get(show_id)?
+                .seasons
+                .get(season)?
+                .episodes
+                .get(episode)
+                .cloned()
+                .map(Item::Episode),
+        }
     }
 
     pub fn get_genres(&self) -> HashMap<String, usize> {
@@ -1,53 +1,183 @@
-use crate::collection::{item::Item, CollectionRepo};
+use crate::collection::{item::Item, CollectionRepo, ImageInfo, ImageMeta, ItemRef};
+use crate::util::{Placeholder, PlaceholderShape};
 use std::path::PathBuf;
 
+/// Like `find_image_path`, together with the image's tag and BlurHash if
+/// the scan got to them.
+pub fn find_image(
+    collections: &CollectionRepo,
+    item_id: &str,
+    image_type: &str,
+    index: usize,
+) -> Option<(PathBuf, Option<ImageMeta>)> {
+    let (_, item) = collections.get_item(item_id)?;
+    let path = image_path(&item, image_type, index)?;
+    let meta = item_images(&item).meta.get(&path).cloned();
+    Some((path, meta))
+}
+
+/// Path of an item's image of the given type. Only backdrops can have an
+/// index other than 0.
 pub fn find_image_path(
     collections: &CollectionRepo,
     item_id: &str,
     image_type: &str,
+    index: usize,
 ) -> Option<PathBuf> {
-    if let Some((_, item)) = collections.get_item(item_id) {
-        match item {
-            Item::Movie(movie) => match image_type.to_lowercase().as_str() {
-                "primary" => movie.images.primary.clone(),
-                "backdrop" => movie.images.backdrop.clone(),
-                "logo" => movie.images.logo.clone(),
-                "thumb" => movie.images.thumb.clone(),
-                "banner" => movie.images.banner.clone(),
-                _ => None,
-            },
-            Item::Show(show) => match image_type.to_lowercase().as_str() {
-                "primary" => show.images.primary.clone(),
-                "backdrop" => show.images.backdrop.clone(),
-                "logo" => show.images.logo.clone(),
-                "thumb" => show.images.thumb.clone(),
-                "banner" => show.images.banner.clone(),
-                _ => None,
-            },
-            Item::Season(season) => match image_type.to_lowercase().as_str() {
-                "primary" => season.images.primary.clone(),
-                "backdrop" => season.images.backdrop.clone(),
-                "logo" => season.images.logo.clone(),
-                "thumb" => season.images.thumb.clone(),
-                "banner" => season.images.banner.clone(),
-                _ => None,
-            },
-            Item::Episode(episode) => match image_type.to_lowercase().as_str() {
-                // For episodes, fall back to thumb if primary is None
-                // (episode thumbnails are often named with -thumb suffix)
-                "primary" => episode
-                    .images
-                    .primary
-                    .clone()
-                    .or_else(|| episode.images.thumb.clone()),
-                "backdrop" => episode.images.backdrop.clone(),
-                "logo" => episode.images.logo.clone(),
-                "thumb" => episode.images.thumb.clone(),
-                "banner" => episode.images.banner.clone(),
-                _ => None,
-            },
+    let (_, item) = collections.get_item(item_id)?;
+    image_path(&item, image_type, index)
+}
+
+/// Generated artwork to serve instead of a missing primary image, if the
+/// item's collection has placeholder images enabled.
+pub fn find_placeholder(
+    collections: &CollectionRepo,
+    item_id: &str,
+    image_type: &str,
+    index: usize,
+) -> Option<Placeholder> {
+    if index != 0 || !image_type.eq_ignore_ascii_case("primary") {
+        return None;
+    }
+    let (_, item) = collections.get_item(item_id)?;
+    if !item_images(&item).placeholder || image_path(&item, "primary", 0).is_some() {
+        return None;
+    }
+    let item = match &item {
+        Item::Movie(movie) => ItemRef::Movie(movie),
+        Item::Show(show) => ItemRef::Show(show),
+        Item::Season(season) => ItemRef::Season(season),
+        Item::Episode(episode) => ItemRef::Episode(episode),
+    };
+    Some(item_placeholder(item))
+}
+
+/// Placeholder artwork for an item: a poster with the title and year, or
+/// for episodes a thumb with the episode name and number.
+pub fn item_placeholder(item: ItemRef<'_>) -> Placeholder {
+    let (id, title, subtitle, shape) = match item {
+        ItemRef::Movie(movie) => (
+            &movie.id,
+            &movie.name,
+            movie.production_year.map(|year| year.to_string()),
+            PlaceholderShape::Poster,
+        ),
+        ItemRef::Show(show) => (
+            &show.id,
+            &show.name,
+            show.production_year.map(|year| year.to_string()),
+            PlaceholderShape::Poster,
+        ),
+        ItemRef::Season(season) => (&season.id, &season.name, None, PlaceholderShape::Poster),
+        ItemRef::Episode(episode) => (
+            &episode.id,
+            &episode.name,
+            Some(format!(
+                "S{:02}E{:02}",
+                episode.season_number, episode.episode_number
+            )),
+            PlaceholderShape::Thumb,
+        ),
+    };
+    Placeholder {
+        id: id.clone(),
+        title: title.clone(),
+        subtitle,
+        shape,
+    }
+}
+
+fn image_path(item: &Item, image_type: &str, index: usize) -> Option<PathBuf> {
+    let images = item_images(item);
+    let image_type = image_type.to_lowercase();
+    if image_type == "backdrop" {
+        return images.backdrops.get(index).cloned();
+    }
+    if index != 0 {
+        return None;
+    }
+    match image_type.as_str() {
+        // For episodes, fall back to thumb if primary is None
+        // (episode thumbnails are often named with -thumb suffix)
+        "primary" => match item {
+            Item::Episode(_) => images.primary.clone().or_else(|| images.thumb.clone()),
+            _ => images.primary.clone(),
+        },
+        "logo" => images.logo.clone(),
+        "thumb" => images.thumb.clone(),
+        "banner" => images.banner.clone(),
+        _ => None,
+    }
+}
+
+fn item_images(item: &Item) -> &ImageInfo {
+    match item {
+        Item::Movie(movie) => &movie.images,
+        Item::Show(show) => &show.images,
+        Item::Season(season) => &season.images,
+        Item::Episode(episode) => &episode.images,
+    }
+}
+
+/// Where to store an uploaded image of the given type. An image that
+/// exists is replaced (returned as the second path, to remove it if the
+/// new one has another extension); a new one is named the Kodi way, as the
+/// scanner recognises it: `poster`, `fanart`, `fanart1`, `logo`, `thumb`,
+/// `banner`, prefixed with `seasonNN-` for seasons and the video name for
+/// episodes. A backdrop without index is added.
+pub fn image_upload_path(
+    item: &Item,
+    image_type: &str,
+    index: Option<usize>,
+    extension: &str,
+) -> Option<(PathBuf, Option<PathBuf>)> {
+    let image_type = image_type.to_lowercase();
+    let is_backdrop = image_type == "backdrop";
+    if let Some(index) = index.or((!is_backdrop).then_some(0)) {
+        if let Some(existing) = image_path(item, &image_type, index) {
+            return Some((existing.with_extension(extension), Some(existing)));
+        }
+        if index != 0 && !is_backdrop {
+            return None;
+        }
+    }
+
+    let name = match image_type.as_str() {
+        "primary" => match item {
+            Item::Episode(_) => "thumb",
+            _ => "poster",
+        },
+        "backdrop" => "fanart",
+        "logo" => "logo",
+        "thumb" => "thumb",
+        "banner" => "banner",
+        _ => return None,
+    };
+    let (dir, prefix) = match item {
+        Item::Movie(movie) => (movie.path.clone(), String::new()),
+        Item::Show(show) => (show.path.clone(), String::new()),
+        Item::Season(season) => (
+            season.path.clone(),
+            format!("season{:02}-", season.season_number),
+        ),
+        Item::Episode(episode) => (
+            episode.path.parent()?.to_path_buf(),
+            format!("{}-", episode.path.file_stem()?.to_str()?),
+        ),
+    };
+
+    let mut stem = format!("{}{}", prefix, name);
+    if is_backdrop {
+        // The first free one of fanart, fanart1, fanart2, ...
+        let backdrops = &item_images(item).backdrops;
+        let mut number = 0;
+        while backdrops
+            .iter()
+            .any(|path| path.file_stem().and_then(|s| s.to_str()) == Some(stem.as_str()))
+        {
+            number += 1;
+            stem = format!("{}{}{}", prefix, name, number);
         }
-    } else {
-        None
     }
+    Some((dir.join(format!("{}.{}", stem, extension)), None))
 }
@@ -0,0 +1,185 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tracing::debug;
+
+use super::collection::Collection;
+use super::item::ImageInfo;
+
+/// Size images are scaled down to before encoding; a BlurHash only holds
+/// a few colors, so more pixels would only cost time.
+const THUMBNAIL_SIZE: u32 = 64;
+
+/// What clients need to know about an image file: its tag, which changes
+/// whenever the file does, and its BlurHash. Size and mtime (in
+/// milliseconds) tell whether the file changed since.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImageMeta {
+    pub size: u64,
+    pub mtime: i64,
+    pub tag: String,
+    /// None if the image cannot be decoded.
+    pub blur_hash: Option<String>,
+}
+
+/// Image metadata by path, so that scans only read new or changed files.
+#[derive(Default)]
+pub struct ImageMetaCache {
+    entries: Mutex<HashMap<PathBuf, ImageMeta>>,
+}
+
+impl ImageMetaCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Remember the metadata of a collection's images, such as those
+    /// loaded from the database.
+    pub fn add_collection(&self, collection: &Collection) {
+        let mut entries = self.entries.lock().unwrap();
+        for_each_image_info(collection, |images| {
+            for (path, meta) in &images.meta {
+                entries.insert(path.clone(), meta.clone());
+            }
+        });
+    }
+
+    /// Give every image in the collection up-to-date metadata. Items are
+    /// only copied if one of their images changed; the IDs of the movies
+    /// and shows that were are returned.
+    pub fn fill_collection(&self, collection: &mut Collection) -> Vec<String> {
+        let mut updated = Vec::new();
+        for movie in collection.movies.values_mut() {
+            if is_stale(&movie.images) {
+                self.fill(&mut Arc::make_mut(movie).images);
+                updated.push(movie.id.clone());
+            }
+        }
+        for show in collection.shows.values_mut() {
+            let stale = is_stale(&show.images)
+                || show.seasons.values().any(|season| {
+                    is_stale(&season.images)
+                        || season.episodes.values().any(|e| is_stale(&e.images))
+                });
+            if !stale {
+                continue;
+            }
+            let show = Arc::make_mut(show);
+            self.fill(&mut show.images);
+            for season in show.seasons.values_mut() {
+                let season = Arc::make_mut(season);
+                self.fill(&mut season.images);
+                for episode in season.episodes.values_mut() {
+                    self.fill(&mut Arc::make_mut(episode).images);
+                }
+            }
+            updated.push(show.id.clone());
+        }
+        updated
+    }
+
+    fn fill(&self, images: &mut ImageInfo) {
+        let meta = images
+            .paths()
+            .filter_map(|path| Some((path.clone(), self.get_or_compute(path)?)))
+            .collect();
+        images.meta = meta;
+    }
+
+    fn get_or_compute(&self, path: &Path) -> Option<ImageMeta> {
+        let (size, mtime) = file_stamp(path)?;
+        if let Some(cached) = self.entries.lock().unwrap().get(path) {
+            if cached.size == size && cached.mtime == mtime {
+                return Some(cached.clone());
+            }
+        }
+
+        let tag = match compute_tag(path, size, mtime) {
+            Ok(tag) => tag,
+            Err(e) => {
+                debug!("Cannot read image {:?}: {}", path, e);
+                return None;
+            }
+        };
+        let meta = ImageMeta {
+            size,
+            mtime,
+            tag,
+            blur_hash: compute_blur_hash(path),
+        };
+        self.entries
+            .lock()
+            .unwrap()
+            .insert(path.to_path_buf(), meta.clone());
+        Some(meta)
+    }
+}
+
+fn is_stale(images: &ImageInfo) -> bool {
+    images.meta.len() != images.paths().count()
+        || images.paths().any(|path| {
+            images
+                .meta
+                .get(path)
+                .is_none_or(|meta| file_stamp(path) != Some((meta.size, meta.mtime)))
+        })
+}
+
+/// Image tag of a file: a hash of its size, mtime and content.
+pub fn compute_tag(path: &Path, size: u64, mtime: i64) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    hasher.update(size.to_le_bytes());
+    hasher.update(mtime.to_le_bytes());
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(hex::encode(&hasher.finalize()[..16]))
+}
+
+/// BlurHash of an image file, or None if it cannot be decoded.
+pub fn compute_blur_hash(path: &Path) -> Option<String> {
+    let img = match image::open(path) {
+        Ok(img) => img,
+        Err(e) => {
+            debug!("Cannot compute BlurHash of {:?}: {}", path, e);
+            return None;
+        }
+    };
+    let thumbnail = img.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE).to_rgba8();
+    let (width, height) = thumbnail.dimensions();
+    // More components along the longer side, as Jellyfin does.
+    let (components_x, components_y) = if width >= height { (4, 3) } else { (3, 4) };
+    blurhash::encode(
+        components_x,
+        components_y,
+        width,
+        height,
+        thumbnail.as_raw(),
+    )
+    .ok()
+}
+
+/// Size and mtime in milliseconds of a file.
+fn file_stamp(path: &Path) -> Option<(u64, i64)> {
+    let metadata = std::fs::metadata(path).ok()?;
+    let modified = metadata.modified().ok()?;
+    let duration = modified.duration_since(std::time::UNIX_EPOCH).ok()?;
+    Some((metadata.len(), duration.as_millis() as i64))
+}
+
+fn for_each_image_info(collection: &Collection, mut f: impl FnMut(&ImageInfo)) {
+    for movie in collection.movies.values() {
+        f(&movie.images);
+    }
+    for show in collection.shows.values() {
+        f(&show.images);
+        for season in show.seasons.values() {
+            f(&season.images);
+            for episode in season.episodes.values() {
+                f(&episode.images);
+            }
+        }
+    }
+}
@@ -2,6 +2,9 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::PathBuf;
+use std::sync::Arc;
+
+use super::image_meta::ImageMeta;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Movie {
@@ -25,6 +28,8 @@ pub struct Movie {
     pub media_sources: Vec<MediaSource>,
     pub date_created: DateTime<Utc>,
     pub date_modified: DateTime<Utc>,
+    #[serde(skip)]
+    pub stamp: DirStamp,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -45,9 +50,11 @@ pub struct Show {
     pub studios: Vec<String>,
     pub people: Vec<Person>,
     pub images: ImageInfo,
-    pub seasons: HashMap<i32, Season>,
+    pub seasons: HashMap<i32, Arc<Season>>,
     pub date_created: DateTime<Utc>,
     pub date_modified: DateTime<Utc>,
+    #[serde(skip)]
+    pub stamp: DirStamp,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -61,7 +68,7 @@ pub struct Season {
     pub premiere_date: Option<DateTime<Utc>>,
     pub overview: Option<String>,
     pub images: ImageInfo,
-    pub episodes: HashMap<i32, Episode>,
+    pub episodes: HashMap<i32, Arc<Episode>>,
     pub date_created: DateTime<Utc>,
     pub date_modified: DateTime<Utc>,
 }
@@ -86,13 +93,67 @@ pub struct Episode {
     pub date_modified: DateTime<Utc>,
 }
 
+/// Modification times (in milliseconds) of the files in a movie or show
+/// directory. If none of them changed, a rescan can reuse the previous result.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DirStamp {
+    pub nfotime: i64,
+    pub firstvideo: i64,
+    pub lastvideo: i64,
+    pub dirtime: i64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct ImageInfo {
     pub primary: Option<PathBuf>,
-    pub backdrop: Option<PathBuf>,
+    /// In display order; see `sort_backdrops` in the scanner.
+    #[serde(default, alias = "backdrop", deserialize_with = "one_or_many")]
+    pub backdrops: Vec<PathBuf>,
     pub logo: Option<PathBuf>,
     pub thumb: Option<PathBuf>,
     pub banner: Option<PathBuf>,
+    /// Tags and BlurHashes of the images above, by path; see `ImageMetaCache`.
+    #[serde(default)]
+    pub meta: HashMap<PathBuf, ImageMeta>,
+    /// Serve generated artwork when there is no primary image; follows
+    /// the collection setting, see `Collection::apply_placeholder_images`.
+    #[serde(skip)]
+    pub placeholder: bool,
+}
+
+impl ImageInfo {
+    pub fn paths(&self) -> impl Iterator<Item = &PathBuf> {
+        [&self.primary, &self.logo, &self.thumb, &self.banner]
+            .into_iter()
+            .flatten()
+            .chain(&self.backdrops)
+    }
+
+    pub fn tag(&self, path: &PathBuf) -> Option<&str> {
+        self.meta.get(path).map(|meta| meta.tag.as_str())
+    }
+
+    pub fn blur_hash(&self, path: &PathBuf) -> Option<&str> {
+        self.meta.get(path)?.blur_hash.as_deref()
+    }
+}
+
+/// Items stored before multiple backdrops were supported have a single,
+/// optional `backdrop`.
+fn one_or_many<'de, D>(deserializer: D) -> Result<Vec<PathBuf>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        Many(Vec<PathBuf>),
+        One(Option<PathBuf>),
+    }
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::Many(paths) => paths,
+        OneOrMany::One(path) => path.into_iter().collect(),
+    })
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -165,10 +226,10 @@ impl ItemType {
 
 #[derive(Debug, Clone)]
 pub enum Item {
-    Movie(crate::collection::Movie),
-    Show(crate::collection::Show),
-    Season(crate::collection::Season),
-    Episode(crate::collection::Episode),
+    Movie(std::sync::Arc<crate::collection::Movie>),
+    Show(std::sync::Arc<crate::collection::Show>),
+    Season(std::sync::Arc<crate::collection::Season>),
+    Episode(std::sync::Arc<crate::collection::Episode>),
 }
 
 #[derive(Debug, Clone, Copy)]
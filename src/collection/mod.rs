@@ -1,18 +1,25 @@
 pub mod collection;
 pub mod image;
+pub mod image_meta;
 pub mod item;
 pub mod nfo;
+pub mod parental_rating;
 pub mod parse_filename;
 pub mod repo;
 pub mod scanner;
 pub mod search;
 pub mod sort_name;
+pub mod watcher;
 
-pub use collection::{Collection, CollectionType};
-pub use image::find_image_path;
+pub use collection::{Collection, CollectionType, ItemLocation};
+pub use image::{
+    find_image, find_image_path, find_placeholder, image_upload_path, item_placeholder,
+};
+pub use image_meta::{ImageMeta, ImageMetaCache};
 pub use item::{
-    Episode, ImageInfo, Item, ItemRef, ItemType, MediaSource, Movie, Person, PersonType, Season, Show,
-    SubtitleStream,
+    DirStamp, Episode, ImageInfo, Item, ItemRef, ItemType, MediaSource, Movie, Person, PersonType,
+    Season, Show, SubtitleStream,
 };
-pub use repo::{CollectionRepo, CollectionRepoError};
+pub use parental_rating::{parental_ratings, rating_age};
+pub use repo::{CollectionRepo, CollectionRepoError, LibraryChange};
 pub use search::{SearchIndex, SearchResult};
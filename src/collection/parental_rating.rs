@@ -0,0 +1,161 @@
+/// A rating system and the minimum age for each of its ratings.
+struct RatingSystem {
+    country: &'static str,
+    ratings: &'static [(&'static str, u32)],
+}
+
+/// Rating systems in lookup order; a rating without a country prefix is
+/// looked up in each of them in turn.
+const RATING_SYSTEMS: &[RatingSystem] = &[
+    // MPAA and US TV Parental Guidelines.
+    RatingSystem {
+        country: "US",
+        ratings: &[
+            ("G", 0),
+            ("PG", 10),
+            ("PG-13", 13),
+            ("R", 17),
+            ("NC-17", 18),
+            ("TV-Y", 0),
+            ("TV-Y7", 7),
+            ("TV-G", 0),
+            ("TV-PG", 10),
+            ("TV-14", 14),
+            ("TV-MA", 17),
+        ],
+    },
+    // Kijkwijzer.
+    RatingSystem {
+        country: "NL",
+        ratings: &[
+            ("AL", 0),
+            ("6", 6),
+            ("9", 9),
+            ("12", 12),
+            ("14", 14),
+            ("16", 16),
+            ("18", 18),
+        ],
+    },
+    // BBFC.
+    RatingSystem {
+        country: "GB",
+        ratings: &[
+            ("U", 0),
+            ("PG", 8),
+            ("12A", 12),
+            ("12", 12),
+            ("15", 15),
+            ("18", 18),
+            ("R18", 18),
+        ],
+    },
+    // FSK.
+    RatingSystem {
+        country: "DE",
+        ratings: &[
+            ("FSK 0", 0),
+            ("FSK 6", 6),
+            ("FSK 12", 12),
+            ("FSK 16", 16),
+            ("FSK 18", 18),
+        ],
+    },
+];
+
+/// The minimum age for a rating as found in an NFO `<mpaa>` tag, such as
+/// `PG-13`, `Rated R`, `NL:12`, `UK:15` or `de:FSK 16`.
+/// Returns None for unrated (`NR`, `Unrated`) or unknown ratings.
+pub fn rating_age(rating: &str) -> Option<u32> {
+    let mut rating = rating.trim();
+    if let Some(prefix) = rating.get(..6) {
+        if prefix.eq_ignore_ascii_case("rated ") {
+            rating = rating[6..].trim();
+        }
+    }
+
+    let (country, name) = split_country(rating);
+    let name = normalize(name);
+    let age = RATING_SYSTEMS
+        .iter()
+        .filter(|system| country.is_none_or(|c| c == system.country))
+        .flat_map(|system| system.ratings)
+        .find(|(rating, _)| normalize(rating) == name)
+        .map(|(_, age)| *age);
+
+    // Anything else that is just an age, such as "FSK16" or "16".
+    age.or_else(|| name.trim_start_matches("FSK").parse().ok())
+}
+
+/// All known ratings, named as `rating_age` accepts them: US ratings as is,
+/// others with their country code (`NL-12`, `GB-15`).
+pub fn parental_ratings() -> Vec<(String, u32)> {
+    RATING_SYSTEMS
+        .iter()
+        .flat_map(|system| {
+            system.ratings.iter().map(move |(rating, age)| {
+                let name = match system.country {
+                    "US" => rating.to_string(),
+                    country => format!("{}-{}", country, rating),
+                };
+                (name, *age)
+            })
+        })
+        .collect()
+}
+
+/// Split off a `NL:` or `NL-` country prefix. `UK` is an alias for `GB`.
+fn split_country(rating: &str) -> (Option<&'static str>, &str) {
+    for separator in [':', '-'] {
+        if let Some((prefix, rest)) = rating.split_once(separator) {
+            let prefix = prefix.trim().to_ascii_uppercase();
+            let prefix = if prefix == "UK" {
+                "GB".to_string()
+            } else {
+                prefix
+            };
+            if let Some(system) = RATING_SYSTEMS.iter().find(|s| s.country == prefix) {
+                return (Some(system.country), rest.trim());
+            }
+        }
+    }
+    (None, rating)
+}
+
+fn normalize(rating: &str) -> String {
+    rating
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rating_age() {
+        assert_eq!(rating_age("PG-13"), Some(13));
+        assert_eq!(rating_age("Rated R"), Some(17));
+        assert_eq!(rating_age("TV-MA"), Some(17));
+        assert_eq!(rating_age("US:PG"), Some(10));
+        assert_eq!(rating_age("UK:PG"), Some(8));
+        assert_eq!(rating_age("GB-12A"), Some(12));
+        assert_eq!(rating_age("NL:AL"), Some(0));
+        assert_eq!(rating_age("NL-16"), Some(16));
+        assert_eq!(rating_age("de:FSK 16"), Some(16));
+        assert_eq!(rating_age("FSK12"), Some(12));
+        assert_eq!(rating_age("12"), Some(12));
+        assert_eq!(rating_age("NR"), None);
+        assert_eq!(rating_age("Unrated"), None);
+        assert_eq!(rating_age(""), None);
+    }
+
+    #[test]
+    fn test_parental_ratings_round_trip() {
+        for (name, age) in parental_ratings() {
+            assert_eq!(rating_age(&name), Some(age), "{}", name);
+        }
+    }
+}
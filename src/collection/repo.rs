@@ -2,27 +2,37 @@ use crate::collection::item::Item;
 use arc_swap::ArcSwap;
 use std::collections::HashMap;
 use std::sync::Arc;
-use tracing::{error, info};
+use tokio::sync::broadcast;
+use tracing::{debug, error, info, warn};
 
 use super::collection::{Collection, CollectionType};
-use super::item::ItemRef;
-use super::scanner::{scan_collection, ScanError};
+use super::image_meta::ImageMetaCache;
+use super::item::{DirStamp, ItemRef, Movie, Show};
+use super::scanner::{rescan_target, scan_collection, ScanError, ScanTarget};
 use super::search::{SearchIndex, SearchResult};
+use super::watcher::start_watchers;
 use crate::config::CollectionConfig;
+use crate::db::{self, DbError, ItemRepo, SqliteRepository};
 
 pub struct CollectionRepo {
-    collections: Arc<ArcSwap<HashMap<String, Collection>>>,
+    library: Arc<ArcSwap<Library>>,
     search_index: Arc<SearchIndex>,
+    db: Arc<SqliteRepository>,
+    changes: broadcast::Sender<LibraryChange>,
+    image_meta: Arc<ImageMetaCache>,
 }
 
 impl CollectionRepo {
-    pub fn new() -> Result<Self, CollectionRepoError> {
+    pub fn new(db: Arc<SqliteRepository>) -> Result<Self, CollectionRepoError> {
         let search_index =
             SearchIndex::new().map_err(|e| CollectionRepoError::Search(e.to_string()))?;
 
         Ok(Self {
-            collections: Arc::new(ArcSwap::from_pointee(HashMap::new())),
+            library: Arc::new(ArcSwap::from_pointee(Library::default())),
             search_index: Arc::new(search_index),
+            db,
+            changes: broadcast::channel(64).0,
+            image_meta: Arc::new(ImageMetaCache::new()),
         })
     }
 
@@ -47,68 +57,228 @@ impl CollectionRepo {
             config.directory.clone().into(),
             config.baseurl.clone(),
             config.hlsserver.clone(),
+            config.placeholder_images,
         );
 
-        // Clone current map, add new collection, and swap
-        let mut new_collections = (**self.collections.load()).clone();
-        new_collections.insert(id.clone(), collection);
-        self.collections.store(Arc::new(new_collections));
+        self.publish(collection);
 
         info!("Added collection: {} ({})", config.name, id);
         Ok(())
     }
 
-    pub async fn scan_all(&self) -> Result<(), CollectionRepoError> {
-        // Load current collections
-        let collections = self.collections.load();
-        let collection_ids: Vec<String> = collections.keys().cloned().collect();
+    /// Load the movies and shows stored by a previous run, so the server can
+    /// start serving before the first scan has finished.
+    pub async fn load_from_db(&self) -> Result<(), CollectionRepoError> {
+        let collection_ids: Vec<String> = self.library.load().collections.keys().cloned().collect();
 
-        // Scan each collection in spawn_blocking
         for id in collection_ids {
-            let collections = self.collections.load();
-
-            if let Some(collection) = collections.get(&id) {
-                info!("Scanning collection: {}", collection.name);
-
-                // Clone collection for scanning (keeps original available)
-                let mut cloned_collection = collection.clone();
-
-                // Use spawn_blocking to avoid blocking the async runtime during filesystem I/O
-                let scan_result = tokio::task::spawn_blocking(move || {
-                    let result = scan_collection(&mut cloned_collection);
-                    (cloned_collection, result)
-                })
-                .await;
-
-                // Atomically update the collection after scan
-                match scan_result {
-                    Ok((scanned_collection, Ok(()))) => {
-                        let mut new_collections = (**self.collections.load()).clone();
-                        new_collections.insert(id.clone(), scanned_collection);
-                        self.collections.store(Arc::new(new_collections));
-                    }
-                    Ok((scanned_collection, Err(e))) => {
-                        error!("Failed to scan collection {}: {}", id, e);
-                        // Still update with scanned collection even if there was an error
-                        let mut new_collections = (**self.collections.load()).clone();
-                        new_collections.insert(id.clone(), scanned_collection);
-                        self.collections.store(Arc::new(new_collections));
-                    }
-                    Err(e) => {
-                        error!("Scan task panicked for collection {}: {}", id, e);
+            let Some(mut collection) = self.collection_for_update(&id) else {
+                continue;
+            };
+
+            for item in self.db.list_items_by_collection(&id).await? {
+                let Some(data) = &item.data else {
+                    continue;
+                };
+                let stamp = DirStamp {
+                    nfotime: item.nfotime,
+                    firstvideo: item.firstvideo,
+                    lastvideo: item.lastvideo,
+                    dirtime: item.dirtime,
+                };
+                let result = match collection.collection_type {
+                    CollectionType::Movies => {
+                        serde_json::from_str::<Movie>(data).map(|mut movie| {
+                            movie.stamp = stamp;
+                            collection.movies.insert(movie.id.clone(), Arc::new(movie));
+                        })
                     }
+                    CollectionType::Shows => serde_json::from_str::<Show>(data).map(|mut show| {
+                        show.stamp = stamp;
+                        collection.shows.insert(show.id.clone(), Arc::new(show));
+                    }),
+                };
+                if let Err(e) = result {
+                    warn!("Ignoring stored item {} ({}): {}", item.id, item.name, e);
                 }
             }
+
+            info!(
+                "Loaded {} movies and {} shows of collection {} from database",
+                collection.movies.len(),
+                collection.shows.len(),
+                collection.name
+            );
+            self.image_meta.add_collection(&collection);
+            self.publish(collection);
+        }
+
+        self.rebuild_search_index().await
+    }
+
+    pub async fn scan_all(&self) -> Result<(), CollectionRepoError> {
+        let collection_ids: Vec<String> = self.library.load().collections.keys().cloned().collect();
+
+        for id in collection_ids {
+            self.scan_collection(&id).await;
+        }
+
+        self.rebuild_search_index().await
+    }
+
+    /// Full rescan of one collection.
+    pub async fn scan_collection(&self, id: &str) {
+        let Some(collection) = self.collection_for_update(id) else {
+            return;
+        };
+        info!("Scanning collection: {}", collection.name);
+        let previous = stamps(&collection);
+
+        // Scan a clone (keeps original available), in spawn_blocking to avoid
+        // blocking the async runtime during filesystem I/O.
+        let mut cloned_collection = collection;
+        let image_meta = self.image_meta.clone();
+        let scan_result = tokio::task::spawn_blocking(move || {
+            let result = scan_collection(&mut cloned_collection);
+            let images_changed = image_meta.fill_collection(&mut cloned_collection);
+            (cloned_collection, result, images_changed)
+        })
+        .await;
+
+        match scan_result {
+            Ok((scanned_collection, Ok(()), images_changed)) => {
+                let mut change = LibraryChange::between(&previous, &scanned_collection);
+                change.add_updated(images_changed);
+                self.persist(&change, &scanned_collection).await;
+                self.publish(scanned_collection);
+                self.notify(change);
+            }
+            Ok((scanned_collection, Err(e), _)) => {
+                error!("Failed to scan collection {}: {}", id, e);
+                // Still update with scanned collection even if there was an error,
+                // but do not drop stored items that may only be missing due to it.
+                self.publish(scanned_collection);
+            }
+            Err(e) => {
+                error!("Scan task panicked for collection {}: {}", id, e);
+            }
+        }
+    }
+
+    /// Rescan only the given movies, shows or seasons of a collection and
+    /// patch them into the published snapshot.
+    pub async fn rescan_targets(&self, id: &str, targets: Vec<ScanTarget>) {
+        let Some(collection) = self.collection_for_update(id) else {
+            return;
+        };
+        let previous = stamps(&collection);
+
+        let mut cloned_collection = collection;
+        let image_meta = self.image_meta.clone();
+        let scan_result = tokio::task::spawn_blocking(move || {
+            for target in &targets {
+                rescan_target(&mut cloned_collection, target);
+            }
+            let images_changed = image_meta.fill_collection(&mut cloned_collection);
+            (cloned_collection, images_changed)
+        })
+        .await;
+
+        match scan_result {
+            Ok((scanned_collection, images_changed)) => {
+                let mut change = LibraryChange::between(&previous, &scanned_collection);
+                change.add_updated(images_changed);
+                self.persist(&change, &scanned_collection).await;
+                self.publish(scanned_collection);
+                self.notify(change);
+            }
+            Err(e) => error!("Rescan task panicked for collection {}: {}", id, e),
         }
+    }
 
+    pub async fn rebuild_search_index(&self) -> Result<(), CollectionRepoError> {
         info!("Rebuilding search index");
-        let collections = self.collections.load();
+        let library = self.library.load();
         self.search_index
-            .rebuild(&collections)
+            .rebuild(&library.collections)
             .await
-            .map_err(|e| CollectionRepoError::Search(e.to_string()))?;
+            .map_err(|e| CollectionRepoError::Search(e.to_string()))
+    }
 
-        Ok(())
+    /// Store the movies and shows that were added or updated, and remove the
+    /// ones that disappeared.
+    async fn persist(&self, change: &LibraryChange, collection: &Collection) {
+        if change.is_empty() {
+            return;
+        }
+        debug!(
+            "Storing {} changed and removing {} deleted items of collection {}",
+            change.added.len() + change.updated.len(),
+            change.removed.len(),
+            collection.name
+        );
+
+        let changed: Vec<db::Item> = change
+            .added
+            .iter()
+            .chain(&change.updated)
+            .filter_map(|id| match collection.movies.get(id) {
+                Some(movie) => Some(movie_to_db_item(movie)),
+                None => collection.shows.get(id).map(|show| show_to_db_item(show)),
+            })
+            .collect();
+
+        if let Err(e) = self.db.upsert_items(&changed).await {
+            error!(
+                "Failed to store items of collection {}: {}",
+                collection.name, e
+            );
+        }
+        for id in &change.removed {
+            if let Err(e) = self.db.delete_item(id).await {
+                error!("Failed to delete item {}: {}", id, e);
+            }
+        }
+    }
+
+    /// Get notified of movies and shows being added, updated or removed.
+    pub fn subscribe(&self) -> broadcast::Receiver<LibraryChange> {
+        self.changes.subscribe()
+    }
+
+    fn notify(&self, change: LibraryChange) {
+        if !change.is_empty() {
+            // Only fails if nobody is listening.
+            let _ = self.changes.send(change);
+        }
+    }
+
+    /// Atomically replace a collection in the published map, together with
+    /// its entries in the item index.
+    fn publish(&self, mut collection: Collection) {
+        collection.apply_placeholder_images();
+        collection.build_index();
+
+        let mut library = (**self.library.load()).clone();
+        library
+            .item_collections
+            .retain(|_, collection_id| *collection_id != collection.id);
+        for item_id in collection.item_ids() {
+            library
+                .item_collections
+                .insert(item_id.clone(), collection.id.clone());
+        }
+        library
+            .collections
+            .insert(collection.id.clone(), Arc::new(collection));
+        self.library.store(Arc::new(library));
+    }
+
+    /// A copy of a published collection to scan into. Movies and shows are
+    /// shared with the published snapshot until they are replaced.
+    fn collection_for_update(&self, id: &str) -> Option<Collection> {
+        let library = self.library.load();
+        library.collections.get(id).map(|c| Collection::clone(c))
     }
 
     pub fn search(
@@ -131,26 +301,22 @@ impl CollectionRepo {
             .map_err(|e| CollectionRepoError::Search(e.to_string()))
     }
 
-    pub async fn get_collection(&self, id: &str) -> Option<Collection> {
-        let collections = self.collections.load();
-        collections.get(id).cloned()
+    pub async fn get_collection(&self, id: &str) -> Option<Arc<Collection>> {
+        self.library.load().collections.get(id).cloned()
     }
 
-    pub async fn list_collections(&self) -> Vec<Collection> {
-        let collections = self.collections.load();
-        collections.values().cloned().collect()
+    pub async fn list_collections(&self) -> Vec<Arc<Collection>> {
+        self.library.load().collections.values().cloned().collect()
     }
 
     pub async fn get_collection_id_for_item(&self, item_id: &str) -> Option<String> {
-        let collections = self.collections.load();
-
-        for (coll_id, collection) in collections.iter() {
-            if collection.get_item(item_id).is_some() {
-                return Some(coll_id.clone());
-            }
-        }
+        self.library.load().item_collections.get(item_id).cloned()
+    }
 
-        None
+    /// Watch the collection directories and rescan changed items as they change.
+    pub fn start_watchers(self: Arc<Self>) -> Result<(), CollectionRepoError> {
+        let library = self.library.load_full();
+        start_watchers(self, &library.collections)
     }
 
     pub fn start_background_scan(self: Arc<Self>, interval_secs: u64) {
@@ -166,22 +332,127 @@ impl CollectionRepo {
             }
         });
     }
+
     pub fn get_item(&self, id: &str) -> Option<(String, Item)> {
-        let collections = self.collections.load();
-
-        for collection in collections.values() {
-            if let Some(item_ref) = collection.get_item(id) {
-                let item = match item_ref {
-                    ItemRef::Movie(m) => Item::Movie(m.clone()),
-                    ItemRef::Show(s) => Item::Show(s.clone()),
-                    ItemRef::Season(s) => Item::Season(s.clone()),
-                    ItemRef::Episode(e) => Item::Episode(e.clone()),
-                };
-                return Some((collection.id.clone(), item));
+        let library = self.library.load();
+        let collection = library.collections.get(library.item_collections.get(id)?)?;
+
+        let item = collection.get_shared_item(id)?;
+        Some((collection.id.clone(), item))
+    }
+
+    /// The rating (NFO `mpaa`) of an item. Seasons and episodes have the
+    /// rating of their show.
+    pub fn get_rating(&self, id: &str) -> Option<String> {
+        let library = self.library.load();
+        let collection = library.collections.get(library.item_collections.get(id)?)?;
+
+        let show_id = match collection.get_item(id)? {
+            ItemRef::Movie(movie) => return movie.mpaa.clone(),
+            ItemRef::Show(show) => return show.mpaa.clone(),
+            ItemRef::Season(season) => &season.show_id,
+            ItemRef::Episode(episode) => &episode.show_id,
+        };
+        match collection.get_item(show_id)? {
+            ItemRef::Show(show) => show.mpaa.clone(),
+            _ => None,
+        }
+    }
+}
+
+/// The published state. The collections and the index from item ID to
+/// collection ID are swapped together, so lookups always see matching data.
+#[derive(Clone, Default)]
+struct Library {
+    collections: HashMap<String, Arc<Collection>>,
+    item_collections: HashMap<String, String>,
+}
+
+/// Movies and shows (by ID) that changed in a collection during a scan.
+#[derive(Debug, Clone, Default)]
+pub struct LibraryChange {
+    pub collection_id: String,
+    pub added: Vec<String>,
+    pub updated: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+impl LibraryChange {
+    fn between(previous: &HashMap<String, DirStamp>, collection: &Collection) -> Self {
+        let mut change = LibraryChange {
+            collection_id: collection.id.clone(),
+            ..Default::default()
+        };
+
+        let current = stamps(collection);
+        for (id, stamp) in &current {
+            match previous.get(id) {
+                None => change.added.push(id.clone()),
+                Some(prev) if prev != stamp => change.updated.push(id.clone()),
+                Some(_) => {}
+            }
+        }
+        change.removed = previous
+            .keys()
+            .filter(|id| !current.contains_key(*id))
+            .cloned()
+            .collect();
+
+        change
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.updated.is_empty() && self.removed.is_empty()
+    }
+
+    /// Also count these movies and shows as updated, such as ones whose
+    /// images changed without their directory changing.
+    fn add_updated(&mut self, ids: Vec<String>) {
+        for id in ids {
+            if !self.added.contains(&id) && !self.updated.contains(&id) {
+                self.updated.push(id);
             }
         }
+    }
+}
+
+fn stamps(collection: &Collection) -> HashMap<String, DirStamp> {
+    let movies = collection.movies.values().map(|m| (m.id.clone(), m.stamp));
+    let shows = collection.shows.values().map(|s| (s.id.clone(), s.stamp));
+    movies.chain(shows).collect()
+}
+
+fn movie_to_db_item(movie: &Movie) -> db::Item {
+    db::Item {
+        id: movie.id.clone(),
+        name: movie.name.clone(),
+        votes: None,
+        year: movie.production_year,
+        genre: movie.genres.join(","),
+        rating: movie.community_rating.map(|r| r as f32),
+        nfotime: movie.stamp.nfotime,
+        firstvideo: movie.stamp.firstvideo,
+        lastvideo: movie.stamp.lastvideo,
+        collectionid: Some(movie.collection_id.clone()),
+        dirtime: movie.stamp.dirtime,
+        data: serde_json::to_string(movie).ok(),
+    }
+}
 
-        None
+fn show_to_db_item(show: &Show) -> db::Item {
+    db::Item {
+        id: show.id.clone(),
+        name: show.name.clone(),
+        votes: None,
+        year: show.production_year,
+        genre: show.genres.join(","),
+        rating: show.community_rating.map(|r| r as f32),
+        nfotime: show.stamp.nfotime,
+        firstvideo: show.stamp.firstvideo,
+        lastvideo: show.stamp.lastvideo,
+        collectionid: Some(show.collection_id.clone()),
+        dirtime: show.stamp.dirtime,
+        data: serde_json::to_string(show).ok(),
     }
 }
 
@@ -193,4 +464,8 @@ pub enum CollectionRepoError {
     Scan(#[from] ScanError),
     #[error("Search error: {0}")]
     Search(String),
+    #[error("Watch error: {0}")]
+    Watch(#[from] notify::Error),
+    #[error("Database error: {0}")]
+    Database(#[from] DbError),
 }
@@ -1,7 +1,8 @@
 use chrono::{DateTime, Datelike, Utc};
 use std::collections::HashMap;
 use std::fs;
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
 use tracing::debug;
 
 #[cfg(unix)]
@@ -16,6 +17,8 @@ use crate::util::generate_id;
 const VIDEO_EXTENSIONS: &[&str] = &["mkv", "mp4", "avi", "m4v", "mov", "wmv", "flv", "webm"];
 const IMAGE_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "webp"];
 const SUBTITLE_EXTENSIONS: &[&str] = &["srt", "vtt"];
+/// Kodi directory with additional backdrops of a movie or show.
+const EXTRAFANART_DIR: &str = "extrafanart";
 
 pub fn scan_collection(collection: &mut Collection) -> Result<(), ScanError> {
     match collection.collection_type {
@@ -24,13 +27,145 @@ pub fn scan_collection(collection: &mut Collection) -> Result<(), ScanError> {
     }
 }
 
+/// A subtree of a collection that can be rescanned on its own.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ScanTarget {
+    Movie(PathBuf),
+    Show(PathBuf),
+    Season(PathBuf, i32),
+}
+
+/// Map a changed path below the collection root to the smallest subtree
+/// that needs to be rescanned.
+pub fn scan_target_for_path(
+    collection_type: CollectionType,
+    root: &Path,
+    path: &Path,
+) -> Option<ScanTarget> {
+    let rel = path.strip_prefix(root).ok()?;
+    let mut components = rel.components().filter_map(|c| match c {
+        Component::Normal(name) => name.to_str(),
+        _ => None,
+    });
+
+    let item_dir = root.join(components.next()?);
+
+    match collection_type {
+        CollectionType::Movies => Some(ScanTarget::Movie(item_dir)),
+        CollectionType::Shows => {
+            // Only a change *inside* a season directory is local to that season.
+            // Adding or removing the season directory itself changes the show.
+            match (components.next(), components.next()) {
+                (Some(dirname), Some(_)) => match parse_season_number(dirname) {
+                    Some(season_num) => {
+                        Some(ScanTarget::Season(item_dir.join(dirname), season_num))
+                    }
+                    None => Some(ScanTarget::Show(item_dir)),
+                },
+                _ => Some(ScanTarget::Show(item_dir)),
+            }
+        }
+    }
+}
+
+/// Rescan a single movie, show or season and patch the result into the collection.
+/// Items whose directory disappeared (or no longer contains videos) are removed.
+pub fn rescan_target(collection: &mut Collection, target: &ScanTarget) {
+    match target {
+        ScanTarget::Movie(dir) => {
+            let Some(name) = dir.file_name().and_then(|n| n.to_str()) else {
+                return;
+            };
+            let movie_id = generate_id(name);
+            let movie = if dir.is_dir() {
+                scan_movie_dir(dir, &collection.id, movie_dir_stamp(dir))
+            } else {
+                None
+            };
+            match movie {
+                Some(movie) => {
+                    debug!(
+                        "Updated movie {} in collection {}",
+                        movie.name, collection.name
+                    );
+                    collection.movies.insert(movie_id, Arc::new(movie));
+                }
+                None => {
+                    if collection.movies.remove(&movie_id).is_some() {
+                        debug!("Removed movie {} from collection {}", name, collection.name);
+                    }
+                }
+            }
+        }
+        ScanTarget::Show(dir) => {
+            let Some(name) = dir.file_name().and_then(|n| n.to_str()) else {
+                return;
+            };
+            let show_id = generate_id(name);
+            let show = if dir.is_dir() {
+                scan_show_dir(dir, &collection.id, show_dir_stamp(dir))
+            } else {
+                None
+            };
+            match show {
+                Some(show) => {
+                    debug!(
+                        "Updated show {} in collection {}",
+                        show.name, collection.name
+                    );
+                    collection.shows.insert(show_id, Arc::new(show));
+                }
+                None => {
+                    if collection.shows.remove(&show_id).is_some() {
+                        debug!("Removed show {} from collection {}", name, collection.name);
+                    }
+                }
+            }
+        }
+        ScanTarget::Season(dir, season_num) => {
+            let Some(show_dir) = dir.parent() else {
+                return;
+            };
+            let Some(show_name) = show_dir.file_name().and_then(|n| n.to_str()) else {
+                return;
+            };
+            let show_id = generate_id(show_name);
+
+            // Season of a show we do not know yet: scan the whole show.
+            let Some(show) = collection.shows.get_mut(&show_id) else {
+                rescan_target(collection, &ScanTarget::Show(show_dir.to_path_buf()));
+                return;
+            };
+            // Copy-on-write: earlier snapshots keep sharing the old show.
+            let show = Arc::make_mut(show);
+
+            let season = if dir.is_dir() {
+                scan_season_dir(dir, &show.id, &show.name, &collection.id, *season_num)
+            } else {
+                None
+            };
+            match season {
+                Some(season) => {
+                    debug!("Updated {} of show {}", season.name, show.name);
+                    show.seasons.insert(*season_num, Arc::new(season));
+                }
+                None => {
+                    show.seasons.remove(season_num);
+                }
+            }
+            show.stamp = show_dir_stamp(show_dir);
+        }
+    }
+}
+
 fn scan_movies(collection: &mut Collection) -> Result<(), ScanError> {
     let dir = &collection.directory;
     if !dir.exists() {
         return Err(ScanError::DirectoryNotFound(dir.clone()));
     }
 
-    collection.movies.clear();
+    let mut previous = std::mem::take(&mut collection.movies);
+    let mut unchanged = 0;
 
     for entry in fs::read_dir(dir)? {
         let entry = entry?;
@@ -40,20 +175,55 @@ fn scan_movies(collection: &mut Collection) -> Result<(), ScanError> {
             continue;
         }
 
-        if let Some(movie) = scan_movie_dir(&path, &collection.id) {
+        // Skip parsing directories where nothing changed since the last scan.
+        let stamp = movie_dir_stamp(&path);
+        if let Some(movie) = take_unchanged(&mut previous, &path, stamp) {
             collection.movies.insert(movie.id.clone(), movie);
+            unchanged += 1;
+            continue;
+        }
+
+        if let Some(movie) = scan_movie_dir(&path, &collection.id, stamp) {
+            collection.movies.insert(movie.id.clone(), Arc::new(movie));
         }
     }
 
     debug!(
-        "Scanned {} movies in collection {}",
+        "Scanned {} movies in collection {} ({} unchanged)",
         collection.movies.len(),
-        collection.name
+        collection.name,
+        unchanged
     );
     Ok(())
 }
 
-fn scan_movie_dir(dir: &Path, collection_id: &str) -> Option<Movie> {
+/// Take the previous scan result for `dir` out of `previous` if its stamp still matches.
+fn take_unchanged<T: HasStamp>(
+    previous: &mut HashMap<String, Arc<T>>,
+    dir: &Path,
+    stamp: DirStamp,
+) -> Option<Arc<T>> {
+    let id = generate_id(dir.file_name()?.to_str()?);
+    previous.remove(&id).filter(|item| item.stamp() == stamp)
+}
+
+trait HasStamp {
+    fn stamp(&self) -> DirStamp;
+}
+
+impl HasStamp for Movie {
+    fn stamp(&self) -> DirStamp {
+        self.stamp
+    }
+}
+
+impl HasStamp for Show {
+    fn stamp(&self) -> DirStamp {
+        self.stamp
+    }
+}
+
+fn scan_movie_dir(dir: &Path, collection_id: &str, stamp: DirStamp) -> Option<Movie> {
     let movie_name = dir.file_name()?.to_str()?.to_string();
     let movie_id = generate_id(&movie_name);
 
@@ -64,6 +234,10 @@ fn scan_movie_dir(dir: &Path, collection_id: &str) -> Option<Movie> {
     if let Ok(entries) = fs::read_dir(dir) {
         for entry in entries.flatten() {
             let path = entry.path();
+            if is_extrafanart_dir(&path) {
+                add_extrafanart(&mut images, &path);
+                continue;
+            }
             let filename = path.file_name()?.to_str()?;
             let extension = path.extension()?.to_str()?.to_lowercase();
 
@@ -76,6 +250,7 @@ fn scan_movie_dir(dir: &Path, collection_id: &str) -> Option<Movie> {
             }
         }
     }
+    sort_backdrops(&mut images.backdrops);
 
     if video_files.is_empty() {
         return None;
@@ -104,6 +279,7 @@ fn scan_movie_dir(dir: &Path, collection_id: &str) -> Option<Movie> {
         media_sources: Vec::new(),
         date_created: Utc::now(),
         date_modified: Utc::now(),
+        stamp,
     };
 
     if let Some(nfo_path) = nfo_path {
@@ -179,7 +355,8 @@ fn scan_shows(collection: &mut Collection) -> Result<(), ScanError> {
         return Err(ScanError::DirectoryNotFound(dir.clone()));
     }
 
-    collection.shows.clear();
+    let mut previous = std::mem::take(&mut collection.shows);
+    let mut unchanged = 0;
 
     for entry in fs::read_dir(dir)? {
         let entry = entry?;
@@ -189,20 +366,28 @@ fn scan_shows(collection: &mut Collection) -> Result<(), ScanError> {
             continue;
         }
 
-        if let Some(show) = scan_show_dir(&path, &collection.id) {
+        let stamp = show_dir_stamp(&path);
+        if let Some(show) = take_unchanged(&mut previous, &path, stamp) {
             collection.shows.insert(show.id.clone(), show);
+            unchanged += 1;
+            continue;
+        }
+
+        if let Some(show) = scan_show_dir(&path, &collection.id, stamp) {
+            collection.shows.insert(show.id.clone(), Arc::new(show));
         }
     }
 
     debug!(
-        "Scanned {} shows in collection {}",
+        "Scanned {} shows in collection {} ({} unchanged)",
         collection.shows.len(),
-        collection.name
+        collection.name,
+        unchanged
     );
     Ok(())
 }
 
-fn scan_show_dir(dir: &Path, collection_id: &str) -> Option<Show> {
+fn scan_show_dir(dir: &Path, collection_id: &str, stamp: DirStamp) -> Option<Show> {
     let show_name = dir.file_name()?.to_str()?.to_string();
     let show_id = generate_id(&show_name);
 
@@ -214,13 +399,15 @@ fn scan_show_dir(dir: &Path, collection_id: &str) -> Option<Show> {
         for entry in entries.flatten() {
             let path = entry.path();
 
-            if path.is_dir() {
+            if is_extrafanart_dir(&path) {
+                add_extrafanart(&mut images, &path);
+            } else if path.is_dir() {
                 let dirname = path.file_name()?.to_str()?;
                 if let Some(season_num) = parse_season_number(dirname) {
                     if let Some(season) =
                         scan_season_dir(&path, &show_id, &show_name, collection_id, season_num)
                     {
-                        seasons.insert(season_num, season);
+                        seasons.insert(season_num, Arc::new(season));
                     }
                 }
             } else {
@@ -235,6 +422,7 @@ fn scan_show_dir(dir: &Path, collection_id: &str) -> Option<Show> {
             }
         }
     }
+    sort_backdrops(&mut images.backdrops);
 
     let mut show = Show {
         id: show_id,
@@ -256,6 +444,7 @@ fn scan_show_dir(dir: &Path, collection_id: &str) -> Option<Show> {
         seasons,
         date_created: Utc::now(),
         date_modified: Utc::now(),
+        stamp,
     };
 
     if let Some(nfo_path) = nfo_path {
@@ -329,7 +518,7 @@ fn scan_season_dir(
                                 ep_info.season,
                                 ep_info.episode,
                             ) {
-                                episodes.insert(ep_info.episode, episode);
+                                episodes.insert(ep_info.episode, Arc::new(episode));
                             }
                         }
                     }
@@ -347,6 +536,7 @@ fn scan_season_dir(
             }
         }
     }
+    sort_backdrops(&mut images.backdrops);
 
     Some(Season {
         id: season_id,
@@ -437,6 +627,81 @@ fn create_episode(
     })
 }
 
+fn movie_dir_stamp(dir: &Path) -> DirStamp {
+    let mut stamp = DirStamp::default();
+    for subdir in add_dir_to_stamp(&mut stamp, dir) {
+        if is_extrafanart_dir(&subdir) {
+            add_dir_to_stamp(&mut stamp, &subdir);
+        }
+    }
+    stamp
+}
+
+fn show_dir_stamp(dir: &Path) -> DirStamp {
+    let mut stamp = DirStamp::default();
+    for season_dir in add_dir_to_stamp(&mut stamp, dir) {
+        let is_season = season_dir
+            .file_name()
+            .and_then(|n| n.to_str())
+            .and_then(parse_season_number)
+            .is_some();
+        if is_season || is_extrafanart_dir(&season_dir) {
+            add_dir_to_stamp(&mut stamp, &season_dir);
+        }
+    }
+    stamp
+}
+
+/// Fold the mtimes of a directory, its NFO files and its videos into `stamp`.
+/// Only stats files, nothing is opened. Returns the subdirectories.
+fn add_dir_to_stamp(stamp: &mut DirStamp, dir: &Path) -> Vec<PathBuf> {
+    let mut subdirs = Vec::new();
+
+    if let Some(mtime) = fs::metadata(dir).ok().and_then(|m| get_mtime_millis(&m)) {
+        stamp.dirtime = stamp.dirtime.max(mtime);
+    }
+
+    let Ok(entries) = fs::read_dir(dir) else {
+        return subdirs;
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Ok(metadata) = fs::metadata(&path) else {
+            continue;
+        };
+        if metadata.is_dir() {
+            subdirs.push(path);
+            continue;
+        }
+        let Some(mtime) = get_mtime_millis(&metadata) else {
+            continue;
+        };
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_lowercase())
+            .unwrap_or_default();
+
+        if VIDEO_EXTENSIONS.contains(&extension.as_str()) {
+            if stamp.firstvideo == 0 || mtime < stamp.firstvideo {
+                stamp.firstvideo = mtime;
+            }
+            stamp.lastvideo = stamp.lastvideo.max(mtime);
+        } else if extension == "nfo" {
+            stamp.nfotime = stamp.nfotime.max(mtime);
+        }
+    }
+
+    subdirs
+}
+
+fn get_mtime_millis(metadata: &fs::Metadata) -> Option<i64> {
+    let modified = metadata.modified().ok()?;
+    let duration = modified.duration_since(std::time::UNIX_EPOCH).ok()?;
+    Some(duration.as_millis() as i64)
+}
+
 fn parse_season_number(dirname: &str) -> Option<i32> {
     let lower = dirname.to_lowercase();
 
@@ -464,7 +729,7 @@ fn assign_image(images: &mut ImageInfo, filename: &str, path: PathBuf) {
     if lower.contains("poster") {
         images.primary = Some(path);
     } else if lower.contains("fanart") || lower.contains("backdrop") {
-        images.backdrop = Some(path);
+        images.backdrops.push(path);
     } else if lower.contains("logo") {
         images.logo = Some(path);
     } else if lower.contains("thumb") {
@@ -476,6 +741,55 @@ fn assign_image(images: &mut ImageInfo, filename: &str, path: PathBuf) {
     }
 }
 
+fn is_extrafanart_dir(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .is_some_and(|n| n.eq_ignore_ascii_case(EXTRAFANART_DIR))
+        && path.is_dir()
+}
+
+/// Every image in an `extrafanart` directory is a backdrop.
+fn add_extrafanart(images: &mut ImageInfo, dir: &Path) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let is_image = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| IMAGE_EXTENSIONS.contains(&e.to_lowercase().as_str()));
+        if is_image {
+            images.backdrops.push(path);
+        }
+    }
+}
+
+/// Put backdrops in Kodi order: `fanart.jpg` and `backdrop.jpg` first, then
+/// numbered ones (`fanart1.jpg`, `backdrop2.jpg`) by number, then those in
+/// `extrafanart/` by name.
+fn sort_backdrops(backdrops: &mut [PathBuf]) {
+    backdrops.sort_by_cached_key(|path| {
+        let in_extrafanart = path
+            .parent()
+            .and_then(|p| p.file_name())
+            .and_then(|n| n.to_str())
+            .is_some_and(|n| n.eq_ignore_ascii_case(EXTRAFANART_DIR));
+        let stem = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or_default()
+            .to_lowercase();
+        let number: u32 = stem
+            .strip_prefix(stem.trim_end_matches(|c: char| c.is_ascii_digit()))
+            .and_then(|digits| digits.parse().ok())
+            .unwrap_or(0);
+        // Kodi prefers fanart over backdrop.
+        let is_backdrop = stem.contains("backdrop");
+        (in_extrafanart, number, is_backdrop, stem)
+    });
+}
+
 /// Find thumbnail images for an episode based on video filename.
 /// Looks for images that match the video file's base name (e.g., "Show.S01E01-thumb.jpg" for "Show.S01E01.mkv")
 fn find_episode_images(video_path: &Path) -> ImageInfo {
@@ -512,6 +826,7 @@ fn find_episode_images(video_path: &Path) -> ImageInfo {
             }
         }
     }
+    sort_backdrops(&mut images.backdrops);
 
     images
 }
@@ -594,3 +909,79 @@ pub enum ScanError {
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sort_backdrops() {
+        let dir = Path::new("/media/movies/Alien (1979)");
+        let mut backdrops = vec![
+            dir.join("extrafanart/b.jpg"),
+            dir.join("backdrop2.jpg"),
+            dir.join("extrafanart/a.jpg"),
+            dir.join("fanart1.jpg"),
+            dir.join("backdrop.jpg"),
+            dir.join("fanart.jpg"),
+        ];
+        sort_backdrops(&mut backdrops);
+        assert_eq!(
+            backdrops,
+            vec![
+                dir.join("fanart.jpg"),
+                dir.join("backdrop.jpg"),
+                dir.join("fanart1.jpg"),
+                dir.join("backdrop2.jpg"),
+                dir.join("extrafanart/a.jpg"),
+                dir.join("extrafanart/b.jpg"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_scan_target_for_movie_path() {
+        let root = Path::new("/media/movies");
+        let target = scan_target_for_path(
+            CollectionType::Movies,
+            root,
+            Path::new("/media/movies/Alien (1979)/movie.nfo"),
+        );
+        assert_eq!(target, Some(ScanTarget::Movie(root.join("Alien (1979)"))));
+        assert_eq!(
+            scan_target_for_path(CollectionType::Movies, root, Path::new("/elsewhere/x.mkv")),
+            None
+        );
+        assert_eq!(
+            scan_target_for_path(CollectionType::Movies, root, root),
+            None
+        );
+    }
+
+    #[test]
+    fn test_scan_target_for_show_path() {
+        let root = Path::new("/media/tv");
+        let show = root.join("Firefly");
+
+        let target = scan_target_for_path(
+            CollectionType::Shows,
+            root,
+            Path::new("/media/tv/Firefly/Season 1/Firefly.S01E01.mkv"),
+        );
+        assert_eq!(target, Some(ScanTarget::Season(show.join("Season 1"), 1)));
+
+        let target = scan_target_for_path(
+            CollectionType::Shows,
+            root,
+            Path::new("/media/tv/Firefly/Season 2"),
+        );
+        assert_eq!(target, Some(ScanTarget::Show(show.clone())));
+
+        let target = scan_target_for_path(
+            CollectionType::Shows,
+            root,
+            Path::new("/media/tv/Firefly/tvshow.nfo"),
+        );
+        assert_eq!(target, Some(ScanTarget::Show(show)));
+    }
+}
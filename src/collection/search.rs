@@ -58,7 +58,7 @@ impl SearchIndex {
 
     pub async fn rebuild(
         &self,
-        collections: &HashMap<String, Collection>,
+        collections: &HashMap<String, Arc<Collection>>,
     ) -> Result<(), SearchError> {
         debug!("Rebuilding search index");
 
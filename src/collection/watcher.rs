@@ -0,0 +1,142 @@
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio::time::{Duration, Instant};
+use tracing::{debug, error, info, warn};
+
+use super::collection::{Collection, CollectionType};
+use super::repo::{CollectionRepo, CollectionRepoError};
+use super::scanner::{scan_target_for_path, ScanTarget};
+
+/// How long to keep collecting filesystem events before rescanning.
+/// A file being copied in generates a burst of events; we only want to
+/// rescan once the burst has settled.
+const DEBOUNCE: Duration = Duration::from_secs(2);
+
+enum WatchEvent {
+    Changed(String, ScanTarget),
+    /// The kernel event queue overflowed, we missed changes.
+    Overflow(String),
+}
+
+/// Watches the directories of all collections and patches changed movies,
+/// shows and seasons into the `CollectionRepo`.
+pub fn start_watchers(
+    repo: Arc<CollectionRepo>,
+    collections: &HashMap<String, Arc<Collection>>,
+) -> Result<(), CollectionRepoError> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let mut watchers = Vec::new();
+
+    for collection in collections.values() {
+        let watcher = watch_collection(
+            collection.id.clone(),
+            collection.collection_type,
+            collection.directory.clone(),
+            tx.clone(),
+        )?;
+        info!(
+            "Watching {} for changes (collection {})",
+            collection.directory.display(),
+            collection.name
+        );
+        watchers.push(watcher);
+    }
+
+    tokio::spawn(async move {
+        // The watchers stop when dropped, so keep them alive as long as the loop runs.
+        let _watchers = watchers;
+        watch_loop(repo, rx).await;
+    });
+
+    Ok(())
+}
+
+fn watch_collection(
+    collection_id: String,
+    collection_type: CollectionType,
+    root: PathBuf,
+    tx: mpsc::UnboundedSender<WatchEvent>,
+) -> Result<RecommendedWatcher, CollectionRepoError> {
+    let watch_root = root.clone();
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<Event>| {
+        let event = match res {
+            Ok(event) => event,
+            Err(e) => {
+                warn!("Filesystem watcher error for {}: {}", root.display(), e);
+                return;
+            }
+        };
+
+        if event.need_rescan() {
+            let _ = tx.send(WatchEvent::Overflow(collection_id.clone()));
+            return;
+        }
+
+        // Reads do not change anything.
+        if matches!(event.kind, EventKind::Access(_)) {
+            return;
+        }
+
+        for path in &event.paths {
+            if let Some(target) = scan_target_for_path(collection_type, &root, path) {
+                let _ = tx.send(WatchEvent::Changed(collection_id.clone(), target));
+            }
+        }
+    })?;
+
+    watcher.watch(&watch_root, RecursiveMode::Recursive)?;
+
+    Ok(watcher)
+}
+
+async fn watch_loop(repo: Arc<CollectionRepo>, mut rx: mpsc::UnboundedReceiver<WatchEvent>) {
+    while let Some(event) = rx.recv().await {
+        let mut changed: HashMap<String, HashSet<ScanTarget>> = HashMap::new();
+        let mut overflowed: HashSet<String> = HashSet::new();
+
+        let deadline = Instant::now() + DEBOUNCE;
+        let mut next = Some(event);
+        while let Some(event) = next {
+            match event {
+                WatchEvent::Changed(collection_id, target) => {
+                    changed.entry(collection_id).or_default().insert(target);
+                }
+                WatchEvent::Overflow(collection_id) => {
+                    overflowed.insert(collection_id);
+                }
+            }
+            next = tokio::time::timeout_at(deadline, rx.recv())
+                .await
+                .ok()
+                .flatten();
+        }
+
+        for collection_id in &overflowed {
+            warn!(
+                "Missed filesystem events for collection {}, rescanning it completely",
+                collection_id
+            );
+            repo.scan_collection(collection_id).await;
+        }
+
+        for (collection_id, targets) in changed {
+            if overflowed.contains(&collection_id) {
+                continue;
+            }
+            debug!(
+                "Rescanning {} changed items in collection {}",
+                targets.len(),
+                collection_id
+            );
+            repo.rescan_targets(&collection_id, targets.into_iter().collect())
+                .await;
+        }
+
+        if let Err(e) = repo.rebuild_search_index().await {
+            error!("Failed to rebuild search index: {}", e);
+        }
+    }
+}
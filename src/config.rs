@@ -7,15 +7,21 @@ pub struct Config {
     pub listen: ListenConfig,
     #[serde(default)]
     pub appdir: Option<String>,
+    /// Resized images are cached in `<cachedir>/images`.
     #[serde(default)]
     pub cachedir: Option<String>,
     #[serde(default)]
+    pub imagecache: ImageCacheConfig,
+    #[serde(default)]
     pub dbdir: Option<String>,
     #[serde(default)]
     pub database: DatabaseConfig,
+    /// Application log: `stdout`, `stderr`, `none` or a file.
     #[serde(default = "default_logfile")]
     pub logfile: String,
     #[serde(default)]
+    pub logging: LoggingConfig,
+    #[serde(default)]
     pub collections: Vec<CollectionConfig>,
     #[serde(default)]
     pub jellyfin: JellyfinConfig,
@@ -46,6 +52,56 @@ impl Default for ListenConfig {
     }
 }
 
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ImageCacheConfig {
+    /// Once the cache grows past this many megabytes, the least recently
+    /// used images are removed; null for no limit.
+    #[serde(alias = "max_size_mb", rename = "maxsizemb")]
+    #[serde(default = "default_image_cache_max_size_mb")]
+    pub max_size_mb: Option<u64>,
+    /// Cached images older than this many days are removed; null for never.
+    #[serde(alias = "max_age_days", rename = "maxagedays")]
+    #[serde(default = "default_image_cache_max_age_days")]
+    pub max_age_days: Option<u64>,
+    /// Images resized at the same time; null for one per CPU.
+    #[serde(alias = "resize_workers", rename = "resizeworkers")]
+    #[serde(default)]
+    pub resize_workers: Option<usize>,
+    /// Images waiting for or being resized; beyond this, image requests
+    /// get 503 Service Unavailable.
+    #[serde(alias = "resize_queue", rename = "resizequeue")]
+    #[serde(default = "default_image_resize_queue")]
+    pub resize_queue: usize,
+}
+
+impl ImageCacheConfig {
+    /// The maximum cache size in bytes.
+    pub fn max_size(&self) -> Option<u64> {
+        self.max_size_mb.map(|mb| mb * 1024 * 1024)
+    }
+
+    pub fn resize_workers(&self) -> usize {
+        self.resize_workers
+            .unwrap_or_else(|| {
+                std::thread::available_parallelism()
+                    .map(|n| n.get())
+                    .unwrap_or(1)
+            })
+            .max(1)
+    }
+}
+
+impl Default for ImageCacheConfig {
+    fn default() -> Self {
+        Self {
+            max_size_mb: default_image_cache_max_size_mb(),
+            max_age_days: default_image_cache_max_age_days(),
+            resize_workers: None,
+            resize_queue: default_image_resize_queue(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize, Default)]
 pub struct DatabaseConfig {
     #[serde(default)]
@@ -57,6 +113,79 @@ pub struct SqliteConfig {
     pub filename: String,
 }
 
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct LoggingConfig {
+    /// Routes whose request and response bodies are logged; the rule with
+    /// the longest matching prefix applies. Credentials are always redacted.
+    #[serde(default = "default_log_bodies")]
+    pub bodies: Vec<LogBodyRule>,
+    /// HTTP access log: `stdout`, `stderr` or a file. Without it, access
+    /// records go to the application log.
+    #[serde(alias = "access_log", rename = "accesslog")]
+    #[serde(default)]
+    pub access_log: Option<String>,
+    #[serde(alias = "access_log_format", rename = "accesslogformat")]
+    #[serde(default)]
+    pub access_log_format: AccessLogFormat,
+    /// Rotation of the log files.
+    #[serde(default)]
+    pub rotate: LogRotateConfig,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            bodies: default_log_bodies(),
+            access_log: None,
+            access_log_format: AccessLogFormat::default(),
+            rotate: LogRotateConfig::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AccessLogFormat {
+    /// Apache/nginx Combined Log Format, followed by the device and the
+    /// duration in milliseconds.
+    #[default]
+    Combined,
+    Json,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct LogRotateConfig {
+    /// Rotate a log file before it grows past this many bytes; 0 for never.
+    #[serde(alias = "max_size", rename = "maxsize")]
+    #[serde(default)]
+    pub max_size: u64,
+    /// Rotate log files at midnight (local time).
+    #[serde(default)]
+    pub daily: bool,
+    /// Number of rotated files to keep, as `<file>.1` (newest) to `<file>.<keep>`.
+    #[serde(default = "default_log_keep")]
+    pub keep: usize,
+}
+
+impl Default for LogRotateConfig {
+    fn default() -> Self {
+        Self {
+            max_size: 0,
+            daily: false,
+            keep: default_log_keep(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct LogBodyRule {
+    pub prefix: String,
+    /// Longer bodies are not logged, nor read into memory; 0 logs no body
+    /// for this prefix.
+    #[serde(alias = "max_size", rename = "maxsize")]
+    pub max_size: usize,
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct CollectionConfig {
     #[serde(default)]
@@ -69,6 +198,10 @@ pub struct CollectionConfig {
     pub baseurl: Option<String>,
     #[serde(default)]
     pub hlsserver: Option<String>,
+    /// Generate artwork for items without a primary image.
+    #[serde(alias = "placeholder_images", rename = "placeholderimages")]
+    #[serde(default)]
+    pub placeholder_images: bool,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -81,9 +214,26 @@ pub struct JellyfinConfig {
     pub server_name: String,
     #[serde(default)]
     pub autoregister: bool,
+    #[serde(default = "default_quickconnect")]
+    pub quickconnect: bool,
     #[serde(alias = "imagequalityposter", rename = "imagequalityposter")]
     #[serde(default)]
     pub image_quality_poster: Option<u32>,
+    /// Access tokens not used for this many days expire; null for never.
+    #[serde(alias = "token_idle_days", rename = "tokenidledays")]
+    #[serde(default = "default_token_idle_days")]
+    pub token_idle_days: Option<u32>,
+    /// Access tokens expire this many days after login; null for never.
+    #[serde(alias = "token_max_age_days", rename = "tokenmaxagedays")]
+    #[serde(default)]
+    pub token_max_age_days: Option<u32>,
+    /// Failed logins after which a user is disabled; null for never.
+    #[serde(
+        alias = "login_attempts_before_lockout",
+        rename = "loginattemptsbeforelockout"
+    )]
+    #[serde(default = "default_login_attempts_before_lockout")]
+    pub login_attempts_before_lockout: Option<u32>,
 }
 
 impl Default for JellyfinConfig {
@@ -92,7 +242,11 @@ impl Default for JellyfinConfig {
             server_id: None,
             server_name: default_server_name(),
             autoregister: false,
+            quickconnect: default_quickconnect(),
             image_quality_poster: None,
+            token_idle_days: default_token_idle_days(),
+            token_max_age_days: None,
+            login_attempts_before_lockout: default_login_attempts_before_lockout(),
         }
     }
 }
@@ -105,10 +259,45 @@ fn default_logfile() -> String {
     "stdout".to_string()
 }
 
+fn default_log_bodies() -> Vec<LogBodyRule> {
+    vec![LogBodyRule {
+        prefix: "/".to_string(),
+        max_size: 4096,
+    }]
+}
+
+fn default_log_keep() -> usize {
+    7
+}
+
+fn default_image_cache_max_size_mb() -> Option<u64> {
+    Some(1024)
+}
+
+fn default_image_cache_max_age_days() -> Option<u64> {
+    Some(30)
+}
+
+fn default_image_resize_queue() -> usize {
+    256
+}
+
 fn default_server_name() -> String {
     "Jellofin".to_string()
 }
 
+fn default_quickconnect() -> bool {
+    true
+}
+
+fn default_token_idle_days() -> Option<u32> {
+    Some(90)
+}
+
+fn default_login_attempts_before_lockout() -> Option<u32> {
+    Some(5)
+}
+
 impl Config {
     pub fn from_file(path: &str) -> Result<Self, ConfigError> {
         let content = std::fs::read_to_string(path)
@@ -132,6 +321,13 @@ impl Config {
 
         None
     }
+
+    pub fn get_image_cache_dir(&self) -> PathBuf {
+        match &self.cachedir {
+            Some(cachedir) => PathBuf::from(cachedir).join("images"),
+            None => PathBuf::from("./cache/images"),
+        }
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -9,6 +9,36 @@ pub struct User {
     pub created: Option<String>,
     pub lastlogin: Option<String>,
     pub lastused: Option<String>,
+    pub is_admin: bool,
+    pub enable_all_folders: bool,
+    /// Comma-separated IDs of the collections the user may access when
+    /// `enable_all_folders` is false.
+    pub enabled_folders: String,
+    /// Highest age rating the user may see; None for no limit.
+    pub max_parental_rating: Option<i32>,
+    /// Comma-separated item kinds (`Movie`, `Series`) hidden when unrated.
+    pub block_unrated_items: String,
+    /// Disabled users cannot log in or use their tokens.
+    pub is_disabled: bool,
+    /// Failed logins since the last successful one.
+    pub invalid_login_attempts: i32,
+}
+
+impl User {
+    pub fn enabled_folder_ids(&self) -> Vec<String> {
+        split_list(&self.enabled_folders)
+    }
+
+    pub fn block_unrated_item_kinds(&self) -> Vec<String> {
+        split_list(&self.block_unrated_items)
+    }
+}
+
+fn split_list(list: &str) -> Vec<String> {
+    list.split(',')
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect()
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
@@ -24,6 +54,16 @@ pub struct AccessToken {
     pub lastused: Option<DateTime<Utc>>,
 }
 
+/// A long-lived key for an application, created by an administrator.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ApiKey {
+    pub token: String,
+    /// Name of the application using the key.
+    pub name: String,
+    pub created: Option<String>,
+    pub lastused: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
 pub struct Item {
     pub id: String,
@@ -35,6 +75,10 @@ pub struct Item {
     pub nfotime: i64,
     pub firstvideo: i64,
     pub lastvideo: i64,
+    pub collectionid: Option<String>,
+    pub dirtime: i64,
+    /// Scanned movie or show, serialized as JSON.
+    pub data: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
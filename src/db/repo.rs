@@ -6,7 +6,18 @@ use super::model::*;
 pub trait UserRepo: Send + Sync {
     async fn get_user(&self, username: &str) -> DbResult<User>;
     async fn get_user_by_id(&self, id: &str) -> DbResult<User>;
+    async fn list_users(&self) -> DbResult<Vec<User>>;
     async fn upsert_user(&self, user: &User) -> DbResult<()>;
+    /// Add a new user. If there are no users yet it becomes an
+    /// administrator; returns the user as stored.
+    async fn create_user(&self, user: &User) -> DbResult<User>;
+    /// Count a failed login. With `lockout`, a user that is not an
+    /// administrator is disabled at that many failures. Returns the user as
+    /// updated.
+    async fn record_invalid_login(&self, id: &str, lockout: Option<u32>) -> DbResult<User>;
+    async fn reset_invalid_logins(&self, id: &str) -> DbResult<()>;
+    /// Delete a user with its access tokens, user data and playlists.
+    async fn delete_user(&self, id: &str) -> DbResult<()>;
 }
 
 #[async_trait]
@@ -15,12 +26,24 @@ pub trait AccessTokenRepo: Send + Sync {
     async fn list_tokens_by_user(&self, user_id: &str) -> DbResult<Vec<AccessToken>>;
     async fn upsert_token(&self, token: &AccessToken) -> DbResult<()>;
     async fn delete_token(&self, token: &str) -> DbResult<()>;
+    /// Delete all tokens of a user, except `keep` if given.
+    async fn delete_tokens_by_user(&self, user_id: &str, keep: Option<&str>) -> DbResult<()>;
+}
+
+#[async_trait]
+pub trait ApiKeyRepo: Send + Sync {
+    async fn get_api_key(&self, token: &str) -> DbResult<ApiKey>;
+    async fn list_api_keys(&self) -> DbResult<Vec<ApiKey>>;
+    async fn upsert_api_key(&self, key: &ApiKey) -> DbResult<()>;
+    async fn delete_api_key(&self, token: &str) -> DbResult<()>;
 }
 
 #[async_trait]
 pub trait ItemRepo: Send + Sync {
     async fn get_item(&self, id: &str) -> DbResult<Item>;
+    async fn list_items_by_collection(&self, collection_id: &str) -> DbResult<Vec<Item>>;
     async fn upsert_item(&self, item: &Item) -> DbResult<()>;
+    async fn upsert_items(&self, items: &[Item]) -> DbResult<()>;
     async fn delete_item(&self, id: &str) -> DbResult<()>;
 }
 
@@ -56,7 +79,7 @@ pub trait PlaylistRepo: Send + Sync {
 }
 
 pub trait Repository:
-    UserRepo + AccessTokenRepo + ItemRepo + UserDataRepo + PlaylistRepo + Send + Sync
+    UserRepo + AccessTokenRepo + ApiKeyRepo + ItemRepo + UserDataRepo + PlaylistRepo + Send + Sync
 {
     fn close(&self);
 }
@@ -5,7 +5,14 @@ CREATE TABLE IF NOT EXISTS users (
     password TEXT NOT NULL,
     created DATETIME,
     lastlogin DATETIME,
-    lastused DATETIME
+    lastused DATETIME,
+    is_admin BOOLEAN NOT NULL DEFAULT 0,
+    enable_all_folders BOOLEAN NOT NULL DEFAULT 1,
+    enabled_folders TEXT NOT NULL DEFAULT '',
+    max_parental_rating INTEGER,
+    block_unrated_items TEXT NOT NULL DEFAULT '',
+    is_disabled BOOLEAN NOT NULL DEFAULT 0,
+    invalid_login_attempts INTEGER NOT NULL DEFAULT 0
 );
 
 CREATE UNIQUE INDEX IF NOT EXISTS users_name_idx ON users (username);
@@ -25,6 +32,14 @@ CREATE TABLE IF NOT EXISTS accesstokens (
 
 CREATE UNIQUE INDEX IF NOT EXISTS accesstokens_idx ON accesstokens (userid, token);
 
+-- API keys of applications; they act as an administrator
+CREATE TABLE IF NOT EXISTS apikeys (
+    token TEXT NOT NULL PRIMARY KEY,
+    name TEXT NOT NULL,
+    created DATETIME,
+    lastused DATETIME
+);
+
 -- Items table (matching Go schema)
 CREATE TABLE IF NOT EXISTS items (
     id TEXT NOT NULL PRIMARY KEY,
@@ -35,7 +50,10 @@ CREATE TABLE IF NOT EXISTS items (
     rating REAL,
     nfotime INTEGER NOT NULL,
     firstvideo INTEGER NOT NULL,
-    lastvideo INTEGER NOT NULL
+    lastvideo INTEGER NOT NULL,
+    collectionid TEXT,
+    dirtime INTEGER NOT NULL DEFAULT 0,
+    data TEXT
 );
 
 CREATE INDEX IF NOT EXISTS items_name_idx ON items (name);
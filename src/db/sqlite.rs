@@ -43,6 +43,78 @@ impl SqliteRepository {
     async fn init_schema(&self) -> DbResult<()> {
         let schema = include_str!("schema.sql");
         sqlx::query(schema).execute(&self.pool).await?;
+
+        // Columns added after the initial schema; CREATE TABLE IF NOT EXISTS
+        // does not add them to existing databases.
+        self.add_column_if_missing("items", "collectionid", "TEXT")
+            .await?;
+        self.add_column_if_missing("items", "dirtime", "INTEGER NOT NULL DEFAULT 0")
+            .await?;
+        self.add_column_if_missing("items", "data", "TEXT").await?;
+        sqlx::query("CREATE INDEX IF NOT EXISTS items_collectionid_idx ON items (collectionid)")
+            .execute(&self.pool)
+            .await?;
+        self.add_column_if_missing("users", "is_admin", "BOOLEAN NOT NULL DEFAULT 0")
+            .await?;
+        self.add_column_if_missing("users", "enable_all_folders", "BOOLEAN NOT NULL DEFAULT 1")
+            .await?;
+        self.add_column_if_missing("users", "enabled_folders", "TEXT NOT NULL DEFAULT ''")
+            .await?;
+        self.add_column_if_missing("users", "max_parental_rating", "INTEGER")
+            .await?;
+        self.add_column_if_missing("users", "block_unrated_items", "TEXT NOT NULL DEFAULT ''")
+            .await?;
+        self.add_column_if_missing("users", "is_disabled", "BOOLEAN NOT NULL DEFAULT 0")
+            .await?;
+        self.add_column_if_missing(
+            "users",
+            "invalid_login_attempts",
+            "INTEGER NOT NULL DEFAULT 0",
+        )
+        .await?;
+
+        // Databases from before administrators existed: the first user becomes one.
+        sqlx::query(
+            "UPDATE users SET is_admin = 1
+             WHERE id = (SELECT id FROM users ORDER BY created, rowid LIMIT 1)
+             AND NOT EXISTS (SELECT 1 FROM users WHERE is_admin)",
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Tokens from before their use was recorded count as used now,
+        // so the idle timeout does not log everyone out after an upgrade.
+        sqlx::query("UPDATE accesstokens SET lastused = ? WHERE lastused IS NULL")
+            .bind(Utc::now().to_rfc3339())
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn add_column_if_missing(
+        &self,
+        table: &str,
+        column: &str,
+        definition: &str,
+    ) -> DbResult<()> {
+        let columns = sqlx::query_as::<_, (String,)>(&format!(
+            "SELECT name FROM pragma_table_info('{}')",
+            table
+        ))
+        .fetch_all(&self.pool)
+        .await?;
+
+        if columns.iter().any(|(name,)| name == column) {
+            return Ok(());
+        }
+
+        info!("Adding column {}.{} to database", table, column);
+        sqlx::query(&format!(
+            "ALTER TABLE {} ADD COLUMN {} {}",
+            table, column, definition
+        ))
+        .execute(&self.pool)
+        .await?;
         Ok(())
     }
 
@@ -68,8 +140,8 @@ impl SqliteRepository {
         for token in cache.values() {
             sqlx::query(
                 "INSERT OR REPLACE INTO accesstokens 
-                (token, userid, deviceid, devicename, applicationname, applicationversion, created)
-                VALUES (?, ?, ?, ?, ?, ?, ?)",
+                (token, userid, deviceid, devicename, applicationname, applicationversion, remoteaddress, created, lastused)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
             )
             .bind(&token.token)
             .bind(&token.userid)
@@ -77,7 +149,9 @@ impl SqliteRepository {
             .bind(&token.devicename)
             .bind(&token.applicationname)
             .bind(&token.applicationversion)
+            .bind(&token.remoteaddress)
             .bind(token.created.as_ref().map(|dt| dt.to_rfc3339()))
+            .bind(token.lastused.as_ref().map(|dt| dt.to_rfc3339()))
             .execute(&self.pool)
             .await?;
         }
@@ -88,7 +162,7 @@ impl SqliteRepository {
 #[async_trait]
 impl UserRepo for SqliteRepository {
     async fn get_user(&self, username: &str) -> DbResult<User> {
-        sqlx::query_as::<_, User>("SELECT id, username, password, created, lastlogin, lastused FROM users WHERE username = ?")
+        sqlx::query_as::<_, User>("SELECT id, username, password, created, lastlogin, lastused, is_admin, enable_all_folders, enabled_folders, max_parental_rating, block_unrated_items, is_disabled, invalid_login_attempts FROM users WHERE username = ?")
             .bind(username)
             .fetch_one(&self.pool)
             .await
@@ -100,7 +174,7 @@ impl UserRepo for SqliteRepository {
 
     async fn get_user_by_id(&self, id: &str) -> DbResult<User> {
         sqlx::query_as::<_, User>(
-            "SELECT id, username, password, created, lastlogin, lastused FROM users WHERE id = ?",
+            "SELECT id, username, password, created, lastlogin, lastused, is_admin, enable_all_folders, enabled_folders, max_parental_rating, block_unrated_items, is_disabled, invalid_login_attempts FROM users WHERE id = ?",
         )
         .bind(id)
         .fetch_one(&self.pool)
@@ -111,18 +185,129 @@ impl UserRepo for SqliteRepository {
         })
     }
 
+    async fn list_users(&self) -> DbResult<Vec<User>> {
+        let users = sqlx::query_as::<_, User>(
+            "SELECT id, username, password, created, lastlogin, lastused, is_admin, enable_all_folders, enabled_folders, max_parental_rating, block_unrated_items, is_disabled, invalid_login_attempts FROM users ORDER BY username",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(users)
+    }
+
     async fn upsert_user(&self, user: &User) -> DbResult<()> {
-        sqlx::query("INSERT OR REPLACE INTO users (id, username, password, created, lastlogin, lastused) VALUES (?, ?, ?, ?, ?, ?)")
+        sqlx::query("INSERT OR REPLACE INTO users (id, username, password, created, lastlogin, lastused, is_admin, enable_all_folders, enabled_folders, max_parental_rating, block_unrated_items, is_disabled, invalid_login_attempts) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)")
             .bind(&user.id)
             .bind(&user.username)
             .bind(&user.password)
             .bind(&user.created)
             .bind(&user.lastlogin)
             .bind(&user.lastused)
+            .bind(user.is_admin)
+            .bind(user.enable_all_folders)
+            .bind(&user.enabled_folders)
+            .bind(user.max_parental_rating)
+            .bind(&user.block_unrated_items)
+            .bind(user.is_disabled)
+            .bind(user.invalid_login_attempts)
             .execute(&self.pool)
             .await?;
         Ok(())
     }
+
+    async fn create_user(&self, user: &User) -> DbResult<User> {
+        // One statement, so that two users created at the same time cannot
+        // both become the first.
+        let is_admin: Option<bool> = sqlx::query_scalar(
+            "INSERT INTO users (id, username, password, created, lastlogin, lastused, is_admin, enable_all_folders, enabled_folders, max_parental_rating, block_unrated_items, is_disabled, invalid_login_attempts)
+             SELECT ?, ?, ?, ?, ?, ?, ? OR NOT EXISTS (SELECT 1 FROM users), ?, ?, ?, ?, ?, ?
+             WHERE NOT EXISTS (SELECT 1 FROM users WHERE id = ? OR username = ?)
+             RETURNING is_admin",
+        )
+        .bind(&user.id)
+        .bind(&user.username)
+        .bind(&user.password)
+        .bind(&user.created)
+        .bind(&user.lastlogin)
+        .bind(&user.lastused)
+        .bind(user.is_admin)
+        .bind(user.enable_all_folders)
+        .bind(&user.enabled_folders)
+        .bind(user.max_parental_rating)
+        .bind(&user.block_unrated_items)
+        .bind(user.is_disabled)
+        .bind(user.invalid_login_attempts)
+        .bind(&user.id)
+        .bind(&user.username)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        match is_admin {
+            Some(is_admin) => Ok(User {
+                is_admin,
+                ..user.clone()
+            }),
+            None => Err(DbError::AlreadyExists(format!(
+                "User already exists: {}",
+                user.username
+            ))),
+        }
+    }
+
+    async fn record_invalid_login(&self, id: &str, lockout: Option<u32>) -> DbResult<User> {
+        // Counted in the database, so that parallel failures are not lost.
+        sqlx::query_as::<_, User>(
+            "UPDATE users SET invalid_login_attempts = invalid_login_attempts + 1,
+                 is_disabled = is_disabled OR (NOT is_admin AND ?1 IS NOT NULL AND invalid_login_attempts + 1 >= ?1)
+             WHERE id = ?2
+             RETURNING id, username, password, created, lastlogin, lastused, is_admin, enable_all_folders, enabled_folders, max_parental_rating, block_unrated_items, is_disabled, invalid_login_attempts",
+        )
+        .bind(lockout)
+        .bind(id)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::RowNotFound => DbError::NotFound(format!("User not found: {}", id)),
+            _ => DbError::Sqlx(e),
+        })
+    }
+
+    async fn reset_invalid_logins(&self, id: &str) -> DbResult<()> {
+        sqlx::query("UPDATE users SET invalid_login_attempts = 0 WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_user(&self, id: &str) -> DbResult<()> {
+        self.delete_tokens_by_user(id, None).await?;
+        self.userdata_cache
+            .write()
+            .await
+            .retain(|(user_id, _), _| user_id != id);
+
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("DELETE FROM playstate WHERE userid = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(
+            "DELETE FROM playlist_item WHERE playlistid IN (SELECT id FROM playlist WHERE userid = ?)",
+        )
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query("DELETE FROM playlist WHERE userid = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM users WHERE id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(())
+    }
 }
 
 #[async_trait]
@@ -135,8 +320,8 @@ impl AccessTokenRepo for SqliteRepository {
             }
         }
 
-        let result = sqlx::query_as::<_, (String, String, Option<String>, Option<String>, Option<String>, Option<String>, Option<String>)>(
-            "SELECT token, userid, deviceid, devicename, applicationname, applicationversion, created 
+        let access_token = sqlx::query_as::<_, TokenRow>(
+            "SELECT token, userid, deviceid, devicename, applicationname, applicationversion, remoteaddress, created, lastused 
              FROM accesstokens WHERE token = ?",
         )
         .bind(token)
@@ -145,23 +330,8 @@ impl AccessTokenRepo for SqliteRepository {
         .map_err(|e| match e {
             sqlx::Error::RowNotFound => DbError::NotFound(format!("Token not found: {}", token)),
             _ => DbError::Sqlx(e),
-        })?;
-
-        let access_token = AccessToken {
-            token: result.0,
-            userid: result.1,
-            deviceid: result.2,
-            devicename: result.3,
-            applicationname: result.4,
-            applicationversion: result.5,
-            remoteaddress: None,
-            created: result.6.and_then(|s| {
-                DateTime::parse_from_rfc3339(&s)
-                    .ok()
-                    .map(|dt| dt.with_timezone(&Utc))
-            }),
-            lastused: None,
-        };
+        })
+        .map(token_from_row)?;
 
         let mut cache = self.token_cache.write().await;
         cache.insert(token.to_string(), access_token.clone());
@@ -170,32 +340,25 @@ impl AccessTokenRepo for SqliteRepository {
     }
 
     async fn list_tokens_by_user(&self, user_id: &str) -> DbResult<Vec<AccessToken>> {
-        let results = sqlx::query_as::<_, (String, String, Option<String>, Option<String>, Option<String>, Option<String>, Option<String>)>(
-            "SELECT token, userid, deviceid, devicename, applicationname, applicationversion, created 
+        let mut tokens: Vec<AccessToken> = sqlx::query_as::<_, TokenRow>(
+            "SELECT token, userid, deviceid, devicename, applicationname, applicationversion, remoteaddress, created, lastused 
              FROM accesstokens WHERE userid = ?",
         )
         .bind(user_id)
         .fetch_all(&self.pool)
-        .await?;
+        .await?
+        .into_iter()
+        .map(token_from_row)
+        .collect();
 
-        let tokens = results
-            .into_iter()
-            .map(|r| AccessToken {
-                token: r.0,
-                userid: r.1,
-                deviceid: r.2,
-                devicename: r.3,
-                applicationname: r.4,
-                applicationversion: r.5,
-                remoteaddress: None,
-                created: r.6.and_then(|s| {
-                    DateTime::parse_from_rfc3339(&s)
-                        .ok()
-                        .map(|dt| dt.with_timezone(&Utc))
-                }),
-                lastused: None,
-            })
-            .collect();
+        // Tokens that have not been flushed yet, or with newer activity.
+        let cache = self.token_cache.read().await;
+        for cached in cache.values().filter(|t| t.userid == user_id) {
+            match tokens.iter_mut().find(|t| t.token == cached.token) {
+                Some(t) => *t = cached.clone(),
+                None => tokens.push(cached.clone()),
+            }
+        }
 
         Ok(tokens)
     }
@@ -218,13 +381,106 @@ impl AccessTokenRepo for SqliteRepository {
             .await?;
         Ok(())
     }
+
+    async fn delete_tokens_by_user(&self, user_id: &str, keep: Option<&str>) -> DbResult<()> {
+        {
+            let mut cache = self.token_cache.write().await;
+            cache.retain(|token, t| t.userid != user_id || Some(token.as_str()) == keep);
+        }
+
+        sqlx::query("DELETE FROM accesstokens WHERE userid = ? AND token != ?")
+            .bind(user_id)
+            .bind(keep.unwrap_or_default())
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ApiKeyRepo for SqliteRepository {
+    async fn get_api_key(&self, token: &str) -> DbResult<ApiKey> {
+        sqlx::query_as::<_, ApiKey>(
+            "SELECT token, name, created, lastused FROM apikeys WHERE token = ?",
+        )
+        .bind(token)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::RowNotFound => DbError::NotFound("API key not found".to_string()),
+            _ => DbError::Sqlx(e),
+        })
+    }
+
+    async fn list_api_keys(&self) -> DbResult<Vec<ApiKey>> {
+        let keys = sqlx::query_as::<_, ApiKey>(
+            "SELECT token, name, created, lastused FROM apikeys ORDER BY created",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(keys)
+    }
+
+    async fn upsert_api_key(&self, key: &ApiKey) -> DbResult<()> {
+        sqlx::query(
+            "INSERT OR REPLACE INTO apikeys (token, name, created, lastused) VALUES (?, ?, ?, ?)",
+        )
+        .bind(&key.token)
+        .bind(&key.name)
+        .bind(&key.created)
+        .bind(&key.lastused)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn delete_api_key(&self, token: &str) -> DbResult<()> {
+        sqlx::query("DELETE FROM apikeys WHERE token = ?")
+            .bind(token)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
+
+type TokenRow = (
+    String,
+    String,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+);
+
+fn token_from_row(row: TokenRow) -> AccessToken {
+    let parse_time = |s: Option<String>| {
+        s.and_then(|s| {
+            DateTime::parse_from_rfc3339(&s)
+                .ok()
+                .map(|dt| dt.with_timezone(&Utc))
+        })
+    };
+    AccessToken {
+        token: row.0,
+        userid: row.1,
+        deviceid: row.2,
+        devicename: row.3,
+        applicationname: row.4,
+        applicationversion: row.5,
+        remoteaddress: row.6,
+        created: parse_time(row.7),
+        lastused: parse_time(row.8),
+    }
 }
 
 #[async_trait]
 impl ItemRepo for SqliteRepository {
     async fn get_item(&self, id: &str) -> DbResult<Item> {
-        let result = sqlx::query_as::<_, (String, String, Option<i32>, Option<i32>, String, Option<f32>, i64, i64, i64)>(
-            "SELECT id, name, votes, year, genre, rating, nfotime, firstvideo, lastvideo FROM items WHERE id = ?",
+        let result = sqlx::query_as::<_, ItemRow>(
+            "SELECT id, name, votes, year, genre, rating, nfotime, firstvideo, lastvideo, collectionid, dirtime, data FROM items WHERE id = ?",
         )
         .bind(id)
         .fetch_one(&self.pool)
@@ -234,36 +490,31 @@ impl ItemRepo for SqliteRepository {
             _ => DbError::Sqlx(e),
         })?;
 
-        Ok(Item {
-            id: result.0,
-            name: result.1,
-            votes: result.2,
-            year: result.3,
-            genre: result.4,
-            rating: result.5,
-            nfotime: result.6,
-            firstvideo: result.7,
-            lastvideo: result.8,
-        })
+        Ok(item_from_row(result))
     }
 
-    async fn upsert_item(&self, item: &Item) -> DbResult<()> {
-        sqlx::query(
-            "INSERT OR REPLACE INTO items 
-            (id, name, votes, year, genre, rating, nfotime, firstvideo, lastvideo)
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+    async fn list_items_by_collection(&self, collection_id: &str) -> DbResult<Vec<Item>> {
+        let results = sqlx::query_as::<_, ItemRow>(
+            "SELECT id, name, votes, year, genre, rating, nfotime, firstvideo, lastvideo, collectionid, dirtime, data FROM items WHERE collectionid = ?",
         )
-        .bind(&item.id)
-        .bind(&item.name)
-        .bind(item.votes)
-        .bind(item.year)
-        .bind(&item.genre)
-        .bind(item.rating)
-        .bind(item.nfotime)
-        .bind(item.firstvideo)
-        .bind(item.lastvideo)
-        .execute(&self.pool)
+        .bind(collection_id)
+        .fetch_all(&self.pool)
         .await?;
+
+        Ok(results.into_iter().map(item_from_row).collect())
+    }
+
+    async fn upsert_item(&self, item: &Item) -> DbResult<()> {
+        upsert_item_query(item).execute(&self.pool).await?;
+        Ok(())
+    }
+
+    async fn upsert_items(&self, items: &[Item]) -> DbResult<()> {
+        let mut tx = self.pool.begin().await?;
+        for item in items {
+            upsert_item_query(item).execute(&mut *tx).await?;
+        }
+        tx.commit().await?;
         Ok(())
     }
 
@@ -276,6 +527,60 @@ impl ItemRepo for SqliteRepository {
     }
 }
 
+type ItemRow = (
+    String,
+    String,
+    Option<i32>,
+    Option<i32>,
+    String,
+    Option<f32>,
+    i64,
+    i64,
+    i64,
+    Option<String>,
+    i64,
+    Option<String>,
+);
+
+fn item_from_row(row: ItemRow) -> Item {
+    Item {
+        id: row.0,
+        name: row.1,
+        votes: row.2,
+        year: row.3,
+        genre: row.4,
+        rating: row.5,
+        nfotime: row.6,
+        firstvideo: row.7,
+        lastvideo: row.8,
+        collectionid: row.9,
+        dirtime: row.10,
+        data: row.11,
+    }
+}
+
+fn upsert_item_query(
+    item: &Item,
+) -> sqlx::query::Query<'_, sqlx::Sqlite, sqlx::sqlite::SqliteArguments<'_>> {
+    sqlx::query(
+        "INSERT OR REPLACE INTO items
+        (id, name, votes, year, genre, rating, nfotime, firstvideo, lastvideo, collectionid, dirtime, data)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(&item.id)
+    .bind(&item.name)
+    .bind(item.votes)
+    .bind(item.year)
+    .bind(&item.genre)
+    .bind(item.rating)
+    .bind(item.nfotime)
+    .bind(item.firstvideo)
+    .bind(item.lastvideo)
+    .bind(&item.collectionid)
+    .bind(item.dirtime)
+    .bind(&item.data)
+}
+
 #[async_trait]
 impl UserDataRepo for SqliteRepository {
     async fn get_user_data(&self, user_id: &str, item_id: &str) -> DbResult<UserData> {
@@ -574,3 +879,116 @@ impl PlaylistRepo for SqliteRepository {
 impl Repository for SqliteRepository {
     fn close(&self) {}
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn test_repo() -> (SqliteRepository, std::path::PathBuf) {
+        let path = std::env::temp_dir().join(format!(
+            "jellofin-test-{}.db",
+            uuid::Uuid::new_v4().simple()
+        ));
+        let repo = SqliteRepository::new(path.to_str().unwrap()).await.unwrap();
+        (repo, path)
+    }
+
+    fn test_user(id: &str) -> User {
+        User {
+            id: id.to_string(),
+            username: id.to_string(),
+            password: String::new(),
+            created: None,
+            lastlogin: None,
+            lastused: None,
+            is_admin: false,
+            enable_all_folders: true,
+            enabled_folders: String::new(),
+            max_parental_rating: None,
+            block_unrated_items: String::new(),
+            is_disabled: false,
+            invalid_login_attempts: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_invalid_login_lockout() {
+        let (repo, path) = test_repo().await;
+
+        // The first user becomes administrator.
+        let admin = repo.create_user(&test_user("admin")).await.unwrap();
+        assert!(admin.is_admin);
+        let user = repo.create_user(&test_user("user")).await.unwrap();
+        assert!(!user.is_admin);
+
+        for attempt in 1..3 {
+            let user = repo.record_invalid_login("user", Some(3)).await.unwrap();
+            assert_eq!(user.invalid_login_attempts, attempt);
+            assert!(!user.is_disabled);
+        }
+        let user = repo.record_invalid_login("user", Some(3)).await.unwrap();
+        assert_eq!(user.invalid_login_attempts, 3);
+        assert!(user.is_disabled);
+
+        for _ in 0..5 {
+            let admin = repo.record_invalid_login("admin", Some(3)).await.unwrap();
+            assert!(!admin.is_disabled);
+        }
+        let admin = repo.get_user_by_id("admin").await.unwrap();
+        assert_eq!(admin.invalid_login_attempts, 5);
+
+        // Without a limit users are never disabled.
+        let other = repo.create_user(&test_user("other")).await.unwrap();
+        for _ in 0..5 {
+            let other = repo.record_invalid_login(&other.id, None).await.unwrap();
+            assert!(!other.is_disabled);
+        }
+
+        repo.reset_invalid_logins("admin").await.unwrap();
+        let admin = repo.get_user_by_id("admin").await.unwrap();
+        assert_eq!(admin.invalid_login_attempts, 0);
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test]
+    async fn test_tokens_without_lastused() {
+        let (repo, path) = test_repo().await;
+        sqlx::query("INSERT INTO accesstokens (userid, token, created) VALUES (?, ?, ?)")
+            .bind("user")
+            .bind("token")
+            .bind("2020-01-01T00:00:00+00:00")
+            .execute(&repo.pool)
+            .await
+            .unwrap();
+
+        let before = Utc::now();
+        repo.init_schema().await.unwrap();
+        let token = repo.get_token("token").await.unwrap();
+        assert!(token
+            .lastused
+            .is_some_and(|lastused| lastused >= before - chrono::Duration::seconds(1)));
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test]
+    async fn test_parallel_invalid_logins_are_counted() {
+        let (repo, path) = test_repo().await;
+        repo.create_user(&test_user("user")).await.unwrap();
+
+        let repo = Arc::new(repo);
+        let mut attempts = tokio::task::JoinSet::new();
+        for _ in 0..10 {
+            let repo = repo.clone();
+            attempts.spawn(async move { repo.record_invalid_login("user", None).await });
+        }
+        while let Some(result) = attempts.join_next().await {
+            result.unwrap().unwrap();
+        }
+        let user = repo.get_user_by_id("user").await.unwrap();
+        assert_eq!(user.invalid_login_attempts, 10);
+
+        let _ = std::fs::remove_file(path);
+    }
+}
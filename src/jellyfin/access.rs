@@ -0,0 +1,185 @@
+use axum::http::Request;
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+
+use crate::collection::{rating_age, Collection, CollectionRepo, Item};
+use crate::db::{SqliteRepository, User, UserRepo};
+use crate::server::AppState;
+
+/// What a user may see: the collections it can access and its parental
+/// rating limit. Inserted into the request by the auth middleware;
+/// anonymous requests get access to nothing.
+#[derive(Debug, Clone, Default)]
+pub struct LibraryAccess {
+    /// None means all collections.
+    collections: Option<HashSet<String>>,
+    /// Highest allowed age, see `rating_age`.
+    max_parental_rating: Option<u32>,
+    /// Item kinds (`Movie`, `Series`) that are hidden when unrated.
+    block_unrated_items: Vec<String>,
+}
+
+impl LibraryAccess {
+    pub fn all() -> Self {
+        LibraryAccess::default()
+    }
+
+    pub fn none() -> Self {
+        LibraryAccess {
+            collections: Some(HashSet::new()),
+            ..Default::default()
+        }
+    }
+
+    pub fn for_user(user: &User) -> Self {
+        LibraryAccess {
+            collections: (!user.enable_all_folders)
+                .then(|| user.enabled_folder_ids().into_iter().collect()),
+            max_parental_rating: user.max_parental_rating.map(|age| age.max(0) as u32),
+            block_unrated_items: user.block_unrated_item_kinds(),
+        }
+    }
+
+    /// What may be seen without logging in: only what every user can see.
+    /// Without users there is nothing to protect.
+    pub fn public(users: &[User]) -> Self {
+        let mut access = LibraryAccess::all();
+        for user in users {
+            let user_access = LibraryAccess::for_user(user);
+            access.collections = match (access.collections, user_access.collections) {
+                (None, ids) | (ids, None) => ids,
+                (Some(a), Some(b)) => Some(a.intersection(&b).cloned().collect()),
+            };
+            access.max_parental_rating =
+                match (access.max_parental_rating, user_access.max_parental_rating) {
+                    (Some(a), Some(b)) => Some(a.min(b)),
+                    (a, b) => a.or(b),
+                };
+            for kind in user_access.block_unrated_items {
+                if !access.block_unrated_items.contains(&kind) {
+                    access.block_unrated_items.push(kind);
+                }
+            }
+        }
+        access
+    }
+
+    pub fn allows(&self, collection_id: &str) -> bool {
+        self.collections
+            .as_ref()
+            .is_none_or(|ids| ids.contains(collection_id))
+    }
+
+    pub fn has_parental_limit(&self) -> bool {
+        self.max_parental_rating.is_some() || !self.block_unrated_items.is_empty()
+    }
+
+    /// Whether an item of `item_type` with this rating passes the parental
+    /// rating limit. Seasons and episodes count as series.
+    pub fn allows_rating(&self, item_type: &str, rating: Option<&str>) -> bool {
+        match rating.and_then(rating_age) {
+            Some(age) => self.max_parental_rating.is_none_or(|max| age <= max),
+            None => {
+                let kind = match item_type {
+                    "Season" | "Episode" => "Series",
+                    other => other,
+                };
+                !self.block_unrated_items.iter().any(|k| k == kind)
+            }
+        }
+    }
+
+    pub async fn list_collections(&self, repo: &CollectionRepo) -> Vec<Arc<Collection>> {
+        repo.list_collections()
+            .await
+            .into_iter()
+            .filter(|c| self.allows(&c.id))
+            .collect()
+    }
+
+    pub async fn get_collection(&self, repo: &CollectionRepo, id: &str) -> Option<Arc<Collection>> {
+        repo.get_collection(id).await.filter(|c| self.allows(&c.id))
+    }
+
+    /// Look up an item; items in collections the user may not see, or rated
+    /// above its limit, do not exist.
+    pub fn get_item(&self, repo: &CollectionRepo, id: &str) -> Option<(String, Item)> {
+        let (collection_id, item) = repo.get_item(id)?;
+        if !self.allows(&collection_id) {
+            return None;
+        }
+        if self.has_parental_limit() {
+            let item_type = match &item {
+                Item::Movie(_) => "Movie",
+                Item::Show(_) => "Series",
+                Item::Season(_) => "Season",
+                Item::Episode(_) => "Episode",
+            };
+            if !self.allows_rating(item_type, repo.get_rating(id).as_deref()) {
+                return None;
+            }
+        }
+        Some((collection_id, item))
+    }
+}
+
+pub fn get_library_access<B>(req: &Request<B>) -> LibraryAccess {
+    req.extensions()
+        .get::<LibraryAccess>()
+        .cloned()
+        .unwrap_or_else(LibraryAccess::none)
+}
+
+/// The access of requests without credentials, for images and the notflix
+/// API, which clients load without a token.
+pub async fn public_library_access(state: &AppState) -> LibraryAccess {
+    state.public_access.get(&state.db).await
+}
+
+/// `LibraryAccess::public()` of the enabled users. Computed once and kept
+/// until users are added, changed or removed, which must call
+/// `invalidate()`.
+#[derive(Default)]
+pub struct PublicAccessCache {
+    access: RwLock<Option<LibraryAccess>>,
+    /// Bumped by `invalidate()`, so that a computation that started before
+    /// it is not stored.
+    generation: AtomicU64,
+}
+
+impl PublicAccessCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub async fn get(&self, db: &SqliteRepository) -> LibraryAccess {
+        if let Some(access) = self.access.read().unwrap().as_ref() {
+            return access.clone();
+        }
+        let generation = self.generation.load(Ordering::SeqCst);
+        let users = match db.list_users().await {
+            Ok(users) => users,
+            Err(_) => return LibraryAccess::none(),
+        };
+        let enabled: Vec<User> = users.iter().filter(|u| !u.is_disabled).cloned().collect();
+        // Disabling every user does not make the library public.
+        let access = if enabled.is_empty() && !users.is_empty() {
+            LibraryAccess::none()
+        } else {
+            LibraryAccess::public(&enabled)
+        };
+
+        let mut cached = self.access.write().unwrap();
+        if self.generation.load(Ordering::SeqCst) == generation {
+            *cached = Some(access.clone());
+        }
+        access
+    }
+
+    pub fn invalidate(&self) {
+        let mut cached = self.access.write().unwrap();
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        *cached = None;
+    }
+}
@@ -0,0 +1,103 @@
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::{Request, StatusCode},
+    Json,
+};
+
+use super::auth::{get_user_id, require_admin};
+use super::types::*;
+use crate::db::{ApiKey, ApiKeyRepo};
+use crate::server::AppState;
+use crate::util::QueryParams;
+
+/// GET /Auth/Keys
+pub async fn get_keys(
+    State(state): State<AppState>,
+    req: Request<Body>,
+) -> Result<Json<QueryResult<AuthenticationInfo>>, StatusCode> {
+    let user_id = get_user_id(&req).ok_or(StatusCode::UNAUTHORIZED)?;
+    require_admin(&state, &user_id).await?;
+    let keys = state
+        .db
+        .list_api_keys()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let items: Vec<AuthenticationInfo> = keys
+        .into_iter()
+        .enumerate()
+        .map(|(i, key)| {
+            let created = key.created.unwrap_or_default();
+            AuthenticationInfo {
+                id: i as i64 + 1,
+                access_token: key.token,
+                app_name: key.name,
+                app_version: String::new(),
+                device_id: String::new(),
+                device_name: String::new(),
+                user_id: String::new(),
+                is_active: true,
+                date_last_activity: key.lastused.unwrap_or_else(|| created.clone()),
+                date_created: created,
+                date_revoked: None,
+                user_name: None,
+            }
+        })
+        .collect();
+
+    Ok(Json(QueryResult {
+        total_record_count: items.len(),
+        items,
+        start_index: 0,
+    }))
+}
+
+/// POST /Auth/Keys?app=...
+pub async fn create_key(
+    State(state): State<AppState>,
+    Query(params): Query<QueryParams>,
+    req: Request<Body>,
+) -> Result<StatusCode, StatusCode> {
+    let user_id = get_user_id(&req).ok_or(StatusCode::UNAUTHORIZED)?;
+    require_admin(&state, &user_id).await?;
+    let name = params
+        .get("app")
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .ok_or(StatusCode::BAD_REQUEST)?;
+
+    let key = ApiKey {
+        token: uuid::Uuid::new_v4().simple().to_string(),
+        name: name.to_string(),
+        created: Some(chrono::Utc::now().to_rfc3339()),
+        lastused: None,
+    };
+    state
+        .db
+        .upsert_api_key(&key)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// DELETE /Auth/Keys/{key}
+pub async fn revoke_key(
+    State(state): State<AppState>,
+    Path(key): Path<String>,
+    req: Request<Body>,
+) -> Result<StatusCode, StatusCode> {
+    let user_id = get_user_id(&req).ok_or(StatusCode::UNAUTHORIZED)?;
+    require_admin(&state, &user_id).await?;
+    state
+        .db
+        .get_api_key(&key)
+        .await
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+    state
+        .db
+        .delete_api_key(&key)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(StatusCode::NO_CONTENT)
+}
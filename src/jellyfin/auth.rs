@@ -3,70 +3,169 @@
 // Jellyfin - https://gist.github.com/nielsvanvelzen/ea047d9028f676185832e51ffaf12a6f
 
 use axum::{
-    extract::{Query, State},
-    http::{Request, StatusCode},
+    extract::{ConnectInfo, Query, State},
+    http::{header, HeaderMap, Request, StatusCode},
     middleware::Next,
-    response::Response,
+    response::{IntoResponse, Response},
     Json,
 };
 use bcrypt;
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+use std::net::SocketAddr;
 
+use super::access::LibraryAccess;
+use super::loginattemptmanager::LoginAttemptManager;
+use super::quickconnectmanager::QuickConnectRequest;
+use super::sessionmanager::{ClientInfo, SessionId};
 use super::types::*;
-use crate::db::{AccessToken, AccessTokenRepo, User, UserRepo};
+use super::user::{create_user, create_user_dto};
+use crate::db::{AccessToken, AccessTokenRepo, ApiKeyRepo, User, UserRepo};
+use crate::middleware::RequestUser;
 use crate::server::AppState;
-use crate::util::{generate_id, QueryParams};
+use crate::util::QueryParams;
 
+/// POST /Users/AuthenticateByName
+/// After a failed attempt, the user name and remote address must wait
+/// before trying again (429 with `Retry-After`).
 pub async fn authenticate_by_name(
     State(state): State<AppState>,
+    headers: HeaderMap,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
     Json(req): Json<AuthenticationRequest>,
-) -> Result<Json<AuthenticationResult>, StatusCode> {
+) -> Result<Response, StatusCode> {
     let username = req.username.trim().to_lowercase();
+    let peer = connect_info.map(|ConnectInfo(addr)| addr);
+    let attempt_keys = LoginAttemptManager::keys(&username, &remote_address(&headers, peer));
 
-    let now = chrono::Utc::now();
-    let now_text = now.to_rfc3339();
+    if let Err(wait) = state.login_attempts.begin(&attempt_keys) {
+        let seconds = (wait.num_milliseconds() + 999) / 1000;
+        return Ok((
+            StatusCode::TOO_MANY_REQUESTS,
+            [(header::RETRY_AFTER, seconds.to_string())],
+        )
+            .into_response());
+    }
 
     let user = match state.db.get_user(&username).await {
         Ok(user) => {
+            if user.is_disabled {
+                return Err(StatusCode::UNAUTHORIZED);
+            }
             let auth_ok = match bcrypt::verify(&req.pw, &user.password) {
                 Ok(value) => value,
                 Err(_) => false,
             };
             if !auth_ok {
+                record_invalid_login(&state, &user).await?;
                 return Err(StatusCode::UNAUTHORIZED);
             }
-            user
-        }
-        Err(_) => {
-            if state.config.jellyfin.autoregister {
-                let new_user = User {
-                    id: generate_id(&username),
-                    username: username.to_string(),
-                    password: bcrypt::hash(&req.pw, bcrypt::DEFAULT_COST)
-                        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?,
-                    created: Some(now_text.clone()),
-                    lastlogin: None,
-                    lastused: None,
-                };
+            if user.invalid_login_attempts > 0 {
                 state
                     .db
-                    .upsert_user(&new_user)
+                    .reset_invalid_logins(&user.id)
                     .await
                     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-                new_user
+            }
+            user
+        }
+        Err(_) => {
+            if state.config.jellyfin.autoregister {
+                create_user(&state, &username, &req.pw).await?
             } else {
                 return Err(StatusCode::UNAUTHORIZED);
             }
         }
     };
+    state.login_attempts.succeeded(&attempt_keys);
+
+    Ok(login(&state, &user, &headers, peer).await?.into_response())
+}
 
+/// Count a failed login. At `jellyfin.loginattemptsbeforelockout` the user
+/// is disabled until an administrator enables it again. Administrators are
+/// only slowed down, so that the server cannot be locked out completely.
+async fn record_invalid_login(state: &AppState, user: &User) -> Result<(), StatusCode> {
+    let lockout = state.config.jellyfin.login_attempts_before_lockout;
+    let updated = state
+        .db
+        .record_invalid_login(&user.id, lockout)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if updated.is_disabled && !user.is_disabled {
+        state.public_access.invalidate();
+        tracing::warn!(
+            "User {} disabled after {} failed logins",
+            updated.username,
+            updated.invalid_login_attempts
+        );
+    }
+    Ok(())
+}
+
+/// POST /Users/AuthenticateWithQuickConnect
+pub async fn authenticate_with_quick_connect(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    Json(req): Json<QuickConnectDto>,
+) -> Result<Json<AuthenticationResult>, StatusCode> {
+    if !state.config.jellyfin.quickconnect {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    let user_id = state
+        .quick_connect
+        .redeem(&req.secret)
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+    let user = state
+        .db
+        .get_user_by_id(&user_id)
+        .await
+        .map_err(|_| StatusCode::UNAUTHORIZED)?;
+    if user.is_disabled {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+
+    let peer = connect_info.map(|ConnectInfo(addr)| addr);
+    login(&state, &user, &headers, peer).await
+}
+
+/// Issue an access token and start a session for an authenticated user.
+/// A previous token of the user on the same device is revoked.
+async fn login(
+    state: &AppState,
+    user: &User,
+    headers: &HeaderMap,
+    peer: Option<SocketAddr>,
+) -> Result<Json<AuthenticationResult>, StatusCode> {
+    let now = chrono::Utc::now();
+    let now_text = now.to_rfc3339();
+
+    let mut client = parse_client_info(headers);
+    client.remote_address = remote_address(headers, peer);
+
+    if !client.device_id.is_empty() {
+        let tokens = state
+            .db
+            .list_tokens_by_user(&user.id)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        for old in tokens {
+            if old.deviceid.as_deref() == Some(client.device_id.as_str()) {
+                revoke_token(state, &old.token).await?;
+            }
+        }
+    }
+
+    let non_empty = |s: &str| (!s.is_empty()).then(|| s.to_string());
     let token = AccessToken {
         token: uuid::Uuid::new_v4().to_string(),
         userid: user.id.clone(),
-        deviceid: None,
-        devicename: None,
-        applicationname: None,
-        applicationversion: None,
-        remoteaddress: None,
+        deviceid: non_empty(&client.device_id),
+        devicename: non_empty(&client.device),
+        applicationname: non_empty(&client.client),
+        applicationversion: non_empty(&client.version),
+        remoteaddress: non_empty(&client.remote_address),
         created: Some(now),
         lastused: Some(now),
     };
@@ -77,7 +176,9 @@ pub async fn authenticate_by_name(
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    let session_id = "e3a869b7a901f8894de8ee65688db6c0"; // Hardcoded session ID matching Go implementation
+    let session_id = state
+        .sessions
+        .create(&token.token, &user.id, &user.username, &client);
     let server_id = state
         .config
         .jellyfin
@@ -86,78 +187,7 @@ pub async fn authenticate_by_name(
         .unwrap_or_else(|| "jellyfin-rs".to_string());
 
     let result = AuthenticationResult {
-        user: UserDto {
-            name: user.username.clone(),
-            server_id: server_id.clone(),
-            id: user.id.clone(),
-            has_password: false,
-            has_configured_password: false,
-            has_configured_easy_password: false,
-            enable_auto_login: false,
-            last_login_date: now_text.clone(),
-            last_activity_date: now_text.clone(),
-            configuration: UserConfiguration {
-                grouped_folders: vec![],
-                subtitle_mode: "Default".to_string(),
-                ordered_views: vec![],
-                my_media_excludes: vec![],
-                latest_items_excludes: vec![],
-                subtitle_language_preference: String::new(),
-                cast_receiver_id: String::new(),
-                play_default_audio_track: true,
-                display_missing_episodes: false,
-                display_collections_view: false,
-                enable_local_password: false,
-                hide_played_in_latest: false,
-                remember_audio_selections: true,
-                remember_subtitle_selections: true,
-                enable_next_episode_auto_play: false,
-            },
-            policy: UserPolicy {
-                is_administrator: false,
-                is_hidden: false,
-                enable_collection_management: false,
-                enable_subtitle_management: false,
-                enable_lyric_management: false,
-                is_disabled: false,
-                blocked_tags: vec![],
-                allowed_tags: vec![],
-                enable_user_preference_access: false,
-                access_schedules: vec![],
-                block_unrated_items: vec![],
-                enable_remote_control_of_other_users: false,
-                enable_shared_device_control: false,
-                enable_remote_access: true,
-                enable_live_tv_management: false,
-                enable_live_tv_access: false,
-                enable_media_playback: true,
-                enable_audio_playback_transcoding: false,
-                enable_video_playback_transcoding: false,
-                enable_playback_remuxing: false,
-                force_remote_source_transcoding: false,
-                enable_content_deletion: false,
-                enable_content_deletion_from_folders: vec![],
-                enable_content_downloading: true,
-                enable_sync_transcoding: false,
-                enable_media_conversion: false,
-                enabled_devices: vec![],
-                enable_all_devices: true,
-                enabled_channels: vec![],
-                enable_all_channels: false,
-                enabled_folders: vec![],
-                enable_all_folders: true,
-                invalid_login_attempt_count: 0,
-                login_attempts_before_lockout: 0,
-                max_active_sessions: 0,
-                enable_public_sharing: false,
-                blocked_media_folders: vec![],
-                blocked_channels: vec![],
-                remote_client_bitrate_limit: 0,
-                authentication_provider_id: "DefaultAuthenticationProvider".to_string(),
-                password_reset_provider_id: "DefaultPasswordResetProvider".to_string(),
-                sync_play_access: "CreateAndJoinGroups".to_string(),
-            },
-        },
+        user: create_user_dto(user, server_id.clone()),
         session_info: AuthSessionInfo {
             play_state: PlayState {
                 can_seek: false,
@@ -173,17 +203,17 @@ pub async fn authenticate_by_name(
                 supports_media_control: false,
                 supports_persistent_identifier: true,
             },
-            remote_end_point: String::new(),
+            remote_end_point: client.remote_address,
             playable_media_types: vec![],
-            id: session_id.to_string(),
+            id: session_id,
             user_id: user.id.clone(),
             user_name: user.username.clone(),
-            client: String::new(),
+            client: client.client,
             last_activity_date: now_text.clone(),
             last_playback_check_in: "0001-01-01T00:00:00Z".to_string(),
-            device_name: String::new(),
-            device_id: String::new(),
-            application_version: String::new(),
+            device_name: client.device,
+            device_id: client.device_id,
+            application_version: client.version,
             is_active: true,
             supports_media_control: false,
             supports_remote_control: false,
@@ -200,6 +230,10 @@ pub async fn authenticate_by_name(
     Ok(Json(result))
 }
 
+/// User ID of requests made with an API key. It is not a real user, but
+/// passes `require_admin`.
+pub const API_KEY_USER_ID: &str = "apikey";
+
 pub async fn auth_middleware(
     State(state): State<AppState>,
     Query(params): Query<QueryParams>,
@@ -207,14 +241,162 @@ pub async fn auth_middleware(
     next: Next,
 ) -> Result<Response, StatusCode> {
     let token = extract_token(&req, &params);
+    // Who made the request, for the access log.
+    let mut request_user = None;
 
     if let Some(token_str) = token {
-        if let Ok(token) = state.db.get_token(&token_str).await {
+        if let Ok(mut token) = state.db.get_token(&token_str).await {
+            let now = chrono::Utc::now();
+            if token_expired(&state, &token, now) {
+                revoke_token(&state, &token.token).await?;
+                return Ok(next.run(req).await);
+            }
+
+            let peer = req
+                .extensions()
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(addr)| *addr);
+            let mut client = parse_client_info(req.headers());
+            client.remote_address = remote_address(req.headers(), peer);
+            // WebSocket clients cannot set headers and pass it in the URL.
+            if client.device_id.is_empty() {
+                client.device_id = params.get("deviceId").unwrap_or_default().to_string();
+            }
+
+            // Recording every request would be wasteful; a minute is precise
+            // enough for the idle timeout.
+            if token
+                .lastused
+                .is_none_or(|t| now - t > chrono::Duration::minutes(1))
+            {
+                token.lastused = Some(now);
+                if !client.remote_address.is_empty() {
+                    token.remoteaddress = Some(client.remote_address.clone());
+                }
+                state
+                    .db
+                    .upsert_token(&token)
+                    .await
+                    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+            }
+            let user = state.db.get_user_by_id(&token.userid).await.ok();
+            if user.as_ref().is_some_and(|user| user.is_disabled) {
+                return Ok(next.run(req).await);
+            }
+            let session_id = match state.sessions.touch(&token.token, &client.device_id) {
+                Some(id) => Some(id),
+                None => user.as_ref().map(|user| {
+                    state
+                        .sessions
+                        .create(&token.token, &user.id, &user.username, &client)
+                }),
+            };
+            if let Some(session_id) = session_id {
+                req.extensions_mut().insert(SessionId(session_id));
+            }
+            if let Some(user) = &user {
+                req.extensions_mut().insert(LibraryAccess::for_user(user));
+                request_user = Some(RequestUser {
+                    user_name: user.username.clone(),
+                    device: match client.device.as_str() {
+                        "" => token.devicename.clone().unwrap_or_default(),
+                        device => device.to_string(),
+                    },
+                });
+            }
             req.extensions_mut().insert(token.userid.clone());
+        } else if let Ok(mut key) = state.db.get_api_key(&token_str).await {
+            let now = chrono::Utc::now();
+            let lastused = key
+                .lastused
+                .as_deref()
+                .and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok())
+                .map(|t| t.with_timezone(&chrono::Utc));
+            if lastused.is_none_or(|t| now - t > chrono::Duration::minutes(1)) {
+                key.lastused = Some(now.to_rfc3339());
+                state
+                    .db
+                    .upsert_api_key(&key)
+                    .await
+                    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+            }
+            req.extensions_mut().insert(LibraryAccess::all());
+            req.extensions_mut().insert(API_KEY_USER_ID.to_string());
+            request_user = Some(RequestUser {
+                user_name: API_KEY_USER_ID.to_string(),
+                device: key.name.clone(),
+            });
         }
     }
 
-    Ok(next.run(req).await)
+    let mut response = next.run(req).await;
+    if let Some(request_user) = request_user {
+        response.extensions_mut().insert(request_user);
+    }
+    Ok(response)
+}
+
+/// Whether a token is past the configured idle or absolute lifetime.
+fn token_expired(
+    state: &AppState,
+    token: &AccessToken,
+    now: chrono::DateTime<chrono::Utc>,
+) -> bool {
+    let config = &state.config.jellyfin;
+    let days = |days: u32| chrono::Duration::days(days.into());
+    let idle = config
+        .token_idle_days
+        .zip(token.lastused.or(token.created))
+        .is_some_and(|(d, lastused)| now - lastused > days(d));
+    let too_old = config
+        .token_max_age_days
+        .zip(token.created)
+        .is_some_and(|(d, created)| now - created > days(d));
+    idle || too_old
+}
+
+/// Delete an access token and end the sessions that use it.
+pub async fn revoke_token(state: &AppState, token: &str) -> Result<(), StatusCode> {
+    state
+        .db
+        .delete_token(token)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    state.sessions.end_token_sessions(token);
+    Ok(())
+}
+
+/// POST /Sessions/Logout
+pub async fn logout(
+    State(state): State<AppState>,
+    Query(params): Query<QueryParams>,
+    req: Request<axum::body::Body>,
+) -> Result<StatusCode, StatusCode> {
+    get_user_id(&req).ok_or(StatusCode::UNAUTHORIZED)?;
+    let token = extract_token(&req, &params).ok_or(StatusCode::UNAUTHORIZED)?;
+    revoke_token(&state, &token).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// The client's address. Behind a reverse proxy on the same host, the
+/// address it reports in `X-Forwarded-For` or `X-Real-IP`.
+pub fn remote_address(headers: &HeaderMap, peer: Option<SocketAddr>) -> String {
+    let Some(ip) = peer.map(|addr| addr.ip().to_canonical()) else {
+        return String::new();
+    };
+    if ip.is_loopback() {
+        let forwarded = headers
+            .get("X-Forwarded-For")
+            .and_then(|h| h.to_str().ok())
+            .and_then(|s| s.split(',').next())
+            .or_else(|| headers.get("X-Real-IP").and_then(|h| h.to_str().ok()))
+            .map(str::trim)
+            .filter(|s| !s.is_empty());
+        if let Some(forwarded) = forwarded {
+            return forwarded.to_string();
+        }
+    }
+    ip.to_string()
 }
 
 fn extract_token<B>(req: &Request<B>, params: &QueryParams) -> Option<String> {
@@ -260,34 +442,154 @@ fn extract_token<B>(req: &Request<B>, params: &QueryParams) -> Option<String> {
 }
 
 fn parse_emby_auth(auth_str: &str) -> Option<String> {
-    for part in auth_str.split(',') {
-        let part = part.trim();
-        if let Some(token_part) = part.strip_prefix("Token=") {
-            return Some(token_part.trim_matches('"').to_string());
-        }
+    parse_emby_auth_params(auth_str).remove("Token")
+}
+
+/// Split `MediaBrowser Client="x", Device="y", ...` into its key/value pairs.
+fn parse_emby_auth_params(auth_str: &str) -> HashMap<String, String> {
+    let params = auth_str
+        .trim()
+        .strip_prefix("MediaBrowser")
+        .or_else(|| auth_str.trim().strip_prefix("Emby"))
+        .unwrap_or(auth_str);
+
+    params
+        .split(',')
+        .filter_map(|part| {
+            let (key, value) = part.split_once('=')?;
+            Some((
+                key.trim().to_string(),
+                value.trim().trim_matches('"').to_string(),
+            ))
+        })
+        .collect()
+}
+
+/// Client, device and version as sent in the (X-Emby-)Authorization header.
+pub fn parse_client_info(headers: &HeaderMap) -> ClientInfo {
+    let auth_str = headers
+        .get("Authorization")
+        .or_else(|| headers.get("X-Emby-Authorization"))
+        .and_then(|h| h.to_str().ok())
+        .unwrap_or_default();
+    let mut params = parse_emby_auth_params(auth_str);
+
+    ClientInfo {
+        client: params.remove("Client").unwrap_or_default(),
+        device: params.remove("Device").unwrap_or_default(),
+        device_id: params.remove("DeviceId").unwrap_or_default(),
+        version: params.remove("Version").unwrap_or_default(),
+        remote_address: String::new(),
     }
-    None
 }
 
 pub fn get_user_id<B>(req: &Request<B>) -> Option<String> {
     req.extensions().get::<String>().cloned()
 }
 
-use axum::response::IntoResponse;
+pub fn get_session_id<B>(req: &Request<B>) -> Option<String> {
+    req.extensions().get::<SessionId>().map(|s| s.0.clone())
+}
 
-pub async fn quick_connect_enabled(State(_state): State<AppState>) -> Json<bool> {
-    // Stub: Quick Connect not yet fully implemented
-    Json(false)
+/// Parse the JSON body of a request, for handlers that need the request
+/// itself to find out who is calling.
+pub async fn json_body<T: DeserializeOwned>(
+    req: Request<axum::body::Body>,
+) -> Result<T, StatusCode> {
+    let bytes = axum::body::to_bytes(req.into_body(), usize::MAX)
+        .await
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+    serde_json::from_slice(&bytes).map_err(|_| StatusCode::BAD_REQUEST)
 }
 
-pub async fn quick_connect_initiate(State(_state): State<AppState>) -> impl IntoResponse {
-    StatusCode::NOT_IMPLEMENTED.into_response()
+/// FORBIDDEN unless the request is made by an administrator or with an API key.
+pub async fn require_admin(state: &AppState, user_id: &str) -> Result<(), StatusCode> {
+    if user_id == API_KEY_USER_ID {
+        return Ok(());
+    }
+    let user = state
+        .db
+        .get_user_by_id(user_id)
+        .await
+        .map_err(|_| StatusCode::UNAUTHORIZED)?;
+    if !user.is_admin {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    Ok(())
 }
 
-pub async fn quick_connect_authorize(State(_state): State<AppState>) -> impl IntoResponse {
-    StatusCode::NOT_IMPLEMENTED.into_response()
+/// GET /QuickConnect/Enabled
+pub async fn quick_connect_enabled(State(state): State<AppState>) -> Json<bool> {
+    Json(state.config.jellyfin.quickconnect)
+}
+
+/// POST /QuickConnect/Initiate
+/// Called by a device that wants to log in; it then shows the code to the user.
+pub async fn quick_connect_initiate(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+) -> Result<Json<QuickConnectResult>, StatusCode> {
+    if !state.config.jellyfin.quickconnect {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    let mut client = parse_client_info(&headers);
+    client.remote_address = remote_address(&headers, connect_info.map(|ConnectInfo(addr)| addr));
+    let request = state
+        .quick_connect
+        .initiate(&client)
+        .ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+    Ok(Json(quick_connect_result(request)))
 }
 
-pub async fn quick_connect_connect(State(_state): State<AppState>) -> impl IntoResponse {
-    StatusCode::NOT_IMPLEMENTED.into_response()
+/// POST /QuickConnect/Authorize?code=...
+/// Called by a logged-in client to let the device showing the code log in as its user.
+pub async fn quick_connect_authorize(
+    State(state): State<AppState>,
+    Query(params): Query<QueryParams>,
+    req: Request<axum::body::Body>,
+) -> Result<Json<bool>, StatusCode> {
+    let user_id = get_user_id(&req).ok_or(StatusCode::UNAUTHORIZED)?;
+    if !state.config.jellyfin.quickconnect {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    if params.get("userId").is_some_and(|id| id != user_id) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    let code = params.get("code").ok_or(StatusCode::BAD_REQUEST)?;
+
+    if !state.quick_connect.authorize(code, &user_id) {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    Ok(Json(true))
+}
+
+/// GET /QuickConnect/Connect?secret=...
+/// Polled by the device until the request is authorized.
+pub async fn quick_connect_connect(
+    State(state): State<AppState>,
+    Query(params): Query<QueryParams>,
+) -> Result<Json<QuickConnectResult>, StatusCode> {
+    if !state.config.jellyfin.quickconnect {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    let secret = params.get("secret").ok_or(StatusCode::BAD_REQUEST)?;
+    let request = state
+        .quick_connect
+        .get(secret)
+        .ok_or(StatusCode::NOT_FOUND)?;
+    Ok(Json(quick_connect_result(request)))
+}
+
+fn quick_connect_result(request: QuickConnectRequest) -> QuickConnectResult {
+    QuickConnectResult {
+        authenticated: request.user_id.is_some(),
+        secret: request.secret,
+        code: request.code,
+        device_id: request.client.device_id,
+        device_name: request.client.device,
+        app_name: request.client.client,
+        app_version: request.client.version,
+        date_added: request.date_added.to_rfc3339(),
+    }
 }
@@ -6,7 +6,7 @@ use axum::{
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
-use super::auth::get_user_id;
+use super::auth::{get_user_id, revoke_token};
 use crate::db::{AccessTokenRepo, UserRepo};
 use crate::server::AppState;
 use crate::util::QueryParams;
@@ -90,7 +90,10 @@ pub async fn get_devices(
             name: token.devicename.clone().unwrap_or_default(),
             app_name: token.applicationname.clone().unwrap_or_default(),
             app_version: token.applicationversion.clone().unwrap_or_default(),
-            date_last_activity: token.created.unwrap_or_else(|| chrono::Utc::now()),
+            date_last_activity: token
+                .lastused
+                .or(token.created)
+                .unwrap_or_else(|| chrono::Utc::now()),
             capabilities: DeviceCapabilities {
                 playable_media_types: vec![],
                 supported_commands: vec![],
@@ -126,11 +129,7 @@ pub async fn delete_device(
 
     for token in tokens {
         if token.deviceid.as_deref() == Some(device_id) {
-            state
-                .db
-                .delete_token(&token.token)
-                .await
-                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+            revoke_token(&state, &token.token).await?;
             return Ok(StatusCode::NO_CONTENT);
         }
     }
@@ -168,7 +167,10 @@ pub async fn get_device_info(
                 name: token.devicename.clone().unwrap_or_default(),
                 app_name: token.applicationname.clone().unwrap_or_default(),
                 app_version: token.applicationversion.clone().unwrap_or_default(),
-                date_last_activity: token.created.unwrap_or_else(|| chrono::Utc::now()),
+                date_last_activity: token
+                    .lastused
+                    .or(token.created)
+                    .unwrap_or_else(|| chrono::Utc::now()),
                 capabilities: DeviceCapabilities {
                     playable_media_types: vec![],
                     supported_commands: vec![],
@@ -1,3 +1,5 @@
+use crate::collection::CollectionRepo;
+use crate::jellyfin::access::LibraryAccess;
 use crate::jellyfin::types::BaseItemDto;
 use crate::util::QueryParams;
 
@@ -325,3 +327,21 @@ pub fn apply_items_filter(items: Vec<BaseItemDto>, params: &QueryParams) -> Vec<
         .filter(|item| apply_item_filter(item, params))
         .collect()
 }
+
+/// Parental control: drop items rated above the user's limit, and unrated
+/// items of a kind the user may not see unrated.
+pub fn apply_parental_filter(
+    items: Vec<BaseItemDto>,
+    access: &LibraryAccess,
+    collections: &CollectionRepo,
+) -> Vec<BaseItemDto> {
+    if !access.has_parental_limit() {
+        return items;
+    }
+    items
+        .into_iter()
+        .filter(|item| {
+            access.allows_rating(&item.item_type, collections.get_rating(&item.id).as_deref())
+        })
+        .collect()
+}
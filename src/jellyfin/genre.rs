@@ -2,12 +2,13 @@ use std::collections::HashSet;
 
 use axum::{
     extract::{Path, Query, State},
-    http::StatusCode,
+    http::{Request, StatusCode},
     Json,
 };
 use serde::Deserialize;
 use serde::Serialize;
 
+use super::access::get_library_access;
 use super::types::{BaseItemDto, NameIdPair, QueryResultNameIdPair};
 use crate::server::AppState;
 use crate::util::{generate_id, QueryParams};
@@ -15,10 +16,12 @@ use crate::util::{generate_id, QueryParams};
 pub async fn get_genres(
     State(state): State<AppState>,
     Query(params): Query<QueryParams>,
+    req: Request<axum::body::Body>,
 ) -> Result<Json<QueryResultNameIdPair>, StatusCode> {
     let mut genres = HashSet::new();
 
-    for collection in state.collections.list_collections().await {
+    let access = get_library_access(&req);
+    for collection in access.list_collections(&state.collections).await {
         for movie in collection.movies.values() {
             for genre in &movie.genres {
                 genres.insert(genre.clone());
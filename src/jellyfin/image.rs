@@ -0,0 +1,201 @@
+use axum::{
+    body::{Body, Bytes},
+    extract::{FromRequest, Path, Request, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use base64::Engine;
+use image::ImageFormat;
+use std::path::PathBuf;
+use tracing::{error, info};
+
+use super::auth::{get_user_id, require_admin};
+use crate::collection::scanner::scan_target_for_path;
+use crate::collection::{find_image_path, image_upload_path};
+use crate::server::AppState;
+
+/// Largest accepted upload body; base64 makes images a third larger.
+pub const MAX_IMAGE_UPLOAD: usize = 32 * 1024 * 1024;
+
+/// POST /Items/:item_id/Images/:image_type
+pub async fn upload_image(
+    State(state): State<AppState>,
+    Path((item_id, image_type)): Path<(String, String)>,
+    req: Request<Body>,
+) -> Result<StatusCode, StatusCode> {
+    let body = admin_upload(&state, req).await?;
+    store_image(&state, &item_id, &image_type, None, body).await
+}
+
+/// POST /Items/:item_id/Images/:image_type/:index
+pub async fn upload_image_indexed(
+    State(state): State<AppState>,
+    Path((item_id, image_type, index)): Path<(String, String, usize)>,
+    req: Request<Body>,
+) -> Result<StatusCode, StatusCode> {
+    let body = admin_upload(&state, req).await?;
+    store_image(&state, &item_id, &image_type, Some(index), body).await
+}
+
+/// DELETE /Items/:item_id/Images/:image_type
+pub async fn delete_image(
+    State(state): State<AppState>,
+    Path((item_id, image_type)): Path<(String, String)>,
+    req: Request<Body>,
+) -> Result<StatusCode, StatusCode> {
+    let user_id = get_user_id(&req).ok_or(StatusCode::UNAUTHORIZED)?;
+    require_admin(&state, &user_id).await?;
+    remove_image(&state, &item_id, &image_type, 0).await
+}
+
+/// DELETE /Items/:item_id/Images/:image_type/:index
+pub async fn delete_image_indexed(
+    State(state): State<AppState>,
+    Path((item_id, image_type, index)): Path<(String, String, usize)>,
+    req: Request<Body>,
+) -> Result<StatusCode, StatusCode> {
+    let user_id = get_user_id(&req).ok_or(StatusCode::UNAUTHORIZED)?;
+    require_admin(&state, &user_id).await?;
+    remove_image(&state, &item_id, &image_type, index).await
+}
+
+/// The body of an upload by an administrator, within `MAX_IMAGE_UPLOAD`.
+async fn admin_upload(state: &AppState, req: Request<Body>) -> Result<Bytes, StatusCode> {
+    let user_id = get_user_id(&req).ok_or(StatusCode::UNAUTHORIZED)?;
+    require_admin(state, &user_id).await?;
+    Bytes::from_request(req, state)
+        .await
+        .map_err(|e| e.into_response().status())
+}
+
+/// Write an uploaded image next to the media, replacing the one it
+/// updates, and rescan the item so it shows up.
+async fn store_image(
+    state: &AppState,
+    item_id: &str,
+    image_type: &str,
+    index: Option<usize>,
+    body: Bytes,
+) -> Result<StatusCode, StatusCode> {
+    let (data, format) = tokio::task::spawn_blocking(move || decode_upload(&body))
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::BAD_REQUEST)?;
+    let extension = match format {
+        ImageFormat::Jpeg => "jpg",
+        ImageFormat::Png => "png",
+        ImageFormat::WebP => "webp",
+        _ => return Err(StatusCode::UNSUPPORTED_MEDIA_TYPE),
+    };
+
+    let (collection_id, item) = state
+        .collections
+        .get_item(item_id)
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let (path, replaced) =
+        image_upload_path(&item, image_type, index, extension).ok_or(StatusCode::BAD_REQUEST)?;
+
+    // Write and rename, so that the scanner and image requests never see
+    // a partial file.
+    let partial = path.with_extension(format!("{}.tmp", uuid::Uuid::new_v4()));
+    let written = match tokio::fs::write(&partial, &data).await {
+        Ok(()) => tokio::fs::rename(&partial, &path).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = written {
+        error!("Failed to write image {:?}: {}", path, e);
+        let _ = tokio::fs::remove_file(&partial).await;
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+    let mut changed = vec![path.clone()];
+    if let Some(replaced) = replaced.filter(|replaced| *replaced != path) {
+        if let Err(e) = tokio::fs::remove_file(&replaced).await {
+            error!("Failed to remove replaced image {:?}: {}", replaced, e);
+        }
+        changed.push(replaced);
+    }
+    info!(
+        "Stored {} image of item {} as {:?}",
+        image_type, item_id, path
+    );
+
+    image_changed(state, &collection_id, changed).await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn remove_image(
+    state: &AppState,
+    item_id: &str,
+    image_type: &str,
+    index: usize,
+) -> Result<StatusCode, StatusCode> {
+    let (collection_id, _) = state
+        .collections
+        .get_item(item_id)
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let path = find_image_path(&state.collections, item_id, image_type, index)
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    tokio::fs::remove_file(&path).await.map_err(|e| {
+        error!("Failed to remove image {:?}: {}", path, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    info!(
+        "Removed {} image of item {} ({:?})",
+        image_type, item_id, path
+    );
+
+    image_changed(state, &collection_id, vec![path]).await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Drop the resized copies of the changed files and rescan the directory
+/// they are in, which updates the item's `ImageInfo`.
+async fn image_changed(state: &AppState, collection_id: &str, paths: Vec<PathBuf>) {
+    let target = match state.collections.get_collection(collection_id).await {
+        Some(collection) => {
+            scan_target_for_path(collection.collection_type, &collection.directory, &paths[0])
+        }
+        None => None,
+    };
+
+    let resizer = state.image_resizer.clone();
+    let _ = tokio::task::spawn_blocking(move || {
+        for path in &paths {
+            if let Err(e) = resizer.invalidate(path) {
+                error!("Failed to invalidate resized images of {:?}: {}", path, e);
+            }
+        }
+    })
+    .await;
+
+    if let Some(target) = target {
+        state
+            .collections
+            .rescan_targets(collection_id, vec![target])
+            .await;
+    }
+}
+
+/// The image in a request body: either the file itself, or base64 encoded
+/// as Jellyfin clients send it. Only images that decode are accepted.
+fn decode_upload(body: &[u8]) -> Option<(Vec<u8>, ImageFormat)> {
+    let data = match image::guess_format(body) {
+        Ok(_) => body.to_vec(),
+        Err(_) => {
+            let text = std::str::from_utf8(body).ok()?;
+            // A data URL, or just the base64 text, possibly wrapped over lines.
+            let text = text.split_once("base64,").map_or(text, |(_, data)| data);
+            let text: String = text.split_whitespace().collect();
+            base64::engine::general_purpose::STANDARD
+                .decode(text)
+                .ok()?
+        }
+    };
+    let format = image::guess_format(&data).ok()?;
+    if let Err(e) = image::load_from_memory_with_format(&data, format) {
+        info!("Rejected uploaded {:?} image: {}", format, e);
+        return None;
+    }
+    Some((data, format))
+}
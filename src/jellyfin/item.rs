@@ -1,14 +1,15 @@
 use axum::{
     extract::{Path, Query, State},
-    http::{self, Request, StatusCode},
-    response::{IntoResponse, Redirect, Response},
+    http::{self, header, Request, StatusCode},
+    response::{IntoResponse, Response},
     Json,
 };
 use tower::ServiceExt;
 use tower_http::services::ServeFile;
 
+use super::access::{get_library_access, public_library_access, LibraryAccess};
 use super::auth::get_user_id;
-use super::filter::apply_items_filter;
+use super::filter::{apply_items_filter, apply_parental_filter};
 use super::jfitem::{
     convert_episode_to_dto, convert_movie_to_dto, convert_season_to_dto, convert_show_to_dto,
     convert_to_media_source_info,
@@ -16,17 +17,21 @@ use super::jfitem::{
 use super::pagination::apply_pagination;
 use super::sort::apply_item_sorting;
 use super::types::*;
-use crate::collection::ItemRef;
-use crate::collection::find_image_path;
 use crate::collection::Item;
+use crate::collection::ItemRef;
+use crate::collection::{find_image, find_placeholder, ImageMeta};
 use crate::db::UserDataRepo;
+use crate::middleware::etags_match;
 use crate::server::AppState;
 use crate::util::QueryParams;
+use crate::util::{parse_color, OutputFormat, ResizeOptions};
 
 pub async fn get_item_ancestors(
     State(state): State<AppState>,
     Path(item_id): Path<String>,
+    req: Request<axum::body::Body>,
 ) -> Json<Vec<BaseItemDto>> {
+    let access = get_library_access(&req);
     let mut ancestors = Vec::new();
     let server_id = state
         .config
@@ -36,7 +41,7 @@ pub async fn get_item_ancestors(
         .unwrap_or_default();
 
     // 1. Find item and its collection
-    let (collection_id, found_item) = match state.collections.get_item(&item_id) {
+    let (collection_id, found_item) = match access.get_item(&state.collections, &item_id) {
         Some(res) => res,
         None => return Json(ancestors),
     };
@@ -108,6 +113,7 @@ pub async fn get_items(
         .get("limit")
         .and_then(|s| s.parse::<usize>().ok())
         .unwrap_or(100);
+    let access = get_library_access(&req);
 
     let mut include_item_types = Vec::new();
     if let Some(value) = params.get("includeItemTypes") {
@@ -125,7 +131,7 @@ pub async fn get_items(
 
     if let Some(ids_str) = ids_param {
         let requested_ids: Vec<&str> = ids_str.split(',').map(|s| s.trim()).collect();
-        let collections = state.collections.list_collections().await;
+        let collections = access.list_collections(&state.collections).await;
 
         for collection in &collections {
             // Check movies
@@ -181,10 +187,8 @@ pub async fn get_items(
     } else if let Some(parent_id) = parent_id {
         // Get items from specific collection, series, or season
 
-        let collections = state.collections.list_collections().await;
-
         // 1. Check if ParentId is a Collection
-        if let Some(collection) = state.collections.get_collection(parent_id).await {
+        if let Some(collection) = access.get_collection(&state.collections, parent_id).await {
             if include_item_types.is_empty()
                 || include_item_types
                     .iter()
@@ -213,80 +217,62 @@ pub async fn get_items(
                 }
             }
         } else {
-            // 2. Check if ParentId is a Series (return Seasons)
-            let mut found = false;
-            for collection in &collections {
-                if let Some(show) = collection.shows.get(parent_id) {
-                    // Found the series, return its seasons
+            match access.get_item(&state.collections, parent_id) {
+                // 2. ParentId is a Series (return Seasons)
+                Some((collection_id, Item::Show(show)))
                     if include_item_types.is_empty()
                         || include_item_types
                             .iter()
-                            .any(|t| t.eq_ignore_ascii_case("Season"))
-                    {
-                        // Sort seasons by index number
-                        let mut seasons: Vec<_> = show.seasons.values().collect();
-                        seasons.sort_by_key(|s| s.season_number);
-
-                        for season in seasons {
-                            items.push(convert_season_to_dto(
-                                season,
-                                &show.id,
-                                &collection.id,
-                                &show.name,
-                                &state.config.jellyfin.server_id.clone().unwrap_or_default(),
-                            ));
-                        }
-                    }
-                    found = true;
-                    break;
-                }
-            }
+                            .any(|t| t.eq_ignore_ascii_case("Season")) =>
+                {
+                    // Sort seasons by index number
+                    let mut seasons: Vec<_> = show.seasons.values().collect();
+                    seasons.sort_by_key(|s| s.season_number);
 
-            // 3. Check if ParentId is a Season (return Episodes)
-            if !found {
-                for collection in &collections {
-                    for show in collection.shows.values() {
-                        if let Some(season) = show.seasons.values().find(|s| s.id == *parent_id) {
-                            // Found the season, return its episodes
-                            if include_item_types.is_empty()
-                                || include_item_types
-                                    .iter()
-                                    .any(|t| t.eq_ignore_ascii_case("Episode"))
-                            {
-                                // Sort episodes by index number
-                                let mut episodes: Vec<_> = season.episodes.values().collect();
-                                episodes.sort_by_key(|e| e.episode_number);
-
-                                for episode in episodes {
-                                    items.push(convert_episode_to_dto(
-                                        episode,
-                                        &season.id,
-                                        &show.id,
-                                        &collection.id,
-                                        &season.name,
-                                        &show.name,
-                                        &state
-                                            .config
-                                            .jellyfin
-                                            .server_id
-                                            .clone()
-                                            .unwrap_or_default(),
-                                    ));
-                                }
-                            }
-                            found = true;
-                            break;
-                        }
+                    for season in seasons {
+                        items.push(convert_season_to_dto(
+                            season,
+                            &show.id,
+                            &collection_id,
+                            &show.name,
+                            &state.config.jellyfin.server_id.clone().unwrap_or_default(),
+                        ));
                     }
-                    if found {
-                        break;
+                }
+                // 3. ParentId is a Season (return Episodes)
+                Some((collection_id, Item::Season(season)))
+                    if include_item_types.is_empty()
+                        || include_item_types
+                            .iter()
+                            .any(|t| t.eq_ignore_ascii_case("Episode")) =>
+                {
+                    let show_name = match state.collections.get_item(&season.show_id) {
+                        Some((_, Item::Show(show))) => show.name.clone(),
+                        _ => String::new(),
+                    };
+
+                    // Sort episodes by index number
+                    let mut episodes: Vec<_> = season.episodes.values().collect();
+                    episodes.sort_by_key(|e| e.episode_number);
+
+                    for episode in episodes {
+                        items.push(convert_episode_to_dto(
+                            episode,
+                            &season.id,
+                            &season.show_id,
+                            &collection_id,
+                            &season.name,
+                            &show_name,
+                            &state.config.jellyfin.server_id.clone().unwrap_or_default(),
+                        ));
                     }
                 }
+                _ => {}
             }
         }
     } else if recursive {
         // Get items from all collections when recursive=true and no ParentId
-        let collections = state.collections.list_collections().await;
+        let collections = access.list_collections(&state.collections).await;
 
         for collection in &collections {
             if items.len() >= limit {
@@ -348,6 +334,7 @@ pub async fn get_items(
 
     // Apply filtering
     items = apply_items_filter(items, &params);
+    items = apply_parental_filter(items, &access, &state.collections);
 
     // Store total count before pagination
     let total_count = items.len();
@@ -368,8 +355,10 @@ pub async fn get_items(
 pub async fn get_user_item_by_id(
     State(state): State<AppState>,
     Path((user_id, item_id)): Path<(String, String)>,
+    req: Request<axum::body::Body>,
 ) -> Result<Json<BaseItemDto>, StatusCode> {
-    fetch_item_by_id(&state, &item_id, Some(&user_id)).await
+    let access = get_library_access(&req);
+    fetch_item_by_id(&state, &access, &item_id, Some(&user_id)).await
 }
 
 pub async fn get_item_by_id(
@@ -378,17 +367,19 @@ pub async fn get_item_by_id(
     req: Request<axum::body::Body>,
 ) -> Result<Json<BaseItemDto>, StatusCode> {
     let user_id = get_user_id(&req);
-    fetch_item_by_id(&state, &item_id, user_id.as_deref()).await
+    let access = get_library_access(&req);
+    fetch_item_by_id(&state, &access, &item_id, user_id.as_deref()).await
 }
 
-async fn fetch_item_by_id(
+pub(crate) async fn fetch_item_by_id(
     state: &AppState,
+    access: &LibraryAccess,
     item_id: &str,
     user_id: Option<&str>,
 ) -> Result<Json<BaseItemDto>, StatusCode> {
     let server_id = state.config.jellyfin.server_id.clone().unwrap_or_default();
 
-    if let Some((collection_id, item)) = state.collections.get_item(item_id) {
+    if let Some((collection_id, item)) = access.get_item(&state.collections, item_id) {
         let mut dto = match item {
             Item::Movie(movie) => convert_movie_to_dto(&movie, &collection_id, &server_id),
             Item::Show(show) => convert_show_to_dto(&show, &collection_id, &server_id),
@@ -397,7 +388,7 @@ async fn fetch_item_by_id(
                 let show_name = if let Some((_, Item::Show(show))) =
                     state.collections.get_item(&season.show_id)
                 {
-                    show.name
+                    show.name.clone()
                 } else {
                     String::new()
                 };
@@ -414,7 +405,7 @@ async fn fetch_item_by_id(
                 let show_name = if let Some((_, Item::Show(show))) =
                     state.collections.get_item(&episode.show_id)
                 {
-                    show.name
+                    show.name.clone()
                 } else {
                     String::new()
                 };
@@ -422,7 +413,7 @@ async fn fetch_item_by_id(
                 let season_name = if let Some((_, Item::Season(season))) =
                     state.collections.get_item(&episode.season_id)
                 {
-                    season.name
+                    season.name.clone()
                 } else {
                     String::new()
                 };
@@ -473,13 +464,14 @@ pub async fn get_latest_items(
         .get("limit")
         .and_then(|s| s.parse::<usize>().ok())
         .unwrap_or(16);
+    let access = get_library_access(&req);
 
     let mut all_items = Vec::new();
     let server_id = state.config.jellyfin.server_id.clone().unwrap_or_default();
 
     if let Some(parent_id) = parent_id {
         // Get latest from specific collection
-        if let Some(collection) = state.collections.get_collection(parent_id).await {
+        if let Some(collection) = access.get_collection(&state.collections, parent_id).await {
             for movie in collection.movies.values() {
                 all_items.push((
                     movie.premiere_date,
@@ -495,7 +487,7 @@ pub async fn get_latest_items(
         }
     } else {
         // Get latest from all collections
-        let collections = state.collections.list_collections().await;
+        let collections = access.list_collections(&state.collections).await;
         for collection in collections {
             let coll_id = &collection.id;
             for movie in collection.movies.values() {
@@ -520,6 +512,7 @@ pub async fn get_latest_items(
     let mut items: Vec<BaseItemDto> = all_items.into_iter().map(|(_, dto)| dto).collect();
 
     items = apply_items_filter(items, &params);
+    items = apply_parental_filter(items, &access, &state.collections);
 
     // Take limit after filtering
     items.truncate(limit);
@@ -544,8 +537,13 @@ pub async fn get_latest_items(
     Json(items)
 }
 
-pub async fn get_item_counts(State(state): State<AppState>) -> Json<ItemCounts> {
-    let collections = state.collections.list_collections().await;
+pub async fn get_item_counts(
+    State(state): State<AppState>,
+    req: Request<axum::body::Body>,
+) -> Json<ItemCounts> {
+    let collections = get_library_access(&req)
+        .list_collections(&state.collections)
+        .await;
 
     let mut movie_count = 0;
     let mut series_count = 0;
@@ -574,45 +572,30 @@ pub async fn get_item_counts(State(state): State<AppState>) -> Json<ItemCounts>
 pub async fn get_playback_info(
     State(state): State<AppState>,
     Path(item_id): Path<String>,
+    req: Request<axum::body::Body>,
 ) -> Result<Response, StatusCode> {
     let mut sources = Vec::new();
 
-    for collection in state.collections.list_collections().await {
-        if let Some(movie) = collection.movies.get(&item_id) {
+    match get_library_access(&req).get_item(&state.collections, &item_id) {
+        Some((_, Item::Movie(movie))) => {
             sources.extend(
                 movie.media_sources.iter().map(|ms| {
                     convert_to_media_source_info(ms, &item_id, movie.runtime_ticks.clone())
                 }),
             );
         }
-
-        if sources.len() == 0 {
-            for show in collection.shows.values() {
-                for season in show.seasons.values() {
-                    for episode in season.episodes.values() {
-                        if episode.id == item_id {
-                            sources.extend(episode.media_sources.iter().map(|ms| {
-                                convert_to_media_source_info(
-                                    ms,
-                                    &item_id,
-                                    episode.runtime_ticks.clone(),
-                                )
-                            }));
-                        }
-                    }
-                }
-            }
-        }
-
-        if sources.len() > 0 {
-            break;
+        Some((_, Item::Episode(episode))) => {
+            sources.extend(episode.media_sources.iter().map(|ms| {
+                convert_to_media_source_info(ms, &item_id, episode.runtime_ticks.clone())
+            }));
         }
+        _ => {}
     }
 
     if sources.len() > 0 {
         let response = PlaybackInfoResponse {
             media_sources: sources,
-            play_session_id: "e3a869b7a901f8894de8ee65688db6c0".to_string(),
+            play_session_id: uuid::Uuid::new_v4().simple().to_string(),
         };
 
         let bytes = serde_json::to_vec(&response).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
@@ -632,18 +615,27 @@ pub async fn get_similar_items(
     State(state): State<AppState>,
     Path(item_id): Path<String>,
     Query(params): Query<QueryParams>,
+    req: Request<axum::body::Body>,
 ) -> Json<QueryResult<BaseItemDto>> {
     let limit = params
         .get("limit")
         .and_then(|s| s.parse::<usize>().ok())
         .unwrap_or(20);
+    let access = get_library_access(&req);
+    if access.get_item(&state.collections, &item_id).is_none() {
+        return Json(QueryResult {
+            total_record_count: 0,
+            start_index: 0,
+            items: vec![],
+        });
+    }
 
     let results = state
         .collections
         .find_similar(&item_id, limit)
         .unwrap_or_default();
 
-    let collections = state.collections.list_collections().await;
+    let collections = access.list_collections(&state.collections).await;
     let mut items = Vec::new();
     let server_id = state.config.jellyfin.server_id.clone().unwrap_or_default();
 
@@ -660,6 +652,8 @@ pub async fn get_similar_items(
         }
     }
 
+    let items = apply_parental_filter(items, &access, &state.collections);
+
     Json(QueryResult {
         total_record_count: items.len(),
         start_index: 0,
@@ -696,6 +690,28 @@ pub struct ImageParams {
     #[serde(rename = "type")]
     image_type: Option<String>,
     tag: Option<String>,
+    #[serde(alias = "Width")]
+    width: Option<u32>,
+    #[serde(alias = "Height")]
+    height: Option<u32>,
+    #[serde(rename = "maxWidth", alias = "MaxWidth")]
+    max_width: Option<u32>,
+    #[serde(rename = "maxHeight", alias = "MaxHeight")]
+    max_height: Option<u32>,
+    #[serde(rename = "fillWidth", alias = "FillWidth")]
+    fill_width: Option<u32>,
+    #[serde(rename = "fillHeight", alias = "FillHeight")]
+    fill_height: Option<u32>,
+    #[serde(alias = "Quality")]
+    quality: Option<u32>,
+    #[serde(alias = "Format")]
+    format: Option<String>,
+    #[serde(alias = "Blur")]
+    blur: Option<u32>,
+    #[serde(rename = "backgroundColor", alias = "BackgroundColor")]
+    background_color: Option<String>,
+    #[serde(rename = "cropWhitespace", alias = "CropWhitespace")]
+    crop_whitespace: Option<bool>,
 }
 
 pub async fn get_image(
@@ -704,66 +720,190 @@ pub async fn get_image(
     Query(params): Query<ImageParams>,
     req: http::Request<axum::body::Body>,
 ) -> Result<Response, StatusCode> {
-    if let Some(tag) = params.tag {
-        // Jellyfin redirect tag.
-        if let Some(url) = tag.strip_prefix("redirect_") {
-            return Ok(Redirect::to(url).into_response().map(axum::body::Body::new));
-        }
+    serve_image(state, item_id, image_type, 0, params, req).await
+}
 
-        // Jellyfin 'open local file' tag.
-        if let Some(file) = tag.strip_prefix("file_") {
-            // Let's only allow images, shall we.
-            const IMAGE_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "webp"];
-            if let Some((_, ext)) = file.rsplit_once(".") {
-                if IMAGE_EXTENSIONS.contains(&ext) {
-                    let service = ServeFile::new(file);
-                    let response = service
-                        .oneshot(req)
-                        .await
-                        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-                    return Ok(response.map(axum::body::Body::new));
-                }
+/// Backdrops are numbered from 0; other image types only have index 0.
+pub async fn get_image_indexed(
+    State(state): State<AppState>,
+    Path((item_id, image_type, index)): Path<(String, String, usize)>,
+    Query(params): Query<ImageParams>,
+    req: http::Request<axum::body::Body>,
+) -> Result<Response, StatusCode> {
+    serve_image(state, item_id, image_type, index, params, req).await
+}
+
+async fn serve_image(
+    state: AppState,
+    item_id: String,
+    image_type: String,
+    index: usize,
+    params: ImageParams,
+    req: http::Request<axum::body::Body>,
+) -> Result<Response, StatusCode> {
+    // Without an image, the collection may serve a generated one.
+    let image = find_image(&state.collections, &item_id, &image_type, index);
+    let placeholder = match image {
+        Some(_) => None,
+        None => Some(
+            find_placeholder(&state.collections, &item_id, &image_type, index)
+                .ok_or(StatusCode::NOT_FOUND)?,
+        ),
+    };
+    let current_tag = match (&image, &placeholder) {
+        (Some((_, meta)), _) => meta.as_ref().map(|meta| meta.tag.clone()),
+        (None, placeholder) => placeholder.as_ref().map(|p| p.tag()),
+    };
+
+    // Clients load images without credentials. Such requests get what is
+    // public, or an image whose current tag they know: tags are content
+    // hashes that are only handed out in the DTOs of visible items.
+    match req.extensions().get::<LibraryAccess>() {
+        Some(access) => {
+            access
+                .get_item(&state.collections, &item_id)
+                .ok_or(StatusCode::NOT_FOUND)?;
+        }
+        None => {
+            let tagged = params.tag.is_some() && params.tag == current_tag;
+            if !tagged
+                && public_library_access(&state)
+                    .await
+                    .get_item(&state.collections, &item_id)
+                    .is_none()
+            {
+                return Err(StatusCode::UNAUTHORIZED);
             }
-            return Err(StatusCode::NOT_FOUND);
         }
     }
 
-    let image_path =
-        find_image_path(&state.collections, &item_id, &image_type).ok_or(StatusCode::NOT_FOUND)?;
-
-    let quality = match params.image_type.as_deref() {
+    let poster_quality = match params.image_type.as_deref() {
         Some("primary") | Some("logo") => state.config.jellyfin.image_quality_poster,
         _ => None,
     };
 
-    let serve_path = state
-        .image_resizer
-        .resize_image(&image_path, None, None, quality)
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    // An explicit format wins; otherwise use what the client accepts.
+    let negotiated = params.format.is_none();
+    let format = match params.format.as_deref() {
+        Some(format) => OutputFormat::parse(format),
+        None => req
+            .headers()
+            .get(header::ACCEPT)
+            .and_then(|v| v.to_str().ok())
+            .and_then(OutputFormat::from_accept),
+    };
+
+    let options = ResizeOptions {
+        width: params.width,
+        height: params.height,
+        max_width: params.max_width,
+        max_height: params.max_height,
+        fill_width: params.fill_width,
+        fill_height: params.fill_height,
+        quality: poster_quality.or(params.quality),
+        format,
+        blur: params.blur,
+        background_color: params.background_color.as_deref().and_then(parse_color),
+        crop_whitespace: params.crop_whitespace.unwrap_or(false),
+    };
+
+    // Placeholders are rendered as png.
+    let source_extension = match &image {
+        Some((path, _)) => path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or("jpg"),
+        None => "png",
+    };
+    let extension = options.extension(source_extension).to_string();
+
+    // The URL names this exact version of the image, so it never changes.
+    // The extension tells apart the formats negotiated for one URL.
+    let etag = match (&params.tag, &current_tag) {
+        (Some(tag), Some(current_tag)) if tag == current_tag => {
+            header::HeaderValue::from_str(&format!("\"{}.{}\"", tag, extension)).ok()
+        }
+        _ => None,
+    };
+    let immutable = header::HeaderValue::from_static("public, max-age=31536000, immutable");
+
+    if let Some(etag) = &etag {
+        let if_none_match = req
+            .headers()
+            .get(header::IF_NONE_MATCH)
+            .and_then(|v| v.to_str().ok());
+        if if_none_match
+            .is_some_and(|client_etag| etags_match(client_etag, etag.to_str().unwrap_or_default()))
+        {
+            let mut response = StatusCode::NOT_MODIFIED.into_response();
+            let headers = response.headers_mut();
+            headers.insert(header::ETAG, etag.clone());
+            headers.insert(header::CACHE_CONTROL, immutable);
+            if negotiated {
+                headers.insert(header::VARY, header::HeaderValue::from_static("Accept"));
+            }
+            return Ok(response);
+        }
+    }
+
+    let (image_path, modified) = match (image, placeholder) {
+        (Some((path, meta)), _) => (path, meta.as_ref().and_then(last_modified)),
+        (None, Some(placeholder)) => match state.image_resizer.placeholder(&placeholder).await {
+            Ok(path) => (path, None),
+            Err(e) => return Ok(e.into_response()),
+        },
+        (None, None) => return Err(StatusCode::NOT_FOUND),
+    };
+
+    let serve_path = match state.image_resizer.resize(&image_path, &options).await {
+        Ok(path) => path,
+        Err(e) => return Ok(e.into_response()),
+    };
 
     // Use ServeFile for proper ETag and Range header support
     let service = ServeFile::new(serve_path);
-    let response = service
+    let mut response = service
         .oneshot(req)
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let headers = response.headers_mut();
+    if negotiated {
+        headers.insert(header::VARY, header::HeaderValue::from_static("Accept"));
+    }
+
+    match (etag, &params.tag) {
+        (Some(etag), _) => {
+            headers.insert(header::CACHE_CONTROL, immutable);
+            headers.insert(header::ETAG, etag);
+            if let Some(modified) = modified {
+                headers.insert(header::LAST_MODIFIED, modified);
+            }
+        }
+        // An outdated tag: serve the current image, but do not let clients
+        // keep it under this URL.
+        (None, Some(_)) => {
+            headers.insert(
+                header::CACHE_CONTROL,
+                header::HeaderValue::from_static("no-cache"),
+            );
+        }
+        (None, None) => {}
+    }
 
     Ok(response.map(axum::body::Body::new))
 }
 
-pub async fn get_image_indexed(
-    state: State<AppState>,
-    Path((item_id, image_type, _index)): Path<(String, String, String)>,
-    query: Query<ImageParams>,
-    req: http::Request<axum::body::Body>,
-) -> Result<Response, StatusCode> {
-    // Ignore index for now, as we don't support multiple images per type yet
-    get_image(state, Path((item_id, image_type)), query, req).await
+/// The mtime of a source image as an HTTP date.
+fn last_modified(meta: &ImageMeta) -> Option<header::HeaderValue> {
+    let mtime = chrono::DateTime::from_timestamp_millis(meta.mtime)?;
+    let date = mtime.format("%a, %d %b %Y %H:%M:%S GMT").to_string();
+    header::HeaderValue::from_str(&date).ok()
 }
 
 pub async fn get_suggestions(
     State(state): State<AppState>,
     Query(params): Query<QueryParams>,
+    req: Request<axum::body::Body>,
 ) -> Json<QueryResult<BaseItemDto>> {
     // Stub: Return latest items as suggestions for now
     let limit = params
@@ -779,7 +919,8 @@ pub async fn get_suggestions(
     let server_id = state.config.jellyfin.server_id.clone().unwrap_or_default();
 
     // Get all collections
-    let collections = state.collections.list_collections().await;
+    let access = get_library_access(&req);
+    let collections = access.list_collections(&state.collections).await;
     for collection in collections {
         let coll_id = &collection.id;
         for movie in collection.movies.values() {
@@ -804,10 +945,10 @@ pub async fn get_suggestions(
     // returning *something* valid is better than 404.
     // Let's return the latest items.
 
-    let items: Vec<BaseItemDto> = all_items
+    let items: Vec<BaseItemDto> = all_items.into_iter().map(|(_, dto)| dto).collect();
+    let items: Vec<BaseItemDto> = apply_parental_filter(items, &access, &state.collections)
         .into_iter()
         .take(limit)
-        .map(|(_, dto)| dto)
         .collect();
 
     Json(QueryResult {
@@ -832,6 +973,7 @@ pub async fn get_media_segments(
 pub async fn search_hints(
     State(state): State<AppState>,
     Query(params): Query<QueryParams>,
+    req: Request<axum::body::Body>,
 ) -> Json<QueryResult<SearchHint>> {
     let search_term = params
         .get("SearchTerm")
@@ -849,8 +991,10 @@ pub async fn search_hints(
         .search(search_term, limit)
         .unwrap_or_default();
 
+    let access = get_library_access(&req);
     let hints: Vec<SearchHint> = results
         .iter()
+        .filter(|r| access.get_item(&state.collections, &r.id).is_some())
         .map(|r| SearchHint {
             item_id: r.id.clone(),
             name: r.name.clone(),
@@ -873,6 +1017,7 @@ pub async fn get_resume_items(
     req: Request<axum::body::Body>,
 ) -> Result<Json<QueryResult<BaseItemDto>>, StatusCode> {
     let user_id = get_user_id(&req).ok_or(StatusCode::UNAUTHORIZED)?;
+    let access = get_library_access(&req);
 
     let limit = params
         .get("limit")
@@ -886,7 +1031,6 @@ pub async fn get_resume_items(
         .get_user_data_resume(&user_id, Some(limit as u32 * 2))
         .await
     {
-        let collections = state.collections.list_collections().await;
         let server_id = state
             .config
             .jellyfin
@@ -894,59 +1038,46 @@ pub async fn get_resume_items(
             .as_deref()
             .unwrap_or_default();
 
-        // Movies are easy and efficient to scan.
-        for collection in &collections {
-            for data in &db_user_data {
-                if let Some(movie) = collection.movies.get(&data.itemid) {
-                    let mut dto = convert_movie_to_dto(movie, &collection.id, server_id);
-                    dto.user_data = Some(UserData {
-                        playback_position_ticks: data.position.unwrap_or(0),
-                        played_percentage: data.playedpercentage.map(|p| p as f64).unwrap_or(0.0),
-                        play_count: data.playcount.unwrap_or(0),
-                        is_favorite: data.favorite.unwrap_or(false),
-                        last_played_date: data.timestamp.map(|t| t.to_rfc3339()),
-                        played: data.played.unwrap_or(false),
-                        key: data.itemid.clone(),
-                        unplayed_item_count: None,
-                    });
-                    resume_items.push(dto);
+        for data in &db_user_data {
+            let mut dto = match access.get_item(&state.collections, &data.itemid) {
+                Some((collection_id, Item::Movie(movie))) => {
+                    convert_movie_to_dto(&movie, &collection_id, server_id)
                 }
-            }
-
-            // Shows are more complex, as we need to scan the seasons and episodes.
-            for show in collection.shows.values() {
-                for season in show.seasons.values() {
-                    for episode in season.episodes.values() {
-                        for data in &db_user_data {
-                            if data.itemid == episode.id {
-                                let mut dto = convert_episode_to_dto(
-                                    episode,
-                                    &season.id,
-                                    &show.id,
-                                    &collection.id,
-                                    &season.name,
-                                    &show.name,
-                                    server_id,
-                                );
-                                dto.user_data = Some(UserData {
-                                    playback_position_ticks: data.position.unwrap_or(0),
-                                    played_percentage: data
-                                        .playedpercentage
-                                        .map(|p| p as f64)
-                                        .unwrap_or(0.0),
-                                    play_count: data.playcount.unwrap_or(0),
-                                    is_favorite: data.favorite.unwrap_or(false),
-                                    last_played_date: data.timestamp.map(|t| t.to_rfc3339()),
-                                    played: data.played.unwrap_or(false),
-                                    key: episode.id.clone(),
-                                    unplayed_item_count: None,
-                                });
-                                resume_items.push(dto);
-                            }
-                        }
-                    }
+                Some((collection_id, Item::Episode(episode))) => {
+                    let Some(collection) = state.collections.get_collection(&collection_id).await
+                    else {
+                        continue;
+                    };
+                    let Some(ItemRef::Season(season)) = collection.get_item(&episode.season_id)
+                    else {
+                        continue;
+                    };
+                    let Some(ItemRef::Show(show)) = collection.get_item(&episode.show_id) else {
+                        continue;
+                    };
+                    convert_episode_to_dto(
+                        &episode,
+                        &season.id,
+                        &show.id,
+                        &collection.id,
+                        &season.name,
+                        &show.name,
+                        server_id,
+                    )
                 }
-            }
+                _ => continue,
+            };
+            dto.user_data = Some(UserData {
+                playback_position_ticks: data.position.unwrap_or(0),
+                played_percentage: data.playedpercentage.map(|p| p as f64).unwrap_or(0.0),
+                play_count: data.playcount.unwrap_or(0),
+                is_favorite: data.favorite.unwrap_or(false),
+                last_played_date: data.timestamp.map(|t| t.to_rfc3339()),
+                played: data.played.unwrap_or(false),
+                key: data.itemid.clone(),
+                unplayed_item_count: None,
+            });
+            resume_items.push(dto);
         }
     }
 
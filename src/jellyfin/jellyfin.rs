@@ -1,10 +1,13 @@
 use crate::server::AppState;
+use axum::extract::DefaultBodyLimit;
 use axum::routing::{delete, get, post};
 use axum::Router;
 
 pub fn build_jellyfin_router(state: AppState) -> Router<AppState> {
     #[cfg_attr(any(), rustfmt::skip)]
     Router::new()
+        .route("/Auth/Keys", get(super::apikey::get_keys).post(super::apikey::create_key))
+        .route("/Auth/Keys/:key", delete(super::apikey::revoke_key))
         .route("/Branding/Configuration", get(super::branding::get_branding_configuration))
         .route("/Branding/Css", get(super::branding::get_branding_css))
         .route("/Branding/Css.css", get(super::branding::get_branding_css))
@@ -23,15 +26,29 @@ pub fn build_jellyfin_router(state: AppState) -> Router<AppState> {
         .route("/Items/:id/Similar", get(super::item::get_similar_items))
         .route("/Items/:id/SpecialFeatures", get(super::item::get_special_features))
         .route("/Items/:id/ThemeSongs", get(super::item::get_theme_songs))
-        .route("/Items/:item_id/Images/:image_type", get(super::item::get_image))
-        .route("/Items/:item_id/Images/:image_type/:index", get(super::item::get_image_indexed))
+        .route(
+            "/Items/:item_id/Images/:image_type",
+            get(super::item::get_image)
+                .post(super::image::upload_image)
+                .delete(super::image::delete_image)
+                .layer(DefaultBodyLimit::max(super::image::MAX_IMAGE_UPLOAD)),
+        )
+        .route(
+            "/Items/:item_id/Images/:image_type/:index",
+            get(super::item::get_image_indexed)
+                .post(super::image::upload_image_indexed)
+                .delete(super::image::delete_image_indexed)
+                .layer(DefaultBodyLimit::max(super::image::MAX_IMAGE_UPLOAD)),
+        )
         .route("/Items/Counts", get(super::item::get_item_counts))
         .route("/Items/Latest", get(super::item::get_latest_items))
         .route("/Items/Suggestions", get(super::item::get_suggestions))
+        .route("/Library/Refresh", post(super::library::refresh_library))
         .route("/Library/VirtualFolders", get(super::library::get_virtual_folders))
         .route("/Localization/Countries", get(super::localization::get_countries))
         .route("/Localization/Cultures", get(super::localization::get_cultures))
         .route("/Localization/Options", get(super::localization::get_localization_options))
+        .route("/Localization/ParentalRatings", get(super::localization::get_parental_ratings))
         .route("/MediaSegments/:id", get(super::item::get_media_segments))
         .route("/Movies/Recommendations", get(super::movie::get_movie_recommendations))
         .route("/Persons", get(super::person::get_persons))
@@ -52,30 +69,66 @@ pub fn build_jellyfin_router(state: AppState) -> Router<AppState> {
         .route("/Playlists/:playlist_id/Items/:item_id/Move/:new_index", get(super::playlist::move_playlist_item))
         .route("/Playlists/:playlist_id/Users/:user_id", get(super::playlist::get_playlist_user))
         .route("/Plugins", get(super::system::plugins))
-        .route("/QuickConnect/Authorize", get(super::auth::quick_connect_authorize))
+        .route("/QuickConnect/Authorize", post(super::auth::quick_connect_authorize))
         .route("/QuickConnect/Connect", get(super::auth::quick_connect_connect))
         .route("/QuickConnect/Enabled", get(super::auth::quick_connect_enabled))
         .route("/QuickConnect/Initiate", post(super::auth::quick_connect_initiate))
         .route("/Search/Hints", get(super::item::search_hints))
+        .route("/GetUtcTime", get(super::syncplay::get_utc_time))
+        .route("/SyncPlay/List", get(super::syncplay::list_groups))
+        .route("/SyncPlay/New", post(super::syncplay::new_group))
+        .route("/SyncPlay/Join", post(super::syncplay::join_group))
+        .route("/SyncPlay/Leave", post(super::syncplay::leave_group))
+        .route("/SyncPlay/SetNewQueue", post(super::syncplay::set_new_queue))
+        .route("/SyncPlay/SetPlaylistItem", post(super::syncplay::set_playlist_item))
+        .route("/SyncPlay/RemoveFromPlaylist", post(super::syncplay::remove_from_playlist))
+        .route("/SyncPlay/MovePlaylistItem", post(super::syncplay::move_playlist_item))
+        .route("/SyncPlay/Queue", post(super::syncplay::queue))
+        .route("/SyncPlay/Unpause", post(super::syncplay::unpause))
+        .route("/SyncPlay/Pause", post(super::syncplay::pause))
+        .route("/SyncPlay/Stop", post(super::syncplay::stop))
+        .route("/SyncPlay/Seek", post(super::syncplay::seek))
+        .route("/SyncPlay/Buffering", post(super::syncplay::buffering))
+        .route("/SyncPlay/Ready", post(super::syncplay::ready))
+        .route("/SyncPlay/SetIgnoreWait", post(super::syncplay::set_ignore_wait))
+        .route("/SyncPlay/NextItem", post(super::syncplay::next_item))
+        .route("/SyncPlay/PreviousItem", post(super::syncplay::previous_item))
+        .route("/SyncPlay/SetRepeatMode", post(super::syncplay::set_repeat_mode))
+        .route("/SyncPlay/SetShuffleMode", post(super::syncplay::set_shuffle_mode))
+        .route("/SyncPlay/Ping", post(super::syncplay::ping))
+        .route("/SyncPlay/:id", get(super::syncplay::get_group))
+        .route("/socket", get(super::websocket::websocket_handler))
         .route("/Sessions", get(super::session::get_sessions))
         .route("/Sessions/Capabilities", post(super::session::post_session_capabilities))
         .route("/Sessions/Capabilities/Full", post(super::session::post_session_capabilities_full))
+        .route("/Sessions/Logout", post(super::auth::logout))
         .route("/Sessions/Playing", post(super::userdata::session_playing_progress))
         .route("/Sessions/Playing/Progress", post(super::userdata::session_playing_progress))
-        .route("/Sessions/Playing/Stopped", post(super::userdata::session_playing_progress))
+        .route("/Sessions/Playing/Stopped", post(super::userdata::session_playing_stopped))
+        .route("/Sessions/:session_id/Playing", post(super::session::play))
+        .route("/Sessions/:session_id/Playing/:command", post(super::session::send_playstate_command))
+        .route("/Sessions/:session_id/Command", post(super::session::send_general_command))
+        .route("/Sessions/:session_id/Command/:command", post(super::session::send_named_command))
+        .route("/Sessions/:session_id/Message", post(super::session::send_message_command))
         .route("/PlayingItems/:id", delete(super::userdata::delete_playing_item))
         .route("/Shows/:id/Episodes", get(super::show::get_episodes))
         .route("/Shows/:id/Seasons", get(super::show::get_seasons))
         .route("/Shows/NextUp", get(super::show::get_next_up))
         .route("/Studios", get(super::studio::get_studios))
         .route("/Studios/:name", get(super::studio::get_studio_by_name))
+        .route("/System/ImageCache", get(super::system::get_image_cache).delete(super::system::clear_image_cache))
         .route("/System/Info", get(super::system::system_info))
         .route("/System/Info/Public", get(super::system::public_system_info))
         .route("/System/Ping", get(super::system::system_ping_handler))
         .route("/UserViews", get(super::user::get_user_views))
         .route("/Users", get(super::user::get_users))
         .route("/Users/AuthenticateByName", post(super::auth::authenticate_by_name))
+        .route("/Users/AuthenticateWithQuickConnect", post(super::auth::authenticate_with_quick_connect))
         .route("/Users/Me", get(super::user::get_current_user))
+        .route("/Users/New", post(super::user::new_user))
+        .route("/Users/:user_id", get(super::user::get_user).delete(super::user::delete_user))
+        .route("/Users/:user_id/Password", post(super::user::update_password))
+        .route("/Users/:user_id/Policy", post(super::user::update_policy))
         .route("/Users/:user_id/FavoriteItems/:id", post(super::userdata::mark_favorite))
         .route("/Users/:user_id/FavoriteItems/:id", delete(super::userdata::unmark_favorite))
         .route("/Users/:user_id/Images/:image_type", get(super::user::get_user_image))
@@ -1,8 +1,10 @@
 use std::collections::HashMap;
+use std::path::PathBuf;
 
 use super::types::*;
 use super::userdata::get_default_user_data;
-use crate::collection::item::MediaSource;
+use crate::collection::item::{ImageInfo, MediaSource};
+use crate::collection::{item_placeholder, ItemRef};
 
 pub fn convert_media_sources(
     sources: &[crate::collection::MediaSource],
@@ -25,19 +27,9 @@ pub fn convert_movie_to_dto(
     parent_id: &str,
     server_id: &str,
 ) -> BaseItemDto {
-    let mut image_tags = HashMap::new();
-    if movie.images.primary.is_some() {
-        image_tags.insert("Primary".to_string(), movie.id.clone());
-    }
-    if movie.images.backdrop.is_some() {
-        image_tags.insert("Backdrop".to_string(), movie.id.clone());
-    }
-
-    let backdrop_image_tags = if movie.images.backdrop.is_some() {
-        Some(vec![movie.id.clone()])
-    } else {
-        None
-    };
+    let mut image_tags = convert_image_tags(&movie.images, &movie.id, None);
+    add_placeholder_tag(&mut image_tags, &movie.images, ItemRef::Movie(movie));
+    let backdrop_image_tags = convert_backdrop_image_tags(&movie.images, &movie.id);
 
     let provider_ids = HashMap::new();
 
@@ -92,7 +84,7 @@ pub fn convert_movie_to_dto(
         video_type: Some("VideoFile".to_string()),
         width: Some(1920),
         height: Some(1080),
-        image_blur_hashes: None,
+        image_blur_hashes: convert_blur_hashes(&movie.images, &movie.id, None),
         media_type: Some("Video".to_string()),
         is_hd: Some(true),
         is_4k: Some(false),
@@ -105,7 +97,7 @@ pub fn convert_movie_to_dto(
         media_sources: convert_media_sources(&movie.media_sources, &movie.id),
         provider_ids: Some(provider_ids),
         recursive_item_count: None,
-        official_rating: None,
+        official_rating: movie.mpaa.clone(),
         sort_name: Some(movie.name.to_lowercase()),
         forced_sort_name: Some(movie.name.to_lowercase()),
         original_title: Some(movie.name.clone()),
@@ -124,19 +116,9 @@ pub fn convert_show_to_dto(
     parent_id: &str,
     server_id: &str,
 ) -> BaseItemDto {
-    let mut image_tags = HashMap::new();
-    if show.images.primary.is_some() {
-        image_tags.insert("Primary".to_string(), show.id.clone());
-    }
-    if show.images.backdrop.is_some() {
-        image_tags.insert("Backdrop".to_string(), show.id.clone());
-    }
-
-    let backdrop_image_tags = if show.images.backdrop.is_some() {
-        Some(vec![show.id.clone()])
-    } else {
-        None
-    };
+    let mut image_tags = convert_image_tags(&show.images, &show.id, None);
+    add_placeholder_tag(&mut image_tags, &show.images, ItemRef::Show(show));
+    let backdrop_image_tags = convert_backdrop_image_tags(&show.images, &show.id);
 
     let provider_ids = HashMap::new();
 
@@ -180,7 +162,7 @@ pub fn convert_show_to_dto(
         video_type: None,
         width: None,
         height: None,
-        image_blur_hashes: None,
+        image_blur_hashes: convert_blur_hashes(&show.images, &show.id, None),
         media_type: Some("Video".to_string()),
         is_hd: None,
         is_4k: None,
@@ -198,7 +180,7 @@ pub fn convert_show_to_dto(
                 .map(|(_, s)| s.episodes.len() as i32)
                 .sum(),
         ),
-        official_rating: Some("TV-MA".to_string()),
+        official_rating: show.mpaa.clone(),
         sort_name: Some(show.name.to_lowercase()),
         forced_sort_name: Some(show.name.to_lowercase()),
         original_title: Some(show.name.clone()),
@@ -227,10 +209,8 @@ pub fn convert_season_to_dto(
     series_name: &str,
     server_id: &str,
 ) -> BaseItemDto {
-    let mut image_tags = HashMap::new();
-    if season.images.primary.is_some() {
-        image_tags.insert("Primary".to_string(), season.id.clone());
-    }
+    let mut image_tags = convert_image_tags(&season.images, &season.id, None);
+    add_placeholder_tag(&mut image_tags, &season.images, ItemRef::Season(season));
 
     BaseItemDto {
         name: season.name.clone(),
@@ -264,7 +244,7 @@ pub fn convert_season_to_dto(
         video_type: None,
         width: None,
         height: None,
-        image_blur_hashes: None,
+        image_blur_hashes: convert_blur_hashes(&season.images, &season.id, None),
         media_type: Some("Video".to_string()),
         is_hd: None,
         is_4k: None,
@@ -300,10 +280,9 @@ pub fn convert_episode_to_dto(
     series_name: &str,
     server_id: &str,
 ) -> BaseItemDto {
-    let mut image_tags = HashMap::new();
-    if episode.images.primary.is_some() || episode.images.thumb.is_some() {
-        image_tags.insert("Primary".to_string(), episode.id.clone());
-    }
+    let mut image_tags =
+        convert_image_tags(&episode.images, &episode.id, episode.images.thumb.as_ref());
+    add_placeholder_tag(&mut image_tags, &episode.images, ItemRef::Episode(episode));
 
     BaseItemDto {
         name: episode.name.clone(),
@@ -336,7 +315,11 @@ pub fn convert_episode_to_dto(
         video_type: Some("VideoFile".to_string()),
         width: Some(1920),
         height: Some(1080),
-        image_blur_hashes: None,
+        image_blur_hashes: convert_blur_hashes(
+            &episode.images,
+            &episode.id,
+            episode.images.thumb.as_ref(),
+        ),
         media_type: Some("Video".to_string()),
         is_hd: Some(true),
         is_4k: Some(false),
@@ -364,6 +347,100 @@ pub fn convert_episode_to_dto(
     }
 }
 
+/// The images of an item by Jellyfin image type. `primary_fallback` is
+/// used when the item has no primary image, like an episode's thumb.
+fn images_by_type<'a>(
+    images: &'a ImageInfo,
+    primary_fallback: Option<&'a PathBuf>,
+) -> [(&'static str, Option<&'a PathBuf>); 5] {
+    [
+        ("Primary", images.primary.as_ref().or(primary_fallback)),
+        ("Backdrop", images.backdrops.first()),
+        ("Logo", images.logo.as_ref()),
+        ("Thumb", images.thumb.as_ref()),
+        ("Banner", images.banner.as_ref()),
+    ]
+}
+
+/// Tag of an image, which changes when the file does. Images the scan has
+/// not hashed yet fall back to the item ID.
+fn image_tag(images: &ImageInfo, path: &PathBuf, item_id: &str) -> String {
+    images.tag(path).unwrap_or(item_id).to_string()
+}
+
+fn convert_image_tags(
+    images: &ImageInfo,
+    item_id: &str,
+    primary_fallback: Option<&PathBuf>,
+) -> HashMap<String, String> {
+    images_by_type(images, primary_fallback)
+        .into_iter()
+        .filter_map(|(image_type, path)| {
+            Some((image_type.to_string(), image_tag(images, path?, item_id)))
+        })
+        .collect()
+}
+
+/// Without a primary image, the tag of the generated one, if the item's
+/// collection serves those.
+fn add_placeholder_tag(
+    image_tags: &mut HashMap<String, String>,
+    images: &ImageInfo,
+    item: ItemRef,
+) {
+    if images.placeholder && !image_tags.contains_key("Primary") {
+        image_tags.insert("Primary".to_string(), item_placeholder(item).tag());
+    }
+}
+
+/// Tags of all backdrops, in order; clients request them by index.
+fn convert_backdrop_image_tags(images: &ImageInfo, item_id: &str) -> Option<Vec<String>> {
+    if images.backdrops.is_empty() {
+        return None;
+    }
+    let tags = images
+        .backdrops
+        .iter()
+        .map(|backdrop| image_tag(images, backdrop, item_id))
+        .collect();
+    Some(tags)
+}
+
+/// BlurHashes of an item's images, keyed by image tag.
+fn convert_blur_hashes(
+    images: &ImageInfo,
+    item_id: &str,
+    primary_fallback: Option<&PathBuf>,
+) -> Option<ImageBlurHashes> {
+    if images.meta.is_empty() {
+        return None;
+    }
+    let primary = images.primary.as_ref().or(primary_fallback);
+    Some(ImageBlurHashes {
+        primary: blur_hashes_by_tag(images, item_id, primary),
+        art: None,
+        backdrop: blur_hashes_by_tag(images, item_id, &images.backdrops),
+        banner: blur_hashes_by_tag(images, item_id, &images.banner),
+        logo: blur_hashes_by_tag(images, item_id, &images.logo),
+        thumb: blur_hashes_by_tag(images, item_id, &images.thumb),
+    })
+}
+
+fn blur_hashes_by_tag<'a>(
+    images: &ImageInfo,
+    item_id: &str,
+    paths: impl IntoIterator<Item = &'a PathBuf>,
+) -> Option<HashMap<String, String>> {
+    let hashes: HashMap<String, String> = paths
+        .into_iter()
+        .filter_map(|path| {
+            let hash = images.blur_hash(path)?;
+            Some((image_tag(images, path, item_id), hash.to_string()))
+        })
+        .collect();
+    (!hashes.is_empty()).then_some(hashes)
+}
+
 pub fn convert_to_media_source_info(
     ms: &MediaSource,
     item_id: &str,
@@ -1,6 +1,12 @@
-use axum::{extract::State, http::StatusCode, Json};
+use axum::{
+    extract::State,
+    http::{Request, StatusCode},
+    Json,
+};
 use serde::{Deserialize, Serialize};
 
+use super::access::get_library_access;
+use super::auth::{get_user_id, require_admin};
 use crate::server::AppState;
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -22,13 +28,32 @@ pub struct LibraryOptions {
     pub enabled: bool,
 }
 
+/// POST /Library/Refresh
+/// Administrator only; rescans all collections in the background.
+pub async fn refresh_library(
+    State(state): State<AppState>,
+    req: Request<axum::body::Body>,
+) -> Result<StatusCode, StatusCode> {
+    let user_id = get_user_id(&req).ok_or(StatusCode::UNAUTHORIZED)?;
+    require_admin(&state, &user_id).await?;
+    let collections = state.collections.clone();
+    tokio::spawn(async move {
+        if let Err(e) = collections.scan_all().await {
+            tracing::error!("Library refresh failed: {}", e);
+        }
+    });
+    Ok(StatusCode::NO_CONTENT)
+}
+
 pub async fn get_virtual_folders(
     State(state): State<AppState>,
+    req: Request<axum::body::Body>,
 ) -> Result<Json<Vec<VirtualFolderInfo>>, StatusCode> {
     let mut folders = Vec::new();
 
     // Map existing collections to VirtualFolders
-    for collection in state.collections.list_collections().await {
+    let access = get_library_access(&req);
+    for collection in access.list_collections(&state.collections).await {
         folders.push(VirtualFolderInfo {
             name: collection.name.clone(),
             locations: vec![collection.directory.to_string_lossy().to_string()], // Assuming single location for now
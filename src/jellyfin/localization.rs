@@ -1,3 +1,4 @@
+use crate::collection::parental_ratings;
 use crate::server::AppState;
 use axum::{extract::State, http::StatusCode, Json};
 use serde::{Deserialize, Serialize};
@@ -34,6 +35,14 @@ pub struct LocalizationOption {
     pub value: String,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ParentalRating {
+    #[serde(rename = "Name")]
+    pub name: String,
+    #[serde(rename = "Value")]
+    pub value: u32,
+}
+
 pub async fn get_cultures(
     State(_state): State<AppState>,
 ) -> Result<Json<Vec<CultureDto>>, StatusCode> {
@@ -153,3 +162,15 @@ pub async fn get_localization_options(
 ) -> Result<Json<Vec<LocalizationOption>>, StatusCode> {
     Ok(Json(vec![]))
 }
+
+/// The ratings understood in NFO files and the age each maps to; a user's
+/// MaxParentalRating is one of these ages.
+pub async fn get_parental_ratings(
+    State(_state): State<AppState>,
+) -> Result<Json<Vec<ParentalRating>>, StatusCode> {
+    let ratings = parental_ratings()
+        .into_iter()
+        .map(|(name, value)| ParentalRating { name, value })
+        .collect();
+    Ok(Json(ratings))
+}
@@ -0,0 +1,188 @@
+use chrono::{DateTime, Duration, Utc};
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+/// Wait after the first failed login; it doubles with every further failure.
+const BASE_DELAY: Duration = Duration::seconds(1);
+/// Longest wait between two attempts.
+const MAX_DELAY: Duration = Duration::minutes(15);
+/// Failures are forgotten after this long without a new one.
+const FORGET_AFTER: Duration = Duration::hours(24);
+
+/// Consecutive failed logins for one key.
+struct Failures {
+    count: u32,
+    last: DateTime<Utc>,
+}
+
+/// In-memory record of failed logins, keyed by user name and by remote
+/// address. After each failure, further attempts for the same key are
+/// refused for an exponentially growing delay.
+pub struct LoginAttemptManager {
+    failures: RwLock<HashMap<String, Failures>>,
+}
+
+impl Default for LoginAttemptManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LoginAttemptManager {
+    pub fn new() -> Self {
+        Self {
+            failures: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Keys for a login attempt: the user name and, if known, the address.
+    pub fn keys(username: &str, remote_address: &str) -> Vec<String> {
+        let mut keys = vec![format!("user:{}", username)];
+        if !remote_address.is_empty() {
+            keys.push(format!("ip:{}", remote_address));
+        }
+        keys
+    }
+
+    /// Start a login attempt for `keys`. Returns how long to wait instead
+    /// if one of the keys has to. The attempt counts as a failure until
+    /// `succeeded` is called, so that parallel attempts cannot all get past
+    /// the wait.
+    pub fn begin(&self, keys: &[String]) -> Result<(), Duration> {
+        let now = Utc::now();
+        let mut failures = self.failures.write().unwrap();
+        failures.retain(|_, f| now - f.last < FORGET_AFTER);
+
+        let wait = keys
+            .iter()
+            .filter_map(|key| failures.get(key))
+            .map(|f| f.last + delay(f.count) - now)
+            .max();
+        if let Some(wait) = wait.filter(|wait| *wait > Duration::zero()) {
+            return Err(wait);
+        }
+
+        for key in keys {
+            let f = failures.entry(key.clone()).or_insert(Failures {
+                count: 0,
+                last: now,
+            });
+            f.count += 1;
+            f.last = now;
+        }
+        Ok(())
+    }
+
+    /// The attempt started with `begin` succeeded: forget the failures of
+    /// the user name. The address only loses the failure of this attempt.
+    pub fn succeeded(&self, keys: &[String]) {
+        let Some((user_key, other_keys)) = keys.split_first() else {
+            return;
+        };
+        let mut failures = self.failures.write().unwrap();
+        failures.remove(user_key);
+        for key in other_keys {
+            if let Some(f) = failures.get_mut(key) {
+                f.count = f.count.saturating_sub(1);
+                if f.count == 0 {
+                    failures.remove(key);
+                }
+            }
+        }
+    }
+}
+
+fn delay(count: u32) -> Duration {
+    // 2^10 seconds is already past MAX_DELAY.
+    let factor = 1 << count.saturating_sub(1).min(10);
+    (BASE_DELAY * factor).min(MAX_DELAY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Pretend the last failure of `key` was `ago`.
+    fn set_last(manager: &LoginAttemptManager, key: &str, ago: Duration) {
+        let mut failures = manager.failures.write().unwrap();
+        failures.get_mut(key).unwrap().last = Utc::now() - ago;
+    }
+
+    fn count(manager: &LoginAttemptManager, key: &str) -> Option<u32> {
+        manager.failures.read().unwrap().get(key).map(|f| f.count)
+    }
+
+    #[test]
+    fn test_delay() {
+        assert_eq!(delay(1), Duration::seconds(1));
+        assert_eq!(delay(2), Duration::seconds(2));
+        assert_eq!(delay(4), Duration::seconds(8));
+        assert_eq!(delay(20), MAX_DELAY);
+    }
+
+    #[test]
+    fn test_parallel_attempts_are_refused() {
+        let manager = LoginAttemptManager::new();
+        let keys = LoginAttemptManager::keys("alice", "10.0.0.1");
+
+        assert!(manager.begin(&keys).is_ok());
+        let wait = manager.begin(&keys).unwrap_err();
+        assert!(wait > Duration::zero() && wait <= BASE_DELAY);
+
+        // Another user from the same address has to wait as well.
+        let other = LoginAttemptManager::keys("bob", "10.0.0.1");
+        assert!(manager.begin(&other).is_err());
+    }
+
+    #[test]
+    fn test_throttle_window() {
+        let manager = LoginAttemptManager::new();
+        let keys = LoginAttemptManager::keys("alice", "");
+
+        for _ in 0..3 {
+            assert!(manager.begin(&keys).is_ok());
+            set_last(&manager, "user:alice", Duration::hours(1));
+        }
+        assert_eq!(count(&manager, "user:alice"), Some(3));
+
+        // After three failures the wait is four seconds.
+        set_last(&manager, "user:alice", Duration::seconds(3));
+        let wait = manager.begin(&keys).unwrap_err();
+        assert!(wait > Duration::zero() && wait <= Duration::seconds(1));
+        set_last(&manager, "user:alice", Duration::seconds(4));
+        assert!(manager.begin(&keys).is_ok());
+        assert_eq!(count(&manager, "user:alice"), Some(4));
+    }
+
+    #[test]
+    fn test_failures_are_forgotten() {
+        let manager = LoginAttemptManager::new();
+        let keys = LoginAttemptManager::keys("alice", "");
+
+        assert!(manager.begin(&keys).is_ok());
+        set_last(&manager, "user:alice", FORGET_AFTER);
+        assert!(manager.begin(&keys).is_ok());
+        assert_eq!(count(&manager, "user:alice"), Some(1));
+    }
+
+    #[test]
+    fn test_reset_on_success() {
+        let manager = LoginAttemptManager::new();
+        let keys = LoginAttemptManager::keys("alice", "10.0.0.1");
+
+        assert!(manager.begin(&keys).is_ok());
+        set_last(&manager, "user:alice", Duration::minutes(1));
+        set_last(&manager, "ip:10.0.0.1", Duration::minutes(1));
+        assert!(manager.begin(&keys).is_ok());
+        manager.succeeded(&keys);
+
+        // The user name starts over; the address keeps the earlier failure.
+        assert_eq!(count(&manager, "user:alice"), None);
+        assert_eq!(count(&manager, "ip:10.0.0.1"), Some(1));
+
+        let keys = LoginAttemptManager::keys("bob", "10.0.0.2");
+        assert!(manager.begin(&keys).is_ok());
+        manager.succeeded(&keys);
+        assert_eq!(count(&manager, "ip:10.0.0.2"), None);
+    }
+}
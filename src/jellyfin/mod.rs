@@ -1,23 +1,32 @@
+pub mod access;
+pub mod apikey;
 pub mod auth;
 pub mod branding;
 pub mod device;
 pub mod filter;
 pub mod genre;
+pub mod image;
 pub mod item;
 pub mod jellyfin;
 pub mod jfitem;
 pub mod library;
 pub mod localization;
+pub mod loginattemptmanager;
 pub mod movie;
 pub mod pagination;
 pub mod person;
 pub mod playlist;
+pub mod quickconnectmanager;
 pub mod session;
+pub mod sessionmanager;
 pub mod show;
 pub mod sort;
 pub mod studio;
+pub mod syncplay;
+pub mod syncplaymanager;
 pub mod system;
 pub mod types;
 pub mod user;
 pub mod userdata;
 pub mod video;
+pub mod websocket;
@@ -2,10 +2,11 @@ use std::collections::HashSet;
 
 use axum::{
     extract::{Query, State},
-    http::StatusCode,
+    http::{Request, StatusCode},
     Json,
 };
 
+use super::access::get_library_access;
 use super::types::{BaseItemDto, NameIdPair, QueryResultNameIdPair};
 use crate::server::AppState;
 use crate::util::{generate_id, QueryParams};
@@ -28,10 +29,12 @@ pub async fn get_person_by_name(
 pub async fn get_persons(
     State(state): State<AppState>,
     Query(params): Query<QueryParams>,
+    req: Request<axum::body::Body>,
 ) -> Result<Json<QueryResultNameIdPair>, StatusCode> {
     let mut persons = HashSet::new();
 
-    for collection in state.collections.list_collections().await {
+    let access = get_library_access(&req);
+    for collection in access.list_collections(&state.collections).await {
         for movie in collection.movies.values() {
             for person in &movie.people {
                 persons.insert(person.name.clone());
@@ -6,9 +6,11 @@ use axum::{
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
 
+use super::access::get_library_access;
 use super::auth::get_user_id;
 use super::jfitem::{convert_episode_to_dto, convert_movie_to_dto};
 use super::types::*;
+use crate::collection::{Item, ItemRef};
 use crate::db::{Playlist as DbPlaylist, PlaylistRepo};
 use crate::server::AppState;
 use crate::util::QueryParams;
@@ -143,35 +145,37 @@ pub async fn get_playlist_items(
         .unwrap_or_default();
 
     let mut items = Vec::new();
+    let access = get_library_access(&req);
+
+    let server_id = state.config.jellyfin.server_id.clone().unwrap_or_default();
 
     for item_id in &item_ids {
-        for collection in state.collections.list_collections().await {
-            if let Some(movie) = collection.movies.get(item_id) {
-                let server_id = state.config.jellyfin.server_id.clone().unwrap_or_default();
-                items.push(convert_movie_to_dto(movie, &collection.id, &server_id));
-                break;
+        match access.get_item(&state.collections, item_id) {
+            Some((collection_id, Item::Movie(movie))) => {
+                items.push(convert_movie_to_dto(&movie, &collection_id, &server_id));
             }
-
-            for show in collection.shows.values() {
-                for season in show.seasons.values() {
-                    for episode in season.episodes.values() {
-                        if &episode.id == item_id {
-                            let server_id =
-                                state.config.jellyfin.server_id.clone().unwrap_or_default();
-                            items.push(convert_episode_to_dto(
-                                episode,
-                                &season.id,
-                                &show.id,
-                                &collection.id,
-                                &season.name,
-                                &show.name,
-                                &server_id,
-                            ));
-                            break;
-                        }
-                    }
-                }
+            Some((collection_id, Item::Episode(episode))) => {
+                let Some(collection) = state.collections.get_collection(&collection_id).await
+                else {
+                    continue;
+                };
+                let Some(ItemRef::Season(season)) = collection.get_item(&episode.season_id) else {
+                    continue;
+                };
+                let Some(ItemRef::Show(show)) = collection.get_item(&episode.show_id) else {
+                    continue;
+                };
+                items.push(convert_episode_to_dto(
+                    &episode,
+                    &season.id,
+                    &show.id,
+                    &collection.id,
+                    &season.name,
+                    &show.name,
+                    &server_id,
+                ));
             }
+            _ => {}
         }
     }
 
@@ -211,7 +215,11 @@ pub async fn add_playlist_items(
         })
         .unwrap_or_default();
 
+    let access = get_library_access(&req);
     for item_id in item_ids {
+        if access.get_item(&state.collections, &item_id).is_none() {
+            continue;
+        }
         let _ = state.db.add_item_to_playlist(&playlist_id, &item_id).await;
     }
 
@@ -0,0 +1,166 @@
+use chrono::{DateTime, Duration, Utc};
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use super::sessionmanager::ClientInfo;
+
+/// Requests that were not redeemed within this time are dropped.
+const REQUEST_TIMEOUT: Duration = Duration::minutes(10);
+
+/// Initiating a request needs no login, so the number of pending requests
+/// is capped. It is far below the number of codes, which keeps finding a
+/// free code cheap.
+const MAX_PENDING_REQUESTS: usize = 1000;
+
+/// So that a single client cannot take all of them.
+const MAX_PENDING_PER_ADDRESS: usize = 10;
+
+/// Attempts to find a code that is not in use.
+const MAX_CODE_ATTEMPTS: usize = 100;
+
+/// A pending QuickConnect login of a device.
+#[derive(Debug, Clone)]
+pub struct QuickConnectRequest {
+    /// Known only to the device that initiated the request.
+    pub secret: String,
+    /// Shown on the device and entered by the user on an already
+    /// logged-in client.
+    pub code: String,
+    pub client: ClientInfo,
+    pub date_added: DateTime<Utc>,
+    /// Set once a user authorized the code.
+    pub user_id: Option<String>,
+}
+
+/// In-memory registry of pending QuickConnect requests, keyed by secret.
+pub struct QuickConnectManager {
+    requests: RwLock<HashMap<String, QuickConnectRequest>>,
+}
+
+impl Default for QuickConnectManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl QuickConnectManager {
+    pub fn new() -> Self {
+        Self {
+            requests: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Start a request for `client`, replacing a pending one of the same device
+    /// from the same address. Returns None if too many requests are pending,
+    /// in total or from the address of `client`.
+    pub fn initiate(&self, client: &ClientInfo) -> Option<QuickConnectRequest> {
+        let mut requests = self.requests.write().unwrap();
+        let now = Utc::now();
+        requests.retain(|_, r| {
+            now - r.date_added < REQUEST_TIMEOUT
+                && (client.device_id.is_empty()
+                    || r.client.device_id != client.device_id
+                    || r.client.remote_address != client.remote_address)
+        });
+        if requests.len() >= MAX_PENDING_REQUESTS {
+            return None;
+        }
+        let from_address = requests
+            .values()
+            .filter(|r| r.client.remote_address == client.remote_address)
+            .count();
+        if from_address >= MAX_PENDING_PER_ADDRESS {
+            return None;
+        }
+
+        let code = (0..MAX_CODE_ATTEMPTS)
+            .map(|_| format!("{:06}", uuid::Uuid::new_v4().as_u128() % 1_000_000))
+            .find(|code| !requests.values().any(|r| &r.code == code))?;
+        let request = QuickConnectRequest {
+            secret: format!(
+                "{}{}",
+                uuid::Uuid::new_v4().simple(),
+                uuid::Uuid::new_v4().simple()
+            ),
+            code,
+            client: client.clone(),
+            date_added: now,
+            user_id: None,
+        };
+        requests.insert(request.secret.clone(), request.clone());
+        Some(request)
+    }
+
+    pub fn get(&self, secret: &str) -> Option<QuickConnectRequest> {
+        let requests = self.requests.read().unwrap();
+        requests
+            .get(secret)
+            .filter(|r| Utc::now() - r.date_added < REQUEST_TIMEOUT)
+            .cloned()
+    }
+
+    /// Let the device that shows `code` log in as `user_id`.
+    /// Returns false if there is no pending request with that code.
+    pub fn authorize(&self, code: &str, user_id: &str) -> bool {
+        let mut requests = self.requests.write().unwrap();
+        let now = Utc::now();
+        match requests
+            .values_mut()
+            .find(|r| r.code == code && now - r.date_added < REQUEST_TIMEOUT)
+        {
+            Some(request) => {
+                request.user_id = Some(user_id.to_string());
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Consume an authorized request. Returns the user to log in.
+    pub fn redeem(&self, secret: &str) -> Option<String> {
+        let mut requests = self.requests.write().unwrap();
+        let request = requests.get(secret)?;
+        if request.user_id.is_none() || Utc::now() - request.date_added >= REQUEST_TIMEOUT {
+            return None;
+        }
+        requests.remove(secret).and_then(|r| r.user_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client(device_id: &str, remote_address: &str) -> ClientInfo {
+        ClientInfo {
+            device_id: device_id.to_string(),
+            remote_address: remote_address.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_replace_same_device() {
+        let manager = QuickConnectManager::new();
+        let first = manager.initiate(&client("tv", "10.0.0.1")).unwrap();
+
+        // The same device id from elsewhere does not cancel the request.
+        manager.initiate(&client("tv", "10.0.0.2")).unwrap();
+        assert!(manager.get(&first.secret).is_some());
+
+        manager.initiate(&client("tv", "10.0.0.1")).unwrap();
+        assert!(manager.get(&first.secret).is_none());
+    }
+
+    #[test]
+    fn test_limit_per_address() {
+        let manager = QuickConnectManager::new();
+        for i in 0..MAX_PENDING_PER_ADDRESS {
+            assert!(manager
+                .initiate(&client(&i.to_string(), "10.0.0.1"))
+                .is_some());
+        }
+        assert!(manager.initiate(&client("other", "10.0.0.1")).is_none());
+        assert!(manager.initiate(&client("other", "10.0.0.2")).is_some());
+    }
+}
@@ -1,17 +1,21 @@
 use axum::{
-    extract::State,
+    extract::{Path, Query, State},
     http::{Request, StatusCode},
     Json,
 };
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use tracing::debug;
 
-use super::auth::get_user_id;
-use crate::db::{AccessTokenRepo, UserRepo};
+use super::access::{get_library_access, LibraryAccess};
+use super::auth::{get_session_id, get_user_id, require_admin};
+use super::item::fetch_item_by_id;
+use super::sessionmanager::{ClientCapabilities, QueueItem, Session, SessionId};
+use super::types::BaseItemDto;
+use super::websocket::OutboundMessage;
 use crate::server::AppState;
-
-const SESSION_ID: &str = "e3a869b7a901f8894de8ee65688db6c0";
+use crate::util::QueryParams;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SessionInfo {
@@ -23,6 +27,8 @@ pub struct SessionInfo {
     pub user_name: String,
     #[serde(rename = "LastActivityDate")]
     pub last_activity_date: DateTime<Utc>,
+    #[serde(rename = "LastPlaybackCheckIn")]
+    pub last_playback_check_in: DateTime<Utc>,
     #[serde(rename = "RemoteEndPoint")]
     pub remote_end_point: String,
     #[serde(rename = "DeviceName")]
@@ -45,143 +51,418 @@ pub struct SessionInfo {
     pub server_id: String,
     #[serde(rename = "AdditionalUsers")]
     pub additional_users: Vec<String>,
+    #[serde(rename = "Capabilities")]
+    pub capabilities: ClientCapabilities,
+    #[serde(rename = "NowPlayingItem", skip_serializing_if = "Option::is_none")]
+    pub now_playing_item: Option<BaseItemDto>,
     #[serde(rename = "PlayState")]
     pub play_state: SessionPlayState,
-    #[serde(rename = "Capabilities")]
-    pub capabilities: SessionCapabilities,
     #[serde(rename = "NowPlayingQueue")]
-    pub now_playing_queue: Vec<String>,
+    pub now_playing_queue: Vec<QueueItem>,
     #[serde(rename = "NowPlayingQueueFullItems")]
-    pub now_playing_queue_full_items: Vec<String>,
+    pub now_playing_queue_full_items: Vec<BaseItemDto>,
     #[serde(rename = "SupportedCommands")]
     pub supported_commands: Vec<String>,
     #[serde(rename = "PlayableMediaTypes")]
     pub playable_media_types: Vec<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
 pub struct SessionPlayState {
-    #[serde(rename = "RepeatMode")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub position_ticks: Option<i64>,
+    pub can_seek: bool,
+    pub is_paused: bool,
+    pub is_muted: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub volume_level: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub audio_stream_index: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subtitle_stream_index: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub media_source_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub play_method: Option<String>,
     pub repeat_mode: String,
-    #[serde(rename = "PlaybackOrder")]
     pub playback_order: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct SessionCapabilities {
-    #[serde(rename = "PlayableMediaTypes")]
-    pub playable_media_types: Vec<String>,
-    #[serde(rename = "SupportedCommands")]
-    pub supported_commands: Vec<String>,
-    #[serde(rename = "SupportsPersistentIdentifier")]
-    pub supports_persistent_identifier: bool,
-}
-
+/// GET /Sessions
 pub async fn get_sessions(
     State(state): State<AppState>,
+    Query(params): Query<QueryParams>,
     req: Request<axum::body::Body>,
 ) -> Result<Json<Vec<SessionInfo>>, StatusCode> {
     let user_id = get_user_id(&req).ok_or(StatusCode::UNAUTHORIZED)?;
+    let access = get_library_access(&req);
 
-    let user = state
-        .db
-        .get_user_by_id(&user_id)
-        .await
-        .map_err(|_| StatusCode::NOT_FOUND)?;
-
-    let tokens = state
-        .db
-        .list_tokens_by_user(&user_id)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-    // Keep most recent token per device ID only
-    let mut unique_tokens = HashMap::new();
-    for token in tokens {
-        if let Some(device_id) = &token.deviceid {
-            if let Some(&existing) = unique_tokens.get(device_id) {
-                if token.created.unwrap_or_else(|| chrono::Utc::now()) > existing {
-                    unique_tokens.insert(
-                        device_id.clone(),
-                        token.created.unwrap_or_else(|| chrono::Utc::now()),
-                    );
-                }
-            } else {
-                unique_tokens.insert(
-                    device_id.clone(),
-                    token.created.unwrap_or_else(|| chrono::Utc::now()),
-                );
-            }
-        }
-    }
-
-    // Get tokens again and filter to unique ones
-    let all_tokens = state
-        .db
-        .list_tokens_by_user(&user_id)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let controllable_by = params.get("controllableByUserId");
+    let device_id = params.get("deviceId");
+    let active_within = params
+        .get("activeWithinSeconds")
+        .and_then(|s| s.parse::<i64>().ok())
+        .map(chrono::Duration::seconds);
+    let now = Utc::now();
 
     let mut sessions = Vec::new();
-    for token in all_tokens {
-        if let Some(device_id) = &token.deviceid {
-            if let Some(&last_activity) = unique_tokens.get(device_id) {
-                if token.created.unwrap_or_else(|| chrono::Utc::now()) == last_activity {
-                    sessions.push(SessionInfo {
-                        id: SESSION_ID.to_string(),
-                        user_id: token.userid.clone(),
-                        user_name: user.username.clone(),
-                        last_activity_date: token.created.unwrap_or_else(|| chrono::Utc::now()),
-                        remote_end_point: String::new(),
-                        device_name: token.devicename.clone().unwrap_or_default(),
-                        device_id: token.deviceid.clone().unwrap_or_default(),
-                        client: token.applicationname.clone().unwrap_or_default(),
-                        application_version: token.applicationversion.clone().unwrap_or_default(),
-                        is_active: true,
-                        supports_media_control: false,
-                        supports_remote_control: false,
-                        has_custom_device_name: false,
-                        server_id: state
-                            .config
-                            .jellyfin
-                            .server_id
-                            .clone()
-                            .unwrap_or_else(|| "jellyfin-rs".to_string()),
-                        additional_users: vec![],
-                        play_state: SessionPlayState {
-                            repeat_mode: "RepeatNone".to_string(),
-                            playback_order: "Default".to_string(),
-                        },
-                        capabilities: SessionCapabilities {
-                            playable_media_types: vec![],
-                            supported_commands: vec![],
-                            supports_persistent_identifier: true,
-                        },
-                        now_playing_queue: vec![],
-                        now_playing_queue_full_items: vec![],
-                        supported_commands: vec![],
-                        playable_media_types: vec![],
-                    });
-                }
-            }
+    for session in visible_sessions(&state, &user_id).await {
+        if controllable_by.is_some_and(|uid| uid != session.user_id)
+            || device_id.is_some_and(|id| id != session.client.device_id)
+            || active_within.is_some_and(|d| now - session.last_activity > d)
+        {
+            continue;
+        }
+        let dto = session_to_dto(&state, &access, &session).await;
+        if controllable_by.is_some() && !dto.supports_remote_control {
+            continue;
         }
+        sessions.push(dto);
     }
 
     Ok(Json(sessions))
 }
 
+/// The sessions `user_id` may see. Administrators see all sessions, other
+/// users only their own.
+pub(crate) async fn visible_sessions(state: &AppState, user_id: &str) -> Vec<Session> {
+    let is_admin = require_admin(state, user_id).await.is_ok();
+    state
+        .sessions
+        .list()
+        .into_iter()
+        .filter(|session| is_admin || session.user_id == user_id)
+        .collect()
+}
+
+/// A session as seen by a user with `access`; the now playing item is left
+/// out if that user may not see it.
+pub(crate) async fn session_to_dto(
+    state: &AppState,
+    access: &LibraryAccess,
+    session: &Session,
+) -> SessionInfo {
+    let capabilities = session.capabilities.clone().unwrap_or_default();
+
+    let mut play_state = SessionPlayState {
+        repeat_mode: "RepeatNone".to_string(),
+        playback_order: "Default".to_string(),
+        ..Default::default()
+    };
+    let mut now_playing_item = None;
+    let mut now_playing_queue = Vec::new();
+
+    if let Some(np) = &session.now_playing {
+        now_playing_item = fetch_item_by_id(state, access, &np.item_id, Some(&session.user_id))
+            .await
+            .ok()
+            .map(|Json(dto)| dto);
+        play_state = SessionPlayState {
+            position_ticks: Some(np.position_ticks),
+            can_seek: np.can_seek,
+            is_paused: np.is_paused,
+            is_muted: np.is_muted,
+            volume_level: np.volume_level,
+            audio_stream_index: np.audio_stream_index,
+            subtitle_stream_index: np.subtitle_stream_index,
+            media_source_id: np.media_source_id.clone(),
+            play_method: np.play_method.clone(),
+            repeat_mode: np.repeat_mode.clone().unwrap_or(play_state.repeat_mode),
+            playback_order: np
+                .playback_order
+                .clone()
+                .unwrap_or(play_state.playback_order),
+        };
+        now_playing_queue = np.queue.clone();
+    }
+
+    SessionInfo {
+        id: session.id.clone(),
+        user_id: session.user_id.clone(),
+        user_name: session.user_name.clone(),
+        last_activity_date: session.last_activity,
+        last_playback_check_in: session
+            .last_playback_check_in
+            .unwrap_or(DateTime::<Utc>::MIN_UTC),
+        remote_end_point: session.client.remote_address.clone(),
+        device_name: session.client.device.clone(),
+        device_id: session.client.device_id.clone(),
+        client: session.client.client.clone(),
+        application_version: session.client.version.clone(),
+        is_active: true,
+        supports_media_control: capabilities.supports_media_control,
+        // Commands are delivered over the session's WebSocket.
+        supports_remote_control: capabilities.supports_media_control
+            && state.sessions.is_connected(&session.id),
+        has_custom_device_name: false,
+        server_id: state
+            .config
+            .jellyfin
+            .server_id
+            .clone()
+            .unwrap_or_else(|| "jellyfin-rs".to_string()),
+        additional_users: vec![],
+        now_playing_item,
+        play_state,
+        supported_commands: capabilities.supported_commands.clone(),
+        playable_media_types: capabilities.playable_media_types.clone(),
+        capabilities,
+        now_playing_queue,
+        now_playing_queue_full_items: vec![],
+    }
+}
+
+/// POST /Sessions/Capabilities
+/// Capabilities are passed as query parameters.
 pub async fn post_session_capabilities(
-    State(_state): State<AppState>,
-    _req: Request<axum::body::Body>,
+    State(state): State<AppState>,
+    Query(params): Query<QueryParams>,
+    req: Request<axum::body::Body>,
 ) -> Result<StatusCode, StatusCode> {
-    // Accept but ignore capabilities
+    let session_id = get_session_id(&req).ok_or(StatusCode::UNAUTHORIZED)?;
+
+    let list = |name: &str| -> Vec<String> {
+        params
+            .get(name)
+            .map(|v| {
+                v.split(',')
+                    .map(|s| s.trim().to_string())
+                    .filter(|s| !s.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    let flag = |name: &str| {
+        params
+            .get(name)
+            .is_some_and(|v| v.eq_ignore_ascii_case("true"))
+    };
+
+    let capabilities = ClientCapabilities {
+        playable_media_types: list("playableMediaTypes"),
+        supported_commands: list("supportedCommands"),
+        supports_media_control: flag("supportsMediaControl"),
+        supports_persistent_identifier: flag("supportsPersistentIdentifier"),
+    };
+    state.sessions.set_capabilities(&session_id, capabilities);
+
     Ok(StatusCode::NO_CONTENT)
 }
 
+/// POST /Sessions/Capabilities/Full
 pub async fn post_session_capabilities_full(
-    State(_state): State<AppState>,
-    _req: Request<axum::body::Body>,
+    State(state): State<AppState>,
+    axum::Extension(SessionId(session_id)): axum::Extension<SessionId>,
+    Json(capabilities): Json<ClientCapabilities>,
+) -> Result<StatusCode, StatusCode> {
+    state.sessions.set_capabilities(&session_id, capabilities);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Body of POST /Sessions/{id}/Command.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct GeneralCommand {
+    pub name: String,
+    #[serde(default)]
+    pub arguments: HashMap<String, String>,
+}
+
+/// Body of POST /Sessions/{id}/Message.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct MessageCommand {
+    #[serde(default)]
+    pub header: Option<String>,
+    pub text: String,
+    #[serde(default)]
+    pub timeout_ms: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct PlayRequest {
+    item_ids: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    start_position_ticks: Option<i64>,
+    play_command: String,
+    controlling_user_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    media_source_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    audio_stream_index: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    subtitle_stream_index: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    start_index: Option<i32>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct PlaystateRequest {
+    command: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seek_position_ticks: Option<i64>,
+    controlling_user_id: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct GeneralCommandMessage {
+    name: String,
+    controlling_user_id: String,
+    arguments: HashMap<String, String>,
+}
+
+const PLAYSTATE_COMMANDS: &[&str] = &[
+    "Stop",
+    "Pause",
+    "Unpause",
+    "NextTrack",
+    "PreviousTrack",
+    "Seek",
+    "Rewind",
+    "FastForward",
+    "PlayPause",
+];
+
+/// POST /Sessions/{id}/Playing
+/// Tell a session to play the items in `itemIds`.
+pub async fn play(
+    State(state): State<AppState>,
+    Path(session_id): Path<String>,
+    Query(params): Query<QueryParams>,
+    axum::Extension(user_id): axum::Extension<String>,
 ) -> Result<StatusCode, StatusCode> {
-    // Accept but ignore capabilities
+    let item_ids: Vec<String> = params
+        .get("itemIds")
+        .unwrap_or_default()
+        .split(',')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect();
+    if item_ids.is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let number = |name: &str| params.get(name).and_then(|s| s.parse().ok());
+
+    let request = PlayRequest {
+        item_ids,
+        start_position_ticks: number("startPositionTicks"),
+        play_command: params.get("playCommand").unwrap_or("PlayNow").to_string(),
+        controlling_user_id: user_id.clone(),
+        media_source_id: params.get("mediaSourceId").map(|s| s.to_string()),
+        audio_stream_index: number("audioStreamIndex").map(|n: i64| n as i32),
+        subtitle_stream_index: number("subtitleStreamIndex").map(|n: i64| n as i32),
+        start_index: number("startIndex").map(|n: i64| n as i32),
+    };
+    send_command(&state, &session_id, &user_id, "Play", &request)
+}
+
+/// POST /Sessions/{id}/Playing/{command}
+/// Pause, unpause, seek, stop or skip in a session.
+pub async fn send_playstate_command(
+    State(state): State<AppState>,
+    Path((session_id, command)): Path<(String, String)>,
+    Query(params): Query<QueryParams>,
+    axum::Extension(user_id): axum::Extension<String>,
+) -> Result<StatusCode, StatusCode> {
+    let command = PLAYSTATE_COMMANDS
+        .iter()
+        .find(|c| c.eq_ignore_ascii_case(&command))
+        .ok_or(StatusCode::BAD_REQUEST)?;
+
+    let request = PlaystateRequest {
+        command: command.to_string(),
+        seek_position_ticks: params.get("seekPositionTicks").and_then(|s| s.parse().ok()),
+        controlling_user_id: user_id.clone(),
+    };
+    send_command(&state, &session_id, &user_id, "Playstate", &request)
+}
+
+/// POST /Sessions/{id}/Command
+pub async fn send_general_command(
+    State(state): State<AppState>,
+    Path(session_id): Path<String>,
+    axum::Extension(user_id): axum::Extension<String>,
+    Json(command): Json<GeneralCommand>,
+) -> Result<StatusCode, StatusCode> {
+    let message = GeneralCommandMessage {
+        name: command.name,
+        controlling_user_id: user_id.clone(),
+        arguments: command.arguments,
+    };
+    send_command(&state, &session_id, &user_id, "GeneralCommand", &message)
+}
+
+/// POST /Sessions/{id}/Command/{command}
+/// A general command without arguments, e.g. `GoHome` or `ToggleMute`.
+pub async fn send_named_command(
+    State(state): State<AppState>,
+    Path((session_id, command)): Path<(String, String)>,
+    axum::Extension(user_id): axum::Extension<String>,
+) -> Result<StatusCode, StatusCode> {
+    let message = GeneralCommandMessage {
+        name: command,
+        controlling_user_id: user_id.clone(),
+        arguments: HashMap::new(),
+    };
+    send_command(&state, &session_id, &user_id, "GeneralCommand", &message)
+}
+
+/// POST /Sessions/{id}/Message
+/// Show a message on the session's screen.
+pub async fn send_message_command(
+    State(state): State<AppState>,
+    Path(session_id): Path<String>,
+    axum::Extension(user_id): axum::Extension<String>,
+    Json(command): Json<MessageCommand>,
+) -> Result<StatusCode, StatusCode> {
+    let mut arguments = HashMap::from([
+        ("Header".to_string(), command.header.unwrap_or_default()),
+        ("Text".to_string(), command.text),
+    ]);
+    if let Some(timeout) = command.timeout_ms {
+        arguments.insert("TimeoutMs".to_string(), timeout.to_string());
+    }
+
+    let message = GeneralCommandMessage {
+        name: "DisplayMessage".to_string(),
+        controlling_user_id: user_id.clone(),
+        arguments,
+    };
+    send_command(&state, &session_id, &user_id, "GeneralCommand", &message)
+}
+
+/// Deliver a command to the WebSocket of another session of the same user.
+/// 404 if that session has no WebSocket connection.
+fn send_command(
+    state: &AppState,
+    session_id: &str,
+    user_id: &str,
+    message_type: &str,
+    data: &impl Serialize,
+) -> Result<StatusCode, StatusCode> {
+    let session = state
+        .sessions
+        .get(session_id)
+        .ok_or(StatusCode::NOT_FOUND)?;
+    if session.user_id != user_id {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let data = serde_json::to_value(data).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if !state
+        .sessions
+        .send_to_session(session_id, &OutboundMessage::new(message_type, Some(data)))
+    {
+        debug!(
+            "Session {} is not connected, dropping {}",
+            session_id, message_type
+        );
+        return Err(StatusCode::NOT_FOUND);
+    }
+
     Ok(StatusCode::NO_CONTENT)
 }
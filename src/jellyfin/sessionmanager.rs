@@ -0,0 +1,261 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::RwLock;
+use tokio::sync::mpsc;
+
+use super::websocket::OutboundMessage;
+
+/// Sessions without any activity for this long are forgotten.
+const SESSION_IDLE_TIMEOUT: Duration = Duration::hours(24);
+
+/// Identifies the session of the current request. Set by `auth_middleware`.
+#[derive(Debug, Clone)]
+pub struct SessionId(pub String);
+
+/// Client details sent in the `X-Emby-Authorization` header.
+#[derive(Debug, Clone, Default)]
+pub struct ClientInfo {
+    pub client: String,
+    pub device: String,
+    pub device_id: String,
+    pub version: String,
+    /// Address the client connects from, see `auth::remote_address`.
+    pub remote_address: String,
+}
+
+/// Capabilities a client posts to `/Sessions/Capabilities(/Full)`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct ClientCapabilities {
+    pub playable_media_types: Vec<String>,
+    pub supported_commands: Vec<String>,
+    pub supports_media_control: bool,
+    pub supports_persistent_identifier: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct QueueItem {
+    pub id: String,
+    #[serde(default)]
+    pub playlist_item_id: Option<String>,
+}
+
+/// What a session is currently playing.
+#[derive(Debug, Clone, Default)]
+pub struct NowPlaying {
+    pub item_id: String,
+    pub media_source_id: Option<String>,
+    pub position_ticks: i64,
+    pub can_seek: bool,
+    pub is_paused: bool,
+    pub is_muted: bool,
+    pub volume_level: Option<i32>,
+    pub audio_stream_index: Option<i32>,
+    pub subtitle_stream_index: Option<i32>,
+    pub play_method: Option<String>,
+    pub play_session_id: Option<String>,
+    pub repeat_mode: Option<String>,
+    pub playback_order: Option<String>,
+    pub queue: Vec<QueueItem>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Session {
+    pub id: String,
+    pub user_id: String,
+    pub user_name: String,
+    pub token: String,
+    pub client: ClientInfo,
+    pub last_activity: DateTime<Utc>,
+    pub last_playback_check_in: Option<DateTime<Utc>>,
+    pub capabilities: Option<ClientCapabilities>,
+    pub now_playing: Option<NowPlaying>,
+}
+
+/// An open WebSocket connection of a session.
+struct Socket {
+    session_id: String,
+    user_id: String,
+    tx: mpsc::UnboundedSender<OutboundMessage>,
+}
+
+/// In-memory registry of client sessions, one per device and access token,
+/// and of their WebSocket connections.
+pub struct SessionManager {
+    sessions: RwLock<HashMap<(String, String), Session>>,
+    sockets: RwLock<HashMap<u64, Socket>>,
+    next_socket_id: AtomicU64,
+}
+
+impl Default for SessionManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SessionManager {
+    pub fn new() -> Self {
+        Self {
+            sessions: RwLock::new(HashMap::new()),
+            sockets: RwLock::new(HashMap::new()),
+            next_socket_id: AtomicU64::new(1),
+        }
+    }
+
+    /// Record activity for the session of `token` on `device_id`.
+    /// Returns the session ID, or `None` if there is no such session yet.
+    pub fn touch(&self, token: &str, device_id: &str) -> Option<String> {
+        let mut sessions = self.sessions.write().unwrap();
+        let session = sessions.get_mut(&(device_id.to_string(), token.to_string()))?;
+        session.last_activity = Utc::now();
+        Some(session.id.clone())
+    }
+
+    /// Start a new session for `token` on the client's device, replacing
+    /// any existing one. Returns the new session ID.
+    pub fn create(
+        &self,
+        token: &str,
+        user_id: &str,
+        user_name: &str,
+        client: &ClientInfo,
+    ) -> String {
+        let now = Utc::now();
+        let mut sessions = self.sessions.write().unwrap();
+        sessions.retain(|_, s| now - s.last_activity < SESSION_IDLE_TIMEOUT);
+
+        let session = Session {
+            id: uuid::Uuid::new_v4().simple().to_string(),
+            user_id: user_id.to_string(),
+            user_name: user_name.to_string(),
+            token: token.to_string(),
+            client: client.clone(),
+            last_activity: now,
+            last_playback_check_in: None,
+            capabilities: None,
+            now_playing: None,
+        };
+        let id = session.id.clone();
+        sessions.insert((client.device_id.clone(), token.to_string()), session);
+        id
+    }
+
+    /// Forget the sessions of a user, except the one using token `keep`.
+    pub fn end_sessions(&self, user_id: &str, keep: Option<&str>) {
+        let mut sessions = self.sessions.write().unwrap();
+        sessions.retain(|_, s| s.user_id != user_id || Some(s.token.as_str()) == keep);
+    }
+
+    /// Forget the sessions using `token`, for example after logout.
+    pub fn end_token_sessions(&self, token: &str) {
+        let mut sessions = self.sessions.write().unwrap();
+        sessions.retain(|_, s| s.token != token);
+    }
+
+    pub fn get(&self, session_id: &str) -> Option<Session> {
+        let sessions = self.sessions.read().unwrap();
+        sessions.values().find(|s| s.id == session_id).cloned()
+    }
+
+    pub fn list(&self) -> Vec<Session> {
+        let sessions = self.sessions.read().unwrap();
+        let mut list: Vec<Session> = sessions.values().cloned().collect();
+        list.sort_by_key(|s| std::cmp::Reverse(s.last_activity));
+        list
+    }
+
+    /// Record activity for a session without changing it.
+    pub fn keep_alive(&self, session_id: &str) {
+        self.update(session_id, |_| {});
+    }
+
+    pub fn set_capabilities(&self, session_id: &str, capabilities: ClientCapabilities) {
+        self.update(session_id, |session| {
+            session.capabilities = Some(capabilities);
+        });
+    }
+
+    /// Playback started or progressed.
+    pub fn set_now_playing(&self, session_id: &str, now_playing: NowPlaying) {
+        self.update(session_id, |session| {
+            session.last_playback_check_in = Some(Utc::now());
+            session.now_playing = Some(now_playing);
+        });
+    }
+
+    /// Playback of `item_id` stopped. Ignored if the session moved on to
+    /// another item already.
+    pub fn clear_now_playing(&self, session_id: &str, item_id: &str) {
+        self.update(session_id, |session| {
+            session.last_playback_check_in = Some(Utc::now());
+            if session
+                .now_playing
+                .as_ref()
+                .is_some_and(|np| np.item_id == item_id)
+            {
+                session.now_playing = None;
+            }
+        });
+    }
+
+    /// Register a WebSocket connection. Messages for it arrive on the returned receiver.
+    pub fn connect(
+        &self,
+        session_id: &str,
+        user_id: &str,
+    ) -> (u64, mpsc::UnboundedReceiver<OutboundMessage>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let socket_id = self.next_socket_id.fetch_add(1, Ordering::Relaxed);
+        let socket = Socket {
+            session_id: session_id.to_string(),
+            user_id: user_id.to_string(),
+            tx,
+        };
+        self.sockets.write().unwrap().insert(socket_id, socket);
+        (socket_id, rx)
+    }
+
+    pub fn disconnect(&self, socket_id: u64) {
+        self.sockets.write().unwrap().remove(&socket_id);
+    }
+
+    pub fn send_to_all(&self, message: &OutboundMessage) {
+        self.send_where(message, |_| true);
+    }
+
+    pub fn send_to_user(&self, user_id: &str, message: &OutboundMessage) {
+        self.send_where(message, |socket| socket.user_id == user_id);
+    }
+
+    /// Returns false if the session has no open WebSocket connection.
+    pub fn send_to_session(&self, session_id: &str, message: &OutboundMessage) -> bool {
+        self.send_where(message, |socket| socket.session_id == session_id) > 0
+    }
+
+    pub fn is_connected(&self, session_id: &str) -> bool {
+        let sockets = self.sockets.read().unwrap();
+        sockets.values().any(|s| s.session_id == session_id)
+    }
+
+    /// Returns the number of connections the message was queued for.
+    fn send_where(&self, message: &OutboundMessage, filter: impl Fn(&Socket) -> bool) -> usize {
+        let sockets = self.sockets.read().unwrap();
+        sockets
+            .values()
+            .filter(|s| filter(s))
+            // Fails only if the connection is closing; it unregisters itself.
+            .filter(|s| s.tx.send(message.clone()).is_ok())
+            .count()
+    }
+
+    fn update(&self, session_id: &str, f: impl FnOnce(&mut Session)) {
+        let mut sessions = self.sessions.write().unwrap();
+        if let Some(session) = sessions.values_mut().find(|s| s.id == session_id) {
+            session.last_activity = Utc::now();
+            f(session);
+        }
+    }
+}
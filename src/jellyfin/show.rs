@@ -4,7 +4,9 @@ use axum::{
     Json,
 };
 
+use super::access::get_library_access;
 use super::auth::get_user_id;
+use super::filter::apply_parental_filter;
 use super::types::*;
 use crate::collection::Item;
 use crate::db::UserDataRepo;
@@ -17,126 +19,118 @@ pub async fn get_seasons(
     State(state): State<AppState>,
     Path(show_id): Path<String>,
     Query(_params): Query<QueryParams>,
+    req: Request<axum::body::Body>,
 ) -> Result<Json<QueryResult<BaseItemDto>>, StatusCode> {
     let mut seasons_dto = Vec::new();
     let server_id = state.config.jellyfin.server_id.clone().unwrap_or_default();
 
-    // Scan all collections for the show
-    let collections = state.collections.list_collections().await;
-    for collection in collections {
-        if let Some(show) = collection.shows.get(&show_id) {
-            // Found the show, getting seasons
-            let mut seasons: Vec<_> = show.seasons.values().collect();
-            seasons.sort_by_key(|s| s.season_number);
+    let Some((collection_id, Item::Show(show))) =
+        get_library_access(&req).get_item(&state.collections, &show_id)
+    else {
+        return Err(StatusCode::NOT_FOUND);
+    };
 
-            for season in seasons {
-                seasons_dto.push(convert_season_to_dto(
-                    season,
-                    &show.id,
-                    &collection.id, // parent_id for season is usually show_id, but here it might be context dependent.
-                    // In convert_season_to_dto logic:
-                    // pub fn convert_season_to_dto(season: &crate::collection::Season, show_id: &str, _parent_id: &str, series_name: &str)
-                    // It ignores passing parent_id really? Checking impl:
-                    // parent_id: Some(show_id.to_string()), (It uses show_id as parent_id inside)
-                    &show.name,
-                    &server_id,
-                ));
-            }
+    let mut seasons: Vec<_> = show.seasons.values().collect();
+    seasons.sort_by_key(|s| s.season_number);
 
-            return Ok(Json(QueryResult {
-                total_record_count: seasons_dto.len(),
-                start_index: 0,
-                items: seasons_dto,
-            }));
-        }
+    for season in seasons {
+        seasons_dto.push(convert_season_to_dto(
+            season,
+            &show.id,
+            &collection_id,
+            &show.name,
+            &server_id,
+        ));
     }
 
-    Err(StatusCode::NOT_FOUND)
+    Ok(Json(QueryResult {
+        total_record_count: seasons_dto.len(),
+        start_index: 0,
+        items: seasons_dto,
+    }))
 }
 
 pub async fn get_episodes(
     State(state): State<AppState>,
     Path(show_id): Path<String>,
     Query(params): Query<QueryParams>,
+    req: Request<axum::body::Body>,
 ) -> Result<Json<QueryResult<BaseItemDto>>, StatusCode> {
     let season_id = params.get("seasonId");
     let mut episodes = Vec::new();
     let server_id = state.config.jellyfin.server_id.clone().unwrap_or_default();
 
-    // Scan all collections for the show
-    let collections = state.collections.list_collections().await;
-    for collection in collections {
-        if let Some(show) = collection.shows.get(&show_id) {
-            if let Some(sid) = season_id {
-                // Return episodes for specific season
-                // First try to find by ID string
-                let mut found_season = None;
-                for season in show.seasons.values() {
-                    if season.id == *sid {
-                        found_season = Some(season);
-                        break;
-                    }
-                }
+    let Some((collection_id, Item::Show(show))) =
+        get_library_access(&req).get_item(&state.collections, &show_id)
+    else {
+        return Err(StatusCode::NOT_FOUND);
+    };
 
-                // If not found by ID, try parsing as season number (fallback)
-                if found_season.is_none() {
-                    if let Ok(sid_int) = sid.parse::<i32>() {
-                        found_season = show.seasons.get(&sid_int);
-                    }
-                }
-
-                if let Some(season) = found_season {
-                    for episode in season.episodes.values() {
-                        episodes.push(convert_episode_to_dto(
-                            episode,
-                            &season.id,
-                            &show.id,
-                            &collection.id,
-                            &season.name,
-                            &show.name,
-                            &server_id,
-                        ));
-                    }
-                }
-            } else {
-                // Return all episodes from all seasons
-                for season in show.seasons.values() {
-                    for episode in season.episodes.values() {
-                        episodes.push(convert_episode_to_dto(
-                            episode,
-                            &season.id,
-                            &show.id,
-                            &collection.id,
-                            &season.name,
-                            &show.name,
-                            &server_id,
-                        ));
-                    }
-                }
+    if let Some(sid) = season_id {
+        // Return episodes for specific season
+        // First try to find by ID string
+        let mut found_season = match state.collections.get_item(sid) {
+            Some((_, Item::Season(season))) if season.show_id == show.id => {
+                show.seasons.get(&season.season_number)
             }
+            _ => None,
+        };
 
-            // Sort episodes: Season Asc, Episode Asc
-            episodes.sort_by(|a, b| {
-                let season_a = a.parent_index_number.unwrap_or(0);
-                let season_b = b.parent_index_number.unwrap_or(0);
-                if season_a != season_b {
-                    season_a.cmp(&season_b)
-                } else {
-                    a.index_number
-                        .unwrap_or(0)
-                        .cmp(&b.index_number.unwrap_or(0))
-                }
-            });
+        // If not found by ID, try parsing as season number (fallback)
+        if found_season.is_none() {
+            if let Ok(sid_int) = sid.parse::<i32>() {
+                found_season = show.seasons.get(&sid_int);
+            }
+        }
 
-            return Ok(Json(QueryResult {
-                total_record_count: episodes.len(),
-                start_index: 0,
-                items: episodes,
-            }));
+        if let Some(season) = found_season {
+            for episode in season.episodes.values() {
+                episodes.push(convert_episode_to_dto(
+                    episode,
+                    &season.id,
+                    &show.id,
+                    &collection_id,
+                    &season.name,
+                    &show.name,
+                    &server_id,
+                ));
+            }
+        }
+    } else {
+        // Return all episodes from all seasons
+        for season in show.seasons.values() {
+            for episode in season.episodes.values() {
+                episodes.push(convert_episode_to_dto(
+                    episode,
+                    &season.id,
+                    &show.id,
+                    &collection_id,
+                    &season.name,
+                    &show.name,
+                    &server_id,
+                ));
+            }
         }
     }
 
-    Err(StatusCode::NOT_FOUND)
+    // Sort episodes: Season Asc, Episode Asc
+    episodes.sort_by(|a, b| {
+        let season_a = a.parent_index_number.unwrap_or(0);
+        let season_b = b.parent_index_number.unwrap_or(0);
+        if season_a != season_b {
+            season_a.cmp(&season_b)
+        } else {
+            a.index_number
+                .unwrap_or(0)
+                .cmp(&b.index_number.unwrap_or(0))
+        }
+    });
+
+    Ok(Json(QueryResult {
+        total_record_count: episodes.len(),
+        start_index: 0,
+        items: episodes,
+    }))
 }
 
 // Helper to determine the Next Up item for a specific show
@@ -270,6 +264,7 @@ pub async fn get_next_up(
     req: Request<axum::body::Body>,
 ) -> Result<Json<QueryResult<BaseItemDto>>, StatusCode> {
     let user_id = get_user_id(&req).ok_or(StatusCode::UNAUTHORIZED)?;
+    let access = get_library_access(&req);
 
     let limit = params
         .get("limit")
@@ -288,7 +283,7 @@ pub async fn get_next_up(
 
     if let Some(sid) = series_id {
         // Direct lookup
-        if let Some((collection_id, item)) = state.collections.get_item(sid) {
+        if let Some((collection_id, item)) = access.get_item(&state.collections, sid) {
             if let Item::Show(show) = item {
                 if let Some(collection) = state.collections.get_collection(&collection_id).await {
                     if let Some((_, dto)) =
@@ -302,7 +297,7 @@ pub async fn get_next_up(
         }
     } else {
         // Scan all shows
-        let collections = state.collections.list_collections().await;
+        let collections = access.list_collections(&state.collections).await;
         let mut potential_items = Vec::new();
 
         for collection in &collections {
@@ -320,6 +315,7 @@ pub async fn get_next_up(
         potential_items.sort_by(|a, b| b.0.cmp(&a.0));
         next_up_items = potential_items.into_iter().map(|(_, dto)| dto).collect();
     }
+    let next_up_items = apply_parental_filter(next_up_items, &access, &state.collections);
 
     let items: Vec<BaseItemDto> = next_up_items.into_iter().take(limit).collect();
     let count = items.len();
@@ -0,0 +1,416 @@
+use axum::{
+    body::Body,
+    extract::{Path, State},
+    http::{Request, StatusCode},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::access::{get_library_access, LibraryAccess};
+use super::auth::{get_session_id, get_user_id, json_body};
+use super::syncplaymanager::{GroupInfo, GroupRequest};
+use crate::server::AppState;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct NewGroupRequest {
+    pub group_name: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct JoinGroupRequest {
+    pub group_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct PlayRequest {
+    pub playing_queue: Vec<String>,
+    #[serde(default)]
+    pub playing_item_position: usize,
+    #[serde(default)]
+    pub start_position_ticks: i64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct PlaylistItemRequest {
+    pub playlist_item_id: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct RemoveFromPlaylistRequest {
+    pub playlist_item_ids: Vec<String>,
+    pub clear_playlist: bool,
+    pub clear_playing_item: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct MovePlaylistItemRequest {
+    pub playlist_item_id: String,
+    pub new_index: usize,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct QueueRequest {
+    pub item_ids: Vec<String>,
+    /// `Queue` or `QueueNext`.
+    #[serde(default)]
+    pub mode: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct SeekRequest {
+    pub position_ticks: i64,
+}
+
+/// Body of /SyncPlay/Buffering and /SyncPlay/Ready.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct BufferRequest {
+    pub playlist_item_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct IgnoreWaitRequest {
+    pub ignore_wait: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ModeRequest {
+    pub mode: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct PingRequest {
+    pub ping: f64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct UtcTimeResponse {
+    pub request_reception_time: DateTime<Utc>,
+    pub response_transmission_time: DateTime<Utc>,
+}
+
+/// GET /SyncPlay/List
+pub async fn list_groups(
+    State(state): State<AppState>,
+    req: Request<Body>,
+) -> Result<Json<Vec<GroupInfo>>, StatusCode> {
+    get_user_id(&req).ok_or(StatusCode::UNAUTHORIZED)?;
+    Ok(Json(state.syncplay.list_groups()))
+}
+
+/// GET /SyncPlay/{id}
+pub async fn get_group(
+    State(state): State<AppState>,
+    Path(group_id): Path<String>,
+    req: Request<Body>,
+) -> Result<Json<GroupInfo>, StatusCode> {
+    get_user_id(&req).ok_or(StatusCode::UNAUTHORIZED)?;
+    state
+        .syncplay
+        .get_group(&group_id)
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+/// POST /SyncPlay/New
+pub async fn new_group(
+    State(state): State<AppState>,
+    req: Request<Body>,
+) -> Result<StatusCode, StatusCode> {
+    let session_id = get_session_id(&req).ok_or(StatusCode::UNAUTHORIZED)?;
+    let request: NewGroupRequest = json_body(req).await?;
+    let session = state
+        .sessions
+        .get(&session_id)
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+    state.syncplay.new_group(&session, &request.group_name);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// POST /SyncPlay/Join
+/// A user cannot join a group that is playing items it may not see.
+pub async fn join_group(
+    State(state): State<AppState>,
+    req: Request<Body>,
+) -> Result<StatusCode, StatusCode> {
+    let session_id = get_session_id(&req).ok_or(StatusCode::UNAUTHORIZED)?;
+    let access = get_library_access(&req);
+    let request: JoinGroupRequest = json_body(req).await?;
+    let session = state
+        .sessions
+        .get(&session_id)
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+    let visible = |id: &str| access.get_item(&state.collections, id).is_some();
+    if !state
+        .syncplay
+        .join_group(&session, &request.group_id, visible)
+    {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// POST /SyncPlay/Leave
+pub async fn leave_group(
+    State(state): State<AppState>,
+    req: Request<Body>,
+) -> Result<StatusCode, StatusCode> {
+    let session_id = get_session_id(&req).ok_or(StatusCode::UNAUTHORIZED)?;
+    state.syncplay.leave_group(&session_id);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// POST /SyncPlay/SetNewQueue
+pub async fn set_new_queue(
+    State(state): State<AppState>,
+    req: Request<Body>,
+) -> Result<StatusCode, StatusCode> {
+    let session_id = get_session_id(&req).ok_or(StatusCode::UNAUTHORIZED)?;
+    let access = get_library_access(&req);
+    let request: PlayRequest = json_body(req).await?;
+    let request = GroupRequest::SetNewQueue {
+        item_ids: request.playing_queue,
+        playing_index: request.playing_item_position,
+        start_position_ticks: request.start_position_ticks,
+    };
+    handle_items(&state, &session_id, &access, request)
+}
+
+/// POST /SyncPlay/SetPlaylistItem
+pub async fn set_playlist_item(
+    State(state): State<AppState>,
+    req: Request<Body>,
+) -> Result<StatusCode, StatusCode> {
+    let session_id = get_session_id(&req).ok_or(StatusCode::UNAUTHORIZED)?;
+    let request: PlaylistItemRequest = json_body(req).await?;
+    let request = GroupRequest::SetPlaylistItem {
+        playlist_item_id: request.playlist_item_id,
+    };
+    Ok(handle(&state, &session_id, request))
+}
+
+/// POST /SyncPlay/RemoveFromPlaylist
+pub async fn remove_from_playlist(
+    State(state): State<AppState>,
+    req: Request<Body>,
+) -> Result<StatusCode, StatusCode> {
+    let session_id = get_session_id(&req).ok_or(StatusCode::UNAUTHORIZED)?;
+    let request: RemoveFromPlaylistRequest = json_body(req).await?;
+    let request = GroupRequest::RemoveFromPlaylist {
+        playlist_item_ids: request.playlist_item_ids,
+        clear_playlist: request.clear_playlist,
+        clear_playing_item: request.clear_playing_item,
+    };
+    Ok(handle(&state, &session_id, request))
+}
+
+/// POST /SyncPlay/MovePlaylistItem
+pub async fn move_playlist_item(
+    State(state): State<AppState>,
+    req: Request<Body>,
+) -> Result<StatusCode, StatusCode> {
+    let session_id = get_session_id(&req).ok_or(StatusCode::UNAUTHORIZED)?;
+    let request: MovePlaylistItemRequest = json_body(req).await?;
+    let request = GroupRequest::MovePlaylistItem {
+        playlist_item_id: request.playlist_item_id,
+        new_index: request.new_index,
+    };
+    Ok(handle(&state, &session_id, request))
+}
+
+/// POST /SyncPlay/Queue
+pub async fn queue(
+    State(state): State<AppState>,
+    req: Request<Body>,
+) -> Result<StatusCode, StatusCode> {
+    let session_id = get_session_id(&req).ok_or(StatusCode::UNAUTHORIZED)?;
+    let access = get_library_access(&req);
+    let request: QueueRequest = json_body(req).await?;
+    let request = GroupRequest::Queue {
+        item_ids: request.item_ids,
+        next: request.mode == "QueueNext",
+    };
+    handle_items(&state, &session_id, &access, request)
+}
+
+/// POST /SyncPlay/Unpause
+pub async fn unpause(
+    State(state): State<AppState>,
+    req: Request<Body>,
+) -> Result<StatusCode, StatusCode> {
+    let session_id = get_session_id(&req).ok_or(StatusCode::UNAUTHORIZED)?;
+    Ok(handle(&state, &session_id, GroupRequest::Unpause))
+}
+
+/// POST /SyncPlay/Pause
+pub async fn pause(
+    State(state): State<AppState>,
+    req: Request<Body>,
+) -> Result<StatusCode, StatusCode> {
+    let session_id = get_session_id(&req).ok_or(StatusCode::UNAUTHORIZED)?;
+    Ok(handle(&state, &session_id, GroupRequest::Pause))
+}
+
+/// POST /SyncPlay/Stop
+pub async fn stop(
+    State(state): State<AppState>,
+    req: Request<Body>,
+) -> Result<StatusCode, StatusCode> {
+    let session_id = get_session_id(&req).ok_or(StatusCode::UNAUTHORIZED)?;
+    Ok(handle(&state, &session_id, GroupRequest::Stop))
+}
+
+/// POST /SyncPlay/Seek
+pub async fn seek(
+    State(state): State<AppState>,
+    req: Request<Body>,
+) -> Result<StatusCode, StatusCode> {
+    let session_id = get_session_id(&req).ok_or(StatusCode::UNAUTHORIZED)?;
+    let request: SeekRequest = json_body(req).await?;
+    let request = GroupRequest::Seek {
+        position_ticks: request.position_ticks,
+    };
+    Ok(handle(&state, &session_id, request))
+}
+
+/// POST /SyncPlay/Buffering
+pub async fn buffering(
+    State(state): State<AppState>,
+    req: Request<Body>,
+) -> Result<StatusCode, StatusCode> {
+    let session_id = get_session_id(&req).ok_or(StatusCode::UNAUTHORIZED)?;
+    let request: BufferRequest = json_body(req).await?;
+    let request = GroupRequest::Buffering {
+        playlist_item_id: request.playlist_item_id,
+    };
+    Ok(handle(&state, &session_id, request))
+}
+
+/// POST /SyncPlay/Ready
+pub async fn ready(
+    State(state): State<AppState>,
+    req: Request<Body>,
+) -> Result<StatusCode, StatusCode> {
+    let session_id = get_session_id(&req).ok_or(StatusCode::UNAUTHORIZED)?;
+    let request: BufferRequest = json_body(req).await?;
+    let request = GroupRequest::Ready {
+        playlist_item_id: request.playlist_item_id,
+    };
+    Ok(handle(&state, &session_id, request))
+}
+
+/// POST /SyncPlay/SetIgnoreWait
+pub async fn set_ignore_wait(
+    State(state): State<AppState>,
+    req: Request<Body>,
+) -> Result<StatusCode, StatusCode> {
+    let session_id = get_session_id(&req).ok_or(StatusCode::UNAUTHORIZED)?;
+    let request: IgnoreWaitRequest = json_body(req).await?;
+    let request = GroupRequest::SetIgnoreWait(request.ignore_wait);
+    Ok(handle(&state, &session_id, request))
+}
+
+/// POST /SyncPlay/NextItem
+pub async fn next_item(
+    State(state): State<AppState>,
+    req: Request<Body>,
+) -> Result<StatusCode, StatusCode> {
+    let session_id = get_session_id(&req).ok_or(StatusCode::UNAUTHORIZED)?;
+    let request: PlaylistItemRequest = json_body(req).await?;
+    let request = GroupRequest::NextItem {
+        playlist_item_id: request.playlist_item_id,
+    };
+    Ok(handle(&state, &session_id, request))
+}
+
+/// POST /SyncPlay/PreviousItem
+pub async fn previous_item(
+    State(state): State<AppState>,
+    req: Request<Body>,
+) -> Result<StatusCode, StatusCode> {
+    let session_id = get_session_id(&req).ok_or(StatusCode::UNAUTHORIZED)?;
+    let request: PlaylistItemRequest = json_body(req).await?;
+    let request = GroupRequest::PreviousItem {
+        playlist_item_id: request.playlist_item_id,
+    };
+    Ok(handle(&state, &session_id, request))
+}
+
+/// POST /SyncPlay/SetRepeatMode
+pub async fn set_repeat_mode(
+    State(state): State<AppState>,
+    req: Request<Body>,
+) -> Result<StatusCode, StatusCode> {
+    let session_id = get_session_id(&req).ok_or(StatusCode::UNAUTHORIZED)?;
+    let request: ModeRequest = json_body(req).await?;
+    let request = GroupRequest::SetRepeatMode(request.mode);
+    Ok(handle(&state, &session_id, request))
+}
+
+/// POST /SyncPlay/SetShuffleMode
+pub async fn set_shuffle_mode(
+    State(state): State<AppState>,
+    req: Request<Body>,
+) -> Result<StatusCode, StatusCode> {
+    let session_id = get_session_id(&req).ok_or(StatusCode::UNAUTHORIZED)?;
+    let request: ModeRequest = json_body(req).await?;
+    let request = GroupRequest::SetShuffleMode(request.mode);
+    Ok(handle(&state, &session_id, request))
+}
+
+/// POST /SyncPlay/Ping
+pub async fn ping(
+    State(state): State<AppState>,
+    req: Request<Body>,
+) -> Result<StatusCode, StatusCode> {
+    let session_id = get_session_id(&req).ok_or(StatusCode::UNAUTHORIZED)?;
+    let request: PingRequest = json_body(req).await?;
+    Ok(handle(
+        &state,
+        &session_id,
+        GroupRequest::Ping(request.ping as i64),
+    ))
+}
+
+/// GET /GetUtcTime
+/// Lets SyncPlay clients estimate their clock offset to the server.
+pub async fn get_utc_time() -> Json<UtcTimeResponse> {
+    let request_reception_time = Utc::now();
+    Json(UtcTimeResponse {
+        request_reception_time,
+        response_transmission_time: Utc::now(),
+    })
+}
+
+/// Handle a request that adds no items.
+fn handle(state: &AppState, session_id: &str, request: GroupRequest) -> StatusCode {
+    state.syncplay.handle(session_id, request, |_| true);
+    StatusCode::NO_CONTENT
+}
+
+/// Handle a request that adds items, which the user must be able to see.
+fn handle_items(
+    state: &AppState,
+    session_id: &str,
+    access: &LibraryAccess,
+    request: GroupRequest,
+) -> Result<StatusCode, StatusCode> {
+    let visible = |id: &str| access.get_item(&state.collections, id).is_some();
+    if !state.syncplay.handle(session_id, request, visible) {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
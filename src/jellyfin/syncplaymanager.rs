@@ -0,0 +1,1002 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use super::sessionmanager::{Session, SessionManager};
+use super::websocket::OutboundMessage;
+
+/// Playback positions are in ticks of 100ns.
+const TICKS_PER_MS: i64 = 10_000;
+
+/// Playback is scheduled at least this far ahead so that every member
+/// receives the command before it is due.
+const MIN_COMMAND_DELAY_MS: i64 = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum GroupState {
+    /// Nothing is playing.
+    Idle,
+    /// Waiting for members to load or buffer the current item.
+    Waiting,
+    Paused,
+    Playing,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct SyncPlayQueueItem {
+    pub item_id: String,
+    pub playlist_item_id: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct GroupInfo {
+    pub group_id: String,
+    pub group_name: String,
+    pub state: GroupState,
+    pub participants: Vec<String>,
+    pub last_updated_at: DateTime<Utc>,
+}
+
+/// A playback request of a group member, see the `/SyncPlay/*` endpoints.
+#[derive(Debug, Clone)]
+pub enum GroupRequest {
+    SetNewQueue {
+        item_ids: Vec<String>,
+        playing_index: usize,
+        start_position_ticks: i64,
+    },
+    SetPlaylistItem {
+        playlist_item_id: String,
+    },
+    RemoveFromPlaylist {
+        playlist_item_ids: Vec<String>,
+        clear_playlist: bool,
+        clear_playing_item: bool,
+    },
+    MovePlaylistItem {
+        playlist_item_id: String,
+        new_index: usize,
+    },
+    Queue {
+        item_ids: Vec<String>,
+        next: bool,
+    },
+    Unpause,
+    Pause,
+    Stop,
+    Seek {
+        position_ticks: i64,
+    },
+    Buffering {
+        playlist_item_id: String,
+    },
+    Ready {
+        playlist_item_id: String,
+    },
+    SetIgnoreWait(bool),
+    NextItem {
+        playlist_item_id: String,
+    },
+    PreviousItem {
+        playlist_item_id: String,
+    },
+    SetRepeatMode(String),
+    SetShuffleMode(String),
+    Ping(i64),
+}
+
+impl GroupRequest {
+    /// The items a request adds to the queue.
+    pub fn item_ids(&self) -> &[String] {
+        match self {
+            GroupRequest::SetNewQueue { item_ids, .. } | GroupRequest::Queue { item_ids, .. } => {
+                item_ids
+            }
+            _ => &[],
+        }
+    }
+}
+
+struct Member {
+    user_name: String,
+    ping_ms: i64,
+    is_buffering: bool,
+    /// Members that opted out of waiting never hold up the group.
+    ignore_wait: bool,
+}
+
+struct Group {
+    id: String,
+    name: String,
+    state: GroupState,
+    /// Keyed by session ID.
+    members: HashMap<String, Member>,
+    queue: Vec<SyncPlayQueueItem>,
+    /// The queue in its original order while shuffle mode is on.
+    unshuffled: Option<Vec<SyncPlayQueueItem>>,
+    playing_index: Option<usize>,
+    repeat_mode: String,
+    position_ticks: i64,
+    /// When playback was at `position_ticks`. In the future while an
+    /// unpause is scheduled.
+    position_updated: DateTime<Utc>,
+    /// Whether to start playing once all members are ready.
+    resume_playback: bool,
+    last_updated: DateTime<Utc>,
+}
+
+/// In-memory SyncPlay groups. Members are sessions; group updates and
+/// playback commands are sent over their WebSocket connections.
+pub struct SyncPlayManager {
+    sessions: Arc<SessionManager>,
+    inner: Mutex<Groups>,
+}
+
+#[derive(Default)]
+struct Groups {
+    groups: HashMap<String, Group>,
+    /// Session ID → group ID.
+    membership: HashMap<String, String>,
+}
+
+impl SyncPlayManager {
+    pub fn new(sessions: Arc<SessionManager>) -> Self {
+        Self {
+            sessions,
+            inner: Mutex::new(Groups::default()),
+        }
+    }
+
+    pub fn list_groups(&self) -> Vec<GroupInfo> {
+        let inner = self.inner.lock().unwrap();
+        let mut groups: Vec<GroupInfo> = inner.groups.values().map(Group::info).collect();
+        groups.sort_by(|a, b| a.group_name.cmp(&b.group_name));
+        groups
+    }
+
+    pub fn get_group(&self, group_id: &str) -> Option<GroupInfo> {
+        let inner = self.inner.lock().unwrap();
+        inner.groups.get(group_id).map(Group::info)
+    }
+
+    /// Create a group with `session` as its only member.
+    pub fn new_group(&self, session: &Session, name: &str) {
+        let mut inner = self.inner.lock().unwrap();
+        self.leave(&mut inner, &session.id);
+
+        let now = Utc::now();
+        let mut group = Group {
+            id: uuid::Uuid::new_v4().simple().to_string(),
+            name: name.to_string(),
+            state: GroupState::Idle,
+            members: HashMap::new(),
+            queue: Vec::new(),
+            unshuffled: None,
+            playing_index: None,
+            repeat_mode: "RepeatNone".to_string(),
+            position_ticks: 0,
+            position_updated: now,
+            resume_playback: false,
+            last_updated: now,
+        };
+        group
+            .members
+            .insert(session.id.clone(), Member::new(&session.user_name));
+
+        let info = serde_json::to_value(group.info()).unwrap_or_default();
+        self.sessions
+            .send_to_session(&session.id, &group_update(&group.id, "GroupJoined", info));
+
+        inner
+            .membership
+            .insert(session.id.clone(), group.id.clone());
+        inner.groups.insert(group.id.clone(), group);
+    }
+
+    /// Join a group. Returns false, and does not join, if the group's
+    /// queue holds items that `visible` rejects.
+    pub fn join_group(
+        &self,
+        session: &Session,
+        group_id: &str,
+        visible: impl Fn(&str) -> bool,
+    ) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        let Some(group) = inner.groups.get(group_id) else {
+            self.sessions.send_to_session(
+                &session.id,
+                &group_update(group_id, "GroupDoesNotExist", json!(group_id)),
+            );
+            return true;
+        };
+        if !group.queue.iter().all(|item| visible(&item.item_id)) {
+            return false;
+        }
+        if inner.membership.get(&session.id).map(String::as_str) != Some(group_id) {
+            self.leave(&mut inner, &session.id);
+        }
+        inner
+            .membership
+            .insert(session.id.clone(), group_id.to_string());
+
+        let group = inner.groups.get_mut(group_id).unwrap();
+        let mut member = Member::new(&session.user_name);
+        // The new member has to load the current item first.
+        member.is_buffering = group.state != GroupState::Idle;
+        group.members.insert(session.id.clone(), member);
+        group.last_updated = Utc::now();
+
+        let info = serde_json::to_value(group.info()).unwrap_or_default();
+        self.sessions
+            .send_to_session(&session.id, &group_update(&group.id, "GroupJoined", info));
+        group.send_to_others(
+            &self.sessions,
+            &session.id,
+            &group_update(&group.id, "UserJoined", json!(session.user_name)),
+        );
+
+        match group.state {
+            GroupState::Idle => {}
+            GroupState::Playing => {
+                self.sessions
+                    .send_to_session(&session.id, &group.play_queue_update("NewPlaylist"));
+                group.pause(&self.sessions);
+                group.wait(&self.sessions, true, "Join");
+            }
+            GroupState::Paused | GroupState::Waiting => {
+                self.sessions
+                    .send_to_session(&session.id, &group.play_queue_update("NewPlaylist"));
+                let resume = group.is_playing();
+                group.wait(&self.sessions, resume, "Join");
+            }
+        }
+        true
+    }
+
+    pub fn leave_group(&self, session_id: &str) {
+        let mut inner = self.inner.lock().unwrap();
+        if !inner.membership.contains_key(session_id) {
+            self.send_not_in_group(session_id);
+            return;
+        }
+        self.leave(&mut inner, session_id);
+    }
+
+    /// Remove a session from its group, if any, without complaining if it
+    /// is not in one. Used when its WebSocket connection goes away.
+    pub fn remove_session(&self, session_id: &str) {
+        let mut inner = self.inner.lock().unwrap();
+        self.leave(&mut inner, session_id);
+    }
+
+    /// Apply a playback request of a group member. Returns false, and
+    /// ignores the request, if it adds items that `visible` rejects.
+    pub fn handle(
+        &self,
+        session_id: &str,
+        request: GroupRequest,
+        visible: impl Fn(&str) -> bool,
+    ) -> bool {
+        if !request.item_ids().iter().all(|id| visible(id)) {
+            return false;
+        }
+        let mut inner = self.inner.lock().unwrap();
+        let Some(group_id) = inner.membership.get(session_id).cloned() else {
+            self.send_not_in_group(session_id);
+            return true;
+        };
+        let Some(group) = inner.groups.get_mut(&group_id) else {
+            return true;
+        };
+        group.last_updated = Utc::now();
+        group.handle(&self.sessions, session_id, request);
+        true
+    }
+
+    fn leave(&self, inner: &mut Groups, session_id: &str) {
+        let Some(group_id) = inner.membership.remove(session_id) else {
+            return;
+        };
+        let Some(group) = inner.groups.get_mut(&group_id) else {
+            return;
+        };
+        let Some(member) = group.members.remove(session_id) else {
+            return;
+        };
+        group.last_updated = Utc::now();
+
+        self.sessions.send_to_session(
+            session_id,
+            &group_update(&group_id, "GroupLeft", json!(group_id)),
+        );
+        if group.members.is_empty() {
+            inner.groups.remove(&group_id);
+            return;
+        }
+        group.send_to_all(
+            &self.sessions,
+            &group_update(&group_id, "UserLeft", json!(member.user_name)),
+        );
+        // The group may have been waiting for this member only.
+        group.check_ready(&self.sessions, "Leave");
+    }
+
+    fn send_not_in_group(&self, session_id: &str) {
+        self.sessions
+            .send_to_session(session_id, &group_update("", "NotInGroup", Value::Null));
+    }
+}
+
+impl Member {
+    fn new(user_name: &str) -> Self {
+        Self {
+            user_name: user_name.to_string(),
+            ping_ms: 0,
+            is_buffering: false,
+            ignore_wait: false,
+        }
+    }
+}
+
+impl Group {
+    fn info(&self) -> GroupInfo {
+        let mut participants: Vec<String> =
+            self.members.values().map(|m| m.user_name.clone()).collect();
+        participants.sort();
+        GroupInfo {
+            group_id: self.id.clone(),
+            group_name: self.name.clone(),
+            state: self.state,
+            participants,
+            last_updated_at: self.last_updated,
+        }
+    }
+
+    fn handle(&mut self, sessions: &SessionManager, session_id: &str, request: GroupRequest) {
+        match request {
+            GroupRequest::SetNewQueue {
+                item_ids,
+                playing_index,
+                start_position_ticks,
+            } => {
+                self.queue = new_queue_items(item_ids);
+                self.unshuffled = None;
+                if self.queue.is_empty() {
+                    self.playing_index = None;
+                    self.stop(sessions, "SetNewQueue");
+                    return;
+                }
+                self.playing_index = Some(playing_index.min(self.queue.len() - 1));
+                self.position_ticks = start_position_ticks;
+                self.send_to_all(sessions, &self.play_queue_update_with("NewPlaylist", true));
+                self.restart(sessions, true, "SetNewQueue");
+            }
+            GroupRequest::SetPlaylistItem { playlist_item_id } => {
+                let Some(index) = self.index_of(&playlist_item_id) else {
+                    return;
+                };
+                self.playing_index = Some(index);
+                self.position_ticks = 0;
+                self.send_to_all(
+                    sessions,
+                    &self.play_queue_update_with("SetCurrentItem", true),
+                );
+                self.restart(sessions, true, "SetPlaylistItem");
+            }
+            GroupRequest::RemoveFromPlaylist {
+                playlist_item_ids,
+                clear_playlist,
+                clear_playing_item,
+            } => {
+                let playing = self.playing_item_id();
+                let keep = |item: &SyncPlayQueueItem| {
+                    let is_playing = playing.as_deref() == Some(item.playlist_item_id.as_str());
+                    if is_playing && clear_playing_item {
+                        return false;
+                    }
+                    (!clear_playlist || is_playing)
+                        && !playlist_item_ids.contains(&item.playlist_item_id)
+                };
+                self.queue.retain(keep);
+                if let Some(unshuffled) = &mut self.unshuffled {
+                    unshuffled.retain(keep);
+                }
+
+                let playing_index = playing.as_deref().and_then(|id| self.index_of(id));
+                if playing_index.is_some() || self.playing_index.is_none() {
+                    self.playing_index = playing_index;
+                    self.send_to_all(sessions, &self.play_queue_update("RemoveItems"));
+                } else if self.queue.is_empty() {
+                    // The playing item was the last one.
+                    self.playing_index = None;
+                    self.send_to_all(sessions, &self.play_queue_update("RemoveItems"));
+                    self.stop(sessions, "RemoveFromPlaylist");
+                } else {
+                    // Continue with the item that took its place.
+                    let index = self.playing_index.unwrap().min(self.queue.len() - 1);
+                    self.playing_index = Some(index);
+                    self.position_ticks = 0;
+                    self.send_to_all(sessions, &self.play_queue_update("RemoveItems"));
+                    let resume = self.is_playing();
+                    self.restart(sessions, resume, "RemoveFromPlaylist");
+                }
+            }
+            GroupRequest::MovePlaylistItem {
+                playlist_item_id,
+                new_index,
+            } => {
+                let Some(index) = self.index_of(&playlist_item_id) else {
+                    return;
+                };
+                let playing = self.playing_item_id();
+                let item = self.queue.remove(index);
+                self.queue.insert(new_index.min(self.queue.len()), item);
+                self.playing_index = playing.as_deref().and_then(|id| self.index_of(id));
+                self.send_to_all(sessions, &self.play_queue_update("MoveItem"));
+            }
+            GroupRequest::Queue { item_ids, next } => {
+                let items = new_queue_items(item_ids);
+                if let Some(unshuffled) = &mut self.unshuffled {
+                    unshuffled.extend(items.iter().cloned());
+                }
+                match self.playing_index {
+                    Some(index) if next => {
+                        self.queue.splice(index + 1..index + 1, items);
+                    }
+                    _ => self.queue.extend(items),
+                }
+                if self.playing_index.is_none() && !self.queue.is_empty() {
+                    self.playing_index = Some(0);
+                }
+                let reason = if next { "QueueNext" } else { "Queue" };
+                self.send_to_all(sessions, &self.play_queue_update(reason));
+            }
+            GroupRequest::Unpause => match self.state {
+                GroupState::Idle => {
+                    // Start the current item over.
+                    if self.playing_index.is_some() {
+                        self.position_ticks = 0;
+                        self.send_to_all(
+                            sessions,
+                            &self.play_queue_update_with("NewPlaylist", true),
+                        );
+                        self.restart(sessions, true, "Unpause");
+                    }
+                }
+                GroupState::Paused | GroupState::Waiting => {
+                    self.wait(sessions, true, "Unpause");
+                }
+                GroupState::Playing => {}
+            },
+            GroupRequest::Pause => match self.state {
+                GroupState::Playing => {
+                    self.pause(sessions);
+                    self.set_state(sessions, GroupState::Paused, "Pause");
+                }
+                GroupState::Waiting => {
+                    self.resume_playback = false;
+                    self.check_ready(sessions, "Pause");
+                }
+                GroupState::Idle | GroupState::Paused => {}
+            },
+            GroupRequest::Stop => {
+                if self.state != GroupState::Idle {
+                    self.stop(sessions, "Stop");
+                }
+            }
+            GroupRequest::Seek { position_ticks } => {
+                if self.state == GroupState::Idle {
+                    return;
+                }
+                let resume = self.is_playing();
+                self.position_ticks = position_ticks.max(0);
+                self.position_updated = Utc::now();
+                self.send_command(sessions, "Seek", Utc::now());
+                self.restart(sessions, resume, "Seek");
+            }
+            GroupRequest::Buffering { playlist_item_id } => {
+                if self.state == GroupState::Idle
+                    || self.playing_item_id().as_deref() != Some(playlist_item_id.as_str())
+                {
+                    return;
+                }
+                if let Some(member) = self.members.get_mut(session_id) {
+                    member.is_buffering = true;
+                }
+                let resume = self.is_playing();
+                if self.state == GroupState::Playing {
+                    self.pause(sessions);
+                }
+                self.wait(sessions, resume, "Buffer");
+            }
+            GroupRequest::Ready { playlist_item_id } => {
+                if self.state == GroupState::Idle {
+                    return;
+                }
+                if self.playing_item_id().as_deref() != Some(playlist_item_id.as_str()) {
+                    // The member is behind, tell it what is playing now.
+                    sessions.send_to_session(session_id, &self.play_queue_update("SetCurrentItem"));
+                    return;
+                }
+                if let Some(member) = self.members.get_mut(session_id) {
+                    member.is_buffering = false;
+                }
+                self.check_ready(sessions, "Ready");
+            }
+            GroupRequest::SetIgnoreWait(ignore_wait) => {
+                if let Some(member) = self.members.get_mut(session_id) {
+                    member.ignore_wait = ignore_wait;
+                }
+                self.check_ready(sessions, "IgnoreWait");
+            }
+            GroupRequest::NextItem { playlist_item_id } => {
+                let Some(index) = self.current_index(&playlist_item_id) else {
+                    return;
+                };
+                let next = if index + 1 < self.queue.len() {
+                    index + 1
+                } else if self.repeat_mode == "RepeatAll" {
+                    0
+                } else {
+                    return;
+                };
+                self.change_item(sessions, next, "NextItem");
+            }
+            GroupRequest::PreviousItem { playlist_item_id } => {
+                let Some(index) = self.current_index(&playlist_item_id) else {
+                    return;
+                };
+                let previous = if index > 0 {
+                    index - 1
+                } else if self.repeat_mode == "RepeatAll" {
+                    self.queue.len() - 1
+                } else {
+                    return;
+                };
+                self.change_item(sessions, previous, "PreviousItem");
+            }
+            GroupRequest::SetRepeatMode(mode) => {
+                self.repeat_mode = mode;
+                self.send_to_all(sessions, &self.play_queue_update("RepeatMode"));
+            }
+            GroupRequest::SetShuffleMode(mode) => {
+                let playing = self.playing_item_id();
+                if mode == "Shuffle" && self.unshuffled.is_none() {
+                    self.unshuffled = Some(self.queue.clone());
+                    // Keep the playing item first and shuffle the rest.
+                    let mut current = Vec::new();
+                    if let Some(index) = self.playing_index {
+                        current.push(self.queue.remove(index));
+                    }
+                    self.queue.sort_by_cached_key(|_| uuid::Uuid::new_v4());
+                    current.append(&mut self.queue);
+                    self.queue = current;
+                } else if mode == "Sorted" {
+                    if let Some(unshuffled) = self.unshuffled.take() {
+                        self.queue = unshuffled;
+                    }
+                }
+                self.playing_index = playing.as_deref().and_then(|id| self.index_of(id));
+                self.send_to_all(sessions, &self.play_queue_update("ShuffleMode"));
+            }
+            GroupRequest::Ping(ping_ms) => {
+                if let Some(member) = self.members.get_mut(session_id) {
+                    member.ping_ms = ping_ms.max(0);
+                }
+            }
+        }
+    }
+
+    /// Whether playback continues once everyone is ready.
+    fn is_playing(&self) -> bool {
+        match self.state {
+            GroupState::Playing => true,
+            GroupState::Waiting => self.resume_playback,
+            GroupState::Idle | GroupState::Paused => false,
+        }
+    }
+
+    fn playing_item_id(&self) -> Option<String> {
+        self.playing_index
+            .and_then(|i| self.queue.get(i))
+            .map(|item| item.playlist_item_id.clone())
+    }
+
+    fn index_of(&self, playlist_item_id: &str) -> Option<usize> {
+        self.queue
+            .iter()
+            .position(|item| item.playlist_item_id == playlist_item_id)
+    }
+
+    /// Index of the playing item, if `playlist_item_id` is it. Requests for
+    /// other items are stale and ignored.
+    fn current_index(&self, playlist_item_id: &str) -> Option<usize> {
+        self.playing_index
+            .filter(|_| self.playing_item_id().as_deref() == Some(playlist_item_id))
+    }
+
+    fn current_position(&self, now: DateTime<Utc>) -> i64 {
+        if self.state != GroupState::Playing {
+            return self.position_ticks;
+        }
+        let elapsed_ms = (now - self.position_updated).num_milliseconds().max(0);
+        self.position_ticks + elapsed_ms * TICKS_PER_MS
+    }
+
+    fn change_item(&mut self, sessions: &SessionManager, index: usize, reason: &str) {
+        let resume = self.is_playing();
+        self.playing_index = Some(index);
+        self.position_ticks = 0;
+        self.send_to_all(sessions, &self.play_queue_update(reason));
+        self.restart(sessions, resume, reason);
+    }
+
+    /// Have every member load the current item, then continue.
+    fn restart(&mut self, sessions: &SessionManager, resume: bool, reason: &str) {
+        for member in self.members.values_mut() {
+            member.is_buffering = !member.ignore_wait;
+        }
+        self.wait(sessions, resume, reason);
+    }
+
+    fn wait(&mut self, sessions: &SessionManager, resume: bool, reason: &str) {
+        self.resume_playback = resume;
+        if self.state != GroupState::Waiting {
+            self.set_state(sessions, GroupState::Waiting, reason);
+        }
+        self.check_ready(sessions, reason);
+    }
+
+    /// Leave the waiting state once no member is buffering any more.
+    fn check_ready(&mut self, sessions: &SessionManager, reason: &str) {
+        if self.state != GroupState::Waiting
+            || self
+                .members
+                .values()
+                .any(|m| m.is_buffering && !m.ignore_wait)
+        {
+            return;
+        }
+
+        if self.resume_playback {
+            // Give the slowest member time to receive the command.
+            let highest_ping = self.members.values().map(|m| m.ping_ms).max().unwrap_or(0);
+            let delay =
+                chrono::Duration::milliseconds((highest_ping * 2).max(MIN_COMMAND_DELAY_MS));
+            let when = Utc::now() + delay;
+            self.position_updated = when;
+            self.send_command(sessions, "Unpause", when);
+            self.set_state(sessions, GroupState::Playing, reason);
+        } else {
+            self.send_command(sessions, "Pause", Utc::now());
+            self.set_state(sessions, GroupState::Paused, reason);
+        }
+    }
+
+    /// Freeze the position where playback is now and tell members to pause there.
+    fn pause(&mut self, sessions: &SessionManager) {
+        let now = Utc::now();
+        self.position_ticks = self.current_position(now);
+        self.position_updated = now;
+        self.state = GroupState::Paused;
+        self.send_command(sessions, "Pause", now);
+    }
+
+    fn stop(&mut self, sessions: &SessionManager, reason: &str) {
+        self.position_ticks = 0;
+        self.position_updated = Utc::now();
+        self.resume_playback = false;
+        self.send_command(sessions, "Stop", Utc::now());
+        self.set_state(sessions, GroupState::Idle, reason);
+    }
+
+    fn set_state(&mut self, sessions: &SessionManager, state: GroupState, reason: &str) {
+        self.state = state;
+        let data = json!({ "State": state, "Reason": reason });
+        self.send_to_all(sessions, &group_update(&self.id, "StateUpdate", data));
+    }
+
+    fn send_command(&self, sessions: &SessionManager, command: &str, when: DateTime<Utc>) {
+        let data = json!({
+            "GroupId": self.id,
+            "PlaylistItemId": self.playing_item_id().unwrap_or_default(),
+            "When": when,
+            "PositionTicks": self.position_ticks,
+            "Command": command,
+            "EmittedAt": Utc::now(),
+        });
+        self.send_to_all(
+            sessions,
+            &OutboundMessage::new("SyncPlayCommand", Some(data)),
+        );
+    }
+
+    fn play_queue_update(&self, reason: &str) -> OutboundMessage {
+        self.play_queue_update_with(reason, self.is_playing())
+    }
+
+    /// A `PlayQueue` update announcing whether the group is about to play.
+    fn play_queue_update_with(&self, reason: &str, is_playing: bool) -> OutboundMessage {
+        let data = json!({
+            "Reason": reason,
+            "LastUpdate": Utc::now(),
+            "Playlist": self.queue,
+            "PlayingItemIndex": self.playing_index.map(|i| i as i64).unwrap_or(-1),
+            "StartPositionTicks": self.position_ticks,
+            "IsPlaying": is_playing,
+            "ShuffleMode": if self.unshuffled.is_some() { "Shuffle" } else { "Sorted" },
+            "RepeatMode": self.repeat_mode,
+        });
+        group_update(&self.id, "PlayQueue", data)
+    }
+
+    fn send_to_all(&self, sessions: &SessionManager, message: &OutboundMessage) {
+        for session_id in self.members.keys() {
+            sessions.send_to_session(session_id, message);
+        }
+    }
+
+    fn send_to_others(&self, sessions: &SessionManager, except: &str, message: &OutboundMessage) {
+        for session_id in self.members.keys().filter(|id| *id != except) {
+            sessions.send_to_session(session_id, message);
+        }
+    }
+}
+
+fn new_queue_items(item_ids: Vec<String>) -> Vec<SyncPlayQueueItem> {
+    item_ids
+        .into_iter()
+        .map(|item_id| SyncPlayQueueItem {
+            item_id,
+            playlist_item_id: uuid::Uuid::new_v4().simple().to_string(),
+        })
+        .collect()
+}
+
+fn group_update(group_id: &str, update_type: &str, data: Value) -> OutboundMessage {
+    let data = json!({ "GroupId": group_id, "Type": update_type, "Data": data });
+    OutboundMessage::new("SyncPlayGroupUpdate", Some(data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jellyfin::sessionmanager::ClientInfo;
+    use tokio::sync::mpsc::UnboundedReceiver;
+
+    struct Client {
+        session: Session,
+        rx: UnboundedReceiver<OutboundMessage>,
+    }
+
+    fn setup(members: usize) -> (SyncPlayManager, Vec<Client>) {
+        let sessions = Arc::new(SessionManager::new());
+        let manager = SyncPlayManager::new(sessions.clone());
+        let members = (0..members)
+            .map(|i| {
+                let client = ClientInfo {
+                    device_id: format!("device{}", i),
+                    ..Default::default()
+                };
+                let user = format!("user{}", i);
+                let id = sessions.create(&format!("token{}", i), &user, &user, &client);
+                let (_, rx) = sessions.connect(&id, &user);
+                Client {
+                    session: sessions.get(&id).unwrap(),
+                    rx,
+                }
+            })
+            .collect();
+        (manager, members)
+    }
+
+    /// A group with all members in it, idle.
+    fn group(manager: &SyncPlayManager, members: &[Client]) -> String {
+        manager.new_group(&members[0].session, "group");
+        let group_id = manager.list_groups()[0].group_id.clone();
+        for member in &members[1..] {
+            assert!(manager.join_group(&member.session, &group_id, |_| true));
+        }
+        group_id
+    }
+
+    fn state(manager: &SyncPlayManager, group_id: &str) -> GroupState {
+        manager.get_group(group_id).unwrap().state
+    }
+
+    fn playing_item(manager: &SyncPlayManager, group_id: &str) -> String {
+        let inner = manager.inner.lock().unwrap();
+        inner.groups[group_id].playing_item_id().unwrap()
+    }
+
+    fn handle(manager: &SyncPlayManager, member: &Client, request: GroupRequest) {
+        assert!(manager.handle(&member.session.id, request, |_| true));
+    }
+
+    fn set_new_queue(manager: &SyncPlayManager, member: &Client) {
+        let request = GroupRequest::SetNewQueue {
+            item_ids: vec!["a".to_string(), "b".to_string()],
+            playing_index: 0,
+            start_position_ticks: 0,
+        };
+        handle(manager, member, request);
+    }
+
+    fn ready(manager: &SyncPlayManager, group_id: &str, member: &Client) {
+        let playlist_item_id = playing_item(manager, group_id);
+        handle(manager, member, GroupRequest::Ready { playlist_item_id });
+    }
+
+    fn buffering(manager: &SyncPlayManager, group_id: &str, member: &Client) {
+        let playlist_item_id = playing_item(manager, group_id);
+        handle(
+            manager,
+            member,
+            GroupRequest::Buffering { playlist_item_id },
+        );
+    }
+
+    /// The `Command` of the SyncPlay commands a member received.
+    fn commands(member: &mut Client) -> Vec<String> {
+        let mut commands = Vec::new();
+        while let Ok(message) = member.rx.try_recv() {
+            if message.message_type == "SyncPlayCommand" {
+                let data = message.data.unwrap();
+                commands.push(data["Command"].as_str().unwrap().to_string());
+            }
+        }
+        commands
+    }
+
+    /// A group of all members that is playing.
+    fn playing(manager: &SyncPlayManager, members: &mut [Client]) -> String {
+        let group_id = group(manager, members);
+        set_new_queue(manager, &members[0]);
+        for member in members.iter() {
+            ready(manager, &group_id, member);
+        }
+        assert_eq!(state(manager, &group_id), GroupState::Playing);
+        members.iter_mut().for_each(|member| {
+            commands(member);
+        });
+        group_id
+    }
+
+    #[test]
+    fn test_waiting_to_playing() {
+        let (manager, mut members) = setup(2);
+        let group_id = group(&manager, &members);
+        assert_eq!(state(&manager, &group_id), GroupState::Idle);
+
+        set_new_queue(&manager, &members[0]);
+        assert_eq!(state(&manager, &group_id), GroupState::Waiting);
+        ready(&manager, &group_id, &members[0]);
+        assert_eq!(state(&manager, &group_id), GroupState::Waiting);
+        ready(&manager, &group_id, &members[1]);
+        assert_eq!(state(&manager, &group_id), GroupState::Playing);
+
+        for member in &mut members {
+            assert_eq!(commands(member), ["Unpause"]);
+        }
+    }
+
+    #[test]
+    fn test_waiting_to_paused() {
+        let (manager, mut members) = setup(2);
+        let group_id = group(&manager, &members);
+
+        set_new_queue(&manager, &members[0]);
+        handle(&manager, &members[1], GroupRequest::Pause);
+        assert_eq!(state(&manager, &group_id), GroupState::Waiting);
+        ready(&manager, &group_id, &members[0]);
+        ready(&manager, &group_id, &members[1]);
+        assert_eq!(state(&manager, &group_id), GroupState::Paused);
+
+        for member in &mut members {
+            assert_eq!(commands(member), ["Pause"]);
+        }
+    }
+
+    #[test]
+    fn test_buffering_and_ready() {
+        let (manager, mut members) = setup(3);
+        let group_id = playing(&manager, &mut members);
+
+        buffering(&manager, &group_id, &members[1]);
+        assert_eq!(state(&manager, &group_id), GroupState::Waiting);
+        buffering(&manager, &group_id, &members[2]);
+        ready(&manager, &group_id, &members[1]);
+        assert_eq!(state(&manager, &group_id), GroupState::Waiting);
+        ready(&manager, &group_id, &members[2]);
+        assert_eq!(state(&manager, &group_id), GroupState::Playing);
+
+        // Paused for the first member that buffered, resumed once for all.
+        assert_eq!(commands(&mut members[0]), ["Pause", "Unpause"]);
+    }
+
+    #[test]
+    fn test_ignore_wait() {
+        let (manager, mut members) = setup(2);
+        let group_id = playing(&manager, &mut members);
+
+        handle(&manager, &members[1], GroupRequest::SetIgnoreWait(true));
+        buffering(&manager, &group_id, &members[1]);
+        assert_eq!(state(&manager, &group_id), GroupState::Playing);
+    }
+
+    #[test]
+    fn test_join_while_playing() {
+        let (manager, mut members) = setup(3);
+        let group_id = playing(&manager, &mut members[..2]);
+
+        assert!(manager.join_group(&members[2].session, &group_id, |_| true));
+        assert_eq!(state(&manager, &group_id), GroupState::Waiting);
+        assert_eq!(manager.get_group(&group_id).unwrap().participants.len(), 3);
+        assert_eq!(commands(&mut members[0]), ["Pause"]);
+
+        ready(&manager, &group_id, &members[2]);
+        assert_eq!(state(&manager, &group_id), GroupState::Playing);
+        assert_eq!(commands(&mut members[0]), ["Unpause"]);
+    }
+
+    #[test]
+    fn test_last_buffering_member_leaves() {
+        let (manager, mut members) = setup(3);
+        let group_id = playing(&manager, &mut members);
+
+        buffering(&manager, &group_id, &members[1]);
+        buffering(&manager, &group_id, &members[2]);
+        manager.leave_group(&members[1].session.id);
+        assert_eq!(state(&manager, &group_id), GroupState::Waiting);
+        // Gone without leaving, like a closed WebSocket connection.
+        manager.remove_session(&members[2].session.id);
+        assert_eq!(state(&manager, &group_id), GroupState::Playing);
+        assert_eq!(manager.get_group(&group_id).unwrap().participants.len(), 1);
+    }
+
+    #[test]
+    fn test_last_member_leaves() {
+        let (manager, members) = setup(1);
+        let group_id = group(&manager, &members);
+        manager.leave_group(&members[0].session.id);
+        assert!(manager.get_group(&group_id).is_none());
+    }
+
+    #[test]
+    fn test_items_not_visible() {
+        let (manager, members) = setup(2);
+        let group_id = group(&manager, &members[..1]);
+        let visible = |id: &str| id != "hidden";
+
+        let request = GroupRequest::SetNewQueue {
+            item_ids: vec!["a".to_string(), "hidden".to_string()],
+            playing_index: 0,
+            start_position_ticks: 0,
+        };
+        assert!(!manager.handle(&members[0].session.id, request, visible));
+        assert_eq!(state(&manager, &group_id), GroupState::Idle);
+
+        let request = GroupRequest::Queue {
+            item_ids: vec!["hidden".to_string()],
+            next: false,
+        };
+        assert!(!manager.handle(&members[0].session.id, request, visible));
+        assert!(manager.inner.lock().unwrap().groups[&group_id]
+            .queue
+            .is_empty());
+
+        // Nor can a user join a group that plays what it may not see.
+        let request = GroupRequest::SetNewQueue {
+            item_ids: vec!["hidden".to_string()],
+            playing_index: 0,
+            start_position_ticks: 0,
+        };
+        handle(&manager, &members[0], request);
+        assert!(!manager.join_group(&members[1].session, &group_id, visible));
+        assert_eq!(manager.get_group(&group_id).unwrap().participants.len(), 1);
+    }
+}
@@ -1,10 +1,12 @@
 use axum::{
+    body::Body,
     extract::State,
-    http::{header, StatusCode},
+    http::{header, Request, StatusCode},
     response::IntoResponse,
     Json,
 };
 
+use super::auth::{get_user_id, require_admin};
 use super::types::*;
 use crate::server::AppState;
 
@@ -35,6 +37,48 @@ pub async fn public_system_info(State(state): State<AppState>) -> Json<PublicSys
     })
 }
 
+/// GET /System/ImageCache
+pub async fn get_image_cache(
+    State(state): State<AppState>,
+    req: Request<Body>,
+) -> Result<Json<ImageCacheInfo>, StatusCode> {
+    let user_id = get_user_id(&req).ok_or(StatusCode::UNAUTHORIZED)?;
+    require_admin(&state, &user_id).await?;
+    let resizer = state.image_resizer.clone();
+    let stats = tokio::task::spawn_blocking(move || resizer.get_cache_stats())
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let date = |time| chrono::DateTime::<chrono::Utc>::from(time).to_rfc3339();
+    Ok(Json(ImageCacheInfo {
+        path: state.config.get_image_cache_dir().display().to_string(),
+        file_count: stats.total_files,
+        size: stats.total_size,
+        max_size: state.config.imagecache.max_size(),
+        max_age_days: state.config.imagecache.max_age_days,
+        oldest_file_date: stats.oldest_file.map(date),
+        newest_file_date: stats.newest_file.map(date),
+    }))
+}
+
+/// DELETE /System/ImageCache
+pub async fn clear_image_cache(
+    State(state): State<AppState>,
+    req: Request<Body>,
+) -> Result<StatusCode, StatusCode> {
+    let user_id = get_user_id(&req).ok_or(StatusCode::UNAUTHORIZED)?;
+    require_admin(&state, &user_id).await?;
+    let resizer = state.image_resizer.clone();
+    tokio::task::spawn_blocking(move || resizer.clear_cache())
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .map_err(|e| {
+            tracing::error!("Failed to clear image cache: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
 pub async fn plugins() -> Json<Vec<serde_json::Value>> {
     Json(vec![])
 }
@@ -9,6 +9,93 @@ pub struct AuthenticationRequest {
     pub pw: String,
 }
 
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct CreateUserRequest {
+    pub name: String,
+    #[serde(default)]
+    pub password: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct UpdatePasswordRequest {
+    #[serde(default)]
+    pub current_password: Option<String>,
+    #[serde(default)]
+    pub current_pw: Option<String>,
+    #[serde(default)]
+    pub new_pw: Option<String>,
+    #[serde(default)]
+    pub reset_password: bool,
+}
+
+/// The part of a posted `UserPolicy` that is stored.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct UpdateUserPolicyRequest {
+    #[serde(default)]
+    pub is_administrator: Option<bool>,
+    #[serde(default)]
+    pub enable_all_folders: Option<bool>,
+    #[serde(default)]
+    pub enabled_folders: Option<Vec<String>>,
+    /// Absent leaves the limit alone; null removes it.
+    #[serde(default, deserialize_with = "deserialize_present")]
+    pub max_parental_rating: Option<Option<i32>>,
+    #[serde(default)]
+    pub block_unrated_items: Option<Vec<String>>,
+    /// Enabling a user also clears its failed login count.
+    #[serde(default)]
+    pub is_disabled: Option<bool>,
+}
+
+/// Deserialize a field that is present, even if null, as `Some`.
+fn deserialize_present<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+/// An API key as listed by `/Auth/Keys`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct AuthenticationInfo {
+    pub id: i64,
+    pub access_token: String,
+    pub app_name: String,
+    pub app_version: String,
+    pub device_id: String,
+    pub device_name: String,
+    pub user_id: String,
+    pub is_active: bool,
+    pub date_created: String,
+    pub date_revoked: Option<String>,
+    pub date_last_activity: String,
+    pub user_name: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct QuickConnectDto {
+    pub secret: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct QuickConnectResult {
+    pub authenticated: bool,
+    pub secret: String,
+    pub code: String,
+    pub device_id: String,
+    pub device_name: String,
+    pub app_name: String,
+    pub app_version: String,
+    pub date_added: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct AuthenticationResult {
@@ -67,6 +154,7 @@ pub struct UserPolicy {
     pub allowed_tags: Vec<String>,
     pub enable_user_preference_access: bool,
     pub access_schedules: Vec<serde_json::Value>,
+    pub max_parental_rating: Option<i32>,
     pub block_unrated_items: Vec<String>,
     pub enable_remote_control_of_other_users: bool,
     pub enable_shared_device_control: bool,
@@ -110,6 +198,19 @@ pub struct SystemInfo {
     pub operating_system: String,
 }
 
+/// Resized image cache statistics, see `GET /System/ImageCache`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ImageCacheInfo {
+    pub path: String,
+    pub file_count: usize,
+    pub size: u64,
+    pub max_size: Option<u64>,
+    pub max_age_days: Option<u64>,
+    pub oldest_file_date: Option<String>,
+    pub newest_file_date: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct PublicSystemInfo {
@@ -7,19 +7,21 @@ use axum::{
     Json,
 };
 
-use super::auth::get_user_id;
+use super::access::get_library_access;
+use super::auth::{get_session_id, get_user_id, json_body, require_admin};
 use super::types::*;
-use crate::db::UserRepo;
+use crate::db::{AccessTokenRepo, DbError, User, UserRepo};
 use crate::jellyfin::userdata::get_default_user_data;
 use crate::server::AppState;
+use crate::util::generate_id;
 
-fn create_user_dto(user_id: String, username: String, server_id: String) -> UserDto {
+pub(crate) fn create_user_dto(user: &User, server_id: String) -> UserDto {
     let now = chrono::Utc::now().to_rfc3339();
 
     UserDto {
-        name: username,
+        name: user.username.clone(),
         server_id,
-        id: user_id,
+        id: user.id.clone(),
         has_password: false,
         has_configured_password: false,
         has_configured_easy_password: false,
@@ -44,17 +46,18 @@ fn create_user_dto(user_id: String, username: String, server_id: String) -> User
             enable_next_episode_auto_play: false,
         },
         policy: UserPolicy {
-            is_administrator: false,
+            is_administrator: user.is_admin,
             is_hidden: false,
             enable_collection_management: false,
             enable_subtitle_management: false,
             enable_lyric_management: false,
-            is_disabled: false,
+            is_disabled: user.is_disabled,
             blocked_tags: vec![],
             allowed_tags: vec![],
             enable_user_preference_access: false,
             access_schedules: vec![],
-            block_unrated_items: vec![],
+            max_parental_rating: user.max_parental_rating,
+            block_unrated_items: user.block_unrated_item_kinds(),
             enable_remote_control_of_other_users: false,
             enable_shared_device_control: false,
             enable_remote_access: true,
@@ -74,10 +77,11 @@ fn create_user_dto(user_id: String, username: String, server_id: String) -> User
             enable_all_devices: true,
             enabled_channels: vec![],
             enable_all_channels: false,
-            enabled_folders: vec![],
-            enable_all_folders: true,
-            invalid_login_attempt_count: 0,
-            login_attempts_before_lockout: 0,
+            enabled_folders: user.enabled_folder_ids(),
+            enable_all_folders: user.enable_all_folders,
+            invalid_login_attempt_count: user.invalid_login_attempts,
+            // The server default, `jellyfin.loginattemptsbeforelockout`.
+            login_attempts_before_lockout: -1,
             max_active_sessions: 0,
             enable_public_sharing: false,
             blocked_media_folders: vec![],
@@ -90,18 +94,76 @@ fn create_user_dto(user_id: String, username: String, server_id: String) -> User
     }
 }
 
-pub async fn get_users(State(state): State<AppState>) -> Result<Json<Vec<UserDto>>, StatusCode> {
-    let users: Vec<crate::db::User> = vec![];
-    let server_id = state
+/// Register a user. The first user becomes the administrator.
+pub(crate) async fn create_user(
+    state: &AppState,
+    username: &str,
+    password: &str,
+) -> Result<User, StatusCode> {
+    let username = username.trim().to_lowercase();
+    if username.is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let user = User {
+        id: generate_id(&username),
+        username,
+        password: bcrypt::hash(password, bcrypt::DEFAULT_COST)
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?,
+        created: Some(chrono::Utc::now().to_rfc3339()),
+        lastlogin: None,
+        lastused: None,
+        is_admin: false,
+        enable_all_folders: true,
+        enabled_folders: String::new(),
+        max_parental_rating: None,
+        block_unrated_items: String::new(),
+        is_disabled: false,
+        invalid_login_attempts: 0,
+    };
+    let user = state.db.create_user(&user).await.map_err(|e| match e {
+        DbError::AlreadyExists(_) => StatusCode::BAD_REQUEST,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    })?;
+    state.public_access.invalidate();
+    Ok(user)
+}
+
+fn server_id(state: &AppState) -> String {
+    state
         .config
         .jellyfin
         .server_id
         .clone()
-        .unwrap_or_else(|| "jellyfin-rs".to_string());
+        .unwrap_or_else(|| "jellyfin-rs".to_string())
+}
 
+/// GET /Users
+/// Administrators and API keys see all users, others only themselves.
+pub async fn get_users<B>(
+    State(state): State<AppState>,
+    req: Request<B>,
+) -> Result<Json<Vec<UserDto>>, StatusCode> {
+    let user_id = get_user_id(&req).ok_or(StatusCode::UNAUTHORIZED)?;
+    let users = if require_admin(&state, &user_id).await.is_ok() {
+        state
+            .db
+            .list_users()
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    } else {
+        let user = state
+            .db
+            .get_user_by_id(&user_id)
+            .await
+            .map_err(|_| StatusCode::UNAUTHORIZED)?;
+        vec![user]
+    };
+
+    let server_id = server_id(&state);
     let user_dtos: Vec<UserDto> = users
-        .into_iter()
-        .map(|u| create_user_dto(u.id, u.username, server_id.clone()))
+        .iter()
+        .map(|u| create_user_dto(u, server_id.clone()))
         .collect();
 
     Ok(Json(user_dtos))
@@ -119,13 +181,201 @@ pub async fn get_current_user<B>(
         .await
         .map_err(|_| StatusCode::NOT_FOUND)?;
 
-    let server_id = state
-        .config
-        .jellyfin
-        .server_id
-        .clone()
-        .unwrap_or_else(|| "jellyfin-rs".to_string());
-    Ok(Json(create_user_dto(user.id, user.username, server_id)))
+    Ok(Json(create_user_dto(&user, server_id(&state))))
+}
+
+/// GET /Users/{id}
+pub async fn get_user(
+    State(state): State<AppState>,
+    Path(target_id): Path<String>,
+    req: Request<axum::body::Body>,
+) -> Result<Json<UserDto>, StatusCode> {
+    let user_id = get_user_id(&req).ok_or(StatusCode::UNAUTHORIZED)?;
+    if target_id != user_id {
+        require_admin(&state, &user_id).await?;
+    }
+    let user = state
+        .db
+        .get_user_by_id(&target_id)
+        .await
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+
+    Ok(Json(create_user_dto(&user, server_id(&state))))
+}
+
+/// POST /Users/New
+pub async fn new_user(
+    State(state): State<AppState>,
+    req: Request<axum::body::Body>,
+) -> Result<Json<UserDto>, StatusCode> {
+    let user_id = get_user_id(&req).ok_or(StatusCode::UNAUTHORIZED)?;
+    require_admin(&state, &user_id).await?;
+    let req: CreateUserRequest = json_body(req).await?;
+
+    let user = create_user(
+        &state,
+        &req.name,
+        req.password.as_deref().unwrap_or_default(),
+    )
+    .await?;
+    Ok(Json(create_user_dto(&user, server_id(&state))))
+}
+
+/// DELETE /Users/{id}
+/// Removes the user with its tokens, sessions, user data and playlists.
+pub async fn delete_user(
+    State(state): State<AppState>,
+    Path(target_id): Path<String>,
+    req: Request<axum::body::Body>,
+) -> Result<StatusCode, StatusCode> {
+    let user_id = get_user_id(&req).ok_or(StatusCode::UNAUTHORIZED)?;
+    require_admin(&state, &user_id).await?;
+    let target = state
+        .db
+        .get_user_by_id(&target_id)
+        .await
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+    if target.is_admin && is_last_admin(&state, &target.id).await? {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    state
+        .db
+        .delete_user(&target.id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    state.public_access.invalidate();
+    state.sessions.end_sessions(&target.id, None);
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// POST /Users/{id}/Password
+/// Users change their own password by giving the current one; administrators
+/// can set or reset anyone's. Other sessions of the user are logged out.
+pub async fn update_password(
+    State(state): State<AppState>,
+    Path(target_id): Path<String>,
+    req: Request<axum::body::Body>,
+) -> Result<StatusCode, StatusCode> {
+    let user_id = get_user_id(&req).ok_or(StatusCode::UNAUTHORIZED)?;
+    let session_id = get_session_id(&req);
+    let body: UpdatePasswordRequest = json_body(req).await?;
+    let caller = state
+        .db
+        .get_user_by_id(&user_id)
+        .await
+        .map_err(|_| StatusCode::UNAUTHORIZED)?;
+    if target_id != caller.id && !caller.is_admin {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    let mut target = state
+        .db
+        .get_user_by_id(&target_id)
+        .await
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+
+    if !caller.is_admin {
+        let current = body
+            .current_pw
+            .as_deref()
+            .or(body.current_password.as_deref())
+            .unwrap_or_default();
+        if !bcrypt::verify(current, &target.password).unwrap_or(false) {
+            return Err(StatusCode::FORBIDDEN);
+        }
+    }
+
+    let new_password = if body.reset_password {
+        ""
+    } else {
+        body.new_pw.as_deref().unwrap_or_default()
+    };
+    target.password = bcrypt::hash(new_password, bcrypt::DEFAULT_COST)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    state
+        .db
+        .upsert_user(&target)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    // Keep the session that made the change logged in.
+    let keep = session_id
+        .and_then(|id| state.sessions.get(&id))
+        .filter(|s| s.user_id == target.id)
+        .map(|s| s.token);
+    state
+        .db
+        .delete_tokens_by_user(&target.id, keep.as_deref())
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    state.sessions.end_sessions(&target.id, keep.as_deref());
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// POST /Users/{id}/Policy
+/// Only the administrator flag, library access, parental rating limits and
+/// the disabled flag are stored; other policy fields are ignored.
+pub async fn update_policy(
+    State(state): State<AppState>,
+    Path(target_id): Path<String>,
+    req: Request<axum::body::Body>,
+) -> Result<StatusCode, StatusCode> {
+    let user_id = get_user_id(&req).ok_or(StatusCode::UNAUTHORIZED)?;
+    require_admin(&state, &user_id).await?;
+    let policy: UpdateUserPolicyRequest = json_body(req).await?;
+    let mut target = state
+        .db
+        .get_user_by_id(&target_id)
+        .await
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+
+    if let Some(is_admin) = policy.is_administrator {
+        if target.is_admin && !is_admin && is_last_admin(&state, &target.id).await? {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+        target.is_admin = is_admin;
+    }
+    if let Some(enable_all_folders) = policy.enable_all_folders {
+        target.enable_all_folders = enable_all_folders;
+    }
+    if let Some(enabled_folders) = policy.enabled_folders {
+        target.enabled_folders = enabled_folders.join(",");
+    }
+    if let Some(max_parental_rating) = policy.max_parental_rating {
+        target.max_parental_rating = max_parental_rating;
+    }
+    if let Some(block_unrated_items) = policy.block_unrated_items {
+        target.block_unrated_items = block_unrated_items.join(",");
+    }
+    if let Some(is_disabled) = policy.is_disabled {
+        if is_disabled && target.is_admin && is_last_admin(&state, &target.id).await? {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+        target.is_disabled = is_disabled;
+        if !is_disabled {
+            target.invalid_login_attempts = 0;
+        }
+    }
+
+    state
+        .db
+        .upsert_user(&target)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    state.public_access.invalidate();
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// There must always be an administrator left.
+async fn is_last_admin(state: &AppState, user_id: &str) -> Result<bool, StatusCode> {
+    let users = state
+        .db
+        .list_users()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(!users.iter().any(|u| u.is_admin && u.id != user_id))
 }
 
 pub async fn get_user_image(
@@ -137,8 +387,13 @@ pub async fn get_user_image(
     Err(StatusCode::NOT_FOUND)
 }
 
-pub async fn get_user_views(State(state): State<AppState>) -> Json<QueryResult<BaseItemDto>> {
-    let collections = state.collections.list_collections().await;
+pub async fn get_user_views(
+    State(state): State<AppState>,
+    req: Request<axum::body::Body>,
+) -> Json<QueryResult<BaseItemDto>> {
+    let collections = get_library_access(&req)
+        .list_collections(&state.collections)
+        .await;
 
     let mut items: Vec<BaseItemDto> = collections
         .iter()
@@ -338,9 +593,14 @@ pub async fn get_user_views(State(state): State<AppState>) -> Json<QueryResult<B
     })
 }
 
-pub async fn get_grouping_options(State(state): State<AppState>) -> Json<Vec<serde_json::Value>> {
+pub async fn get_grouping_options(
+    State(state): State<AppState>,
+    req: Request<axum::body::Body>,
+) -> Json<Vec<serde_json::Value>> {
     // Return list of collections as grouping options, similar to Go's behavior
-    let collections = state.collections.list_collections().await;
+    let collections = get_library_access(&req)
+        .list_collections(&state.collections)
+        .await;
     let options: Vec<serde_json::Value> = collections
         .iter()
         .map(|c| {
@@ -4,9 +4,12 @@ use axum::{
     Json,
 };
 
+use super::access::get_library_access;
 use super::auth::get_user_id;
 use super::jfitem::{convert_episode_to_dto, convert_movie_to_dto};
+use super::sessionmanager::{NowPlaying, QueueItem, SessionId};
 use super::types::*;
+use super::websocket::notify_user_data_changed;
 use crate::collection::{Item, ItemRef};
 use crate::db::UserDataRepo;
 use crate::server::AppState;
@@ -18,6 +21,7 @@ pub async fn get_resume_items(
     req: Request<axum::body::Body>,
 ) -> Result<Json<QueryResult<BaseItemDto>>, StatusCode> {
     let user_id = get_user_id(&req).ok_or(StatusCode::UNAUTHORIZED)?;
+    let access = get_library_access(&req);
 
     let limit = params
         .get("limit")
@@ -40,7 +44,9 @@ pub async fn get_resume_items(
 
         // Efficiently finding items by ID using the global lookup map
         for data in &db_user_data {
-            if let Some((collection_id, found_item)) = state.collections.get_item(&data.itemid) {
+            if let Some((collection_id, found_item)) =
+                access.get_item(&state.collections, &data.itemid)
+            {
                 if let Some(collection) = state.collections.get_collection(&collection_id).await {
                     match found_item {
                         Item::Movie(movie) => {
@@ -119,6 +125,9 @@ async fn toggle_played(
     is_played: bool,
 ) -> Result<Json<UserData>, StatusCode> {
     let user_id = get_user_id(&req).ok_or(StatusCode::UNAUTHORIZED)?;
+    get_library_access(&req)
+        .get_item(&state.collections, &item_id)
+        .ok_or(StatusCode::NOT_FOUND)?;
 
     let mut user_data = state
         .db
@@ -136,6 +145,7 @@ async fn toggle_played(
         .upsert_user_data(&user_data)
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    notify_user_data_changed(&state, &user_data);
 
     Ok(Json(UserData {
         playback_position_ticks: 0,
@@ -172,6 +182,9 @@ pub async fn toggle_favorite(
     is_favorite: bool,
 ) -> Result<Json<UserData>, StatusCode> {
     let user_id = get_user_id(&req).ok_or(StatusCode::UNAUTHORIZED)?;
+    get_library_access(&req)
+        .get_item(&state.collections, &item_id)
+        .ok_or(StatusCode::NOT_FOUND)?;
 
     let mut user_data = state
         .db
@@ -187,6 +200,7 @@ pub async fn toggle_favorite(
         .upsert_user_data(&user_data)
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    notify_user_data_changed(&state, &user_data);
 
     Ok(Json(UserData {
         playback_position_ticks: user_data.position.unwrap_or(0),
@@ -223,6 +237,9 @@ pub async fn update_playback_position(
     req: Request<axum::body::Body>,
 ) -> Result<StatusCode, StatusCode> {
     let user_id = get_user_id(&req).ok_or(StatusCode::UNAUTHORIZED)?;
+    get_library_access(&req)
+        .get_item(&state.collections, &item_id)
+        .ok_or(StatusCode::NOT_FOUND)?;
 
     let position_ticks = params
         .get("positionTicks")
@@ -242,11 +259,13 @@ pub async fn update_playback_position(
         .upsert_user_data(&user_data)
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    notify_user_data_changed(&state, &user_data);
 
     Ok(StatusCode::NO_CONTENT)
 }
 
-/// Request body for /Sessions/Playing/Progress
+/// Request body for /Sessions/Playing, /Sessions/Playing/Progress and
+/// /Sessions/Playing/Stopped
 #[derive(Debug, serde::Deserialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct PlayingProgressRequest {
@@ -254,35 +273,103 @@ pub struct PlayingProgressRequest {
     pub item_id: Option<String>,
     #[serde(default)]
     pub position_ticks: i64,
-    #[allow(dead_code)]
     pub media_source_id: Option<String>,
-    #[allow(dead_code)]
     pub audio_stream_index: Option<i32>,
-    #[allow(dead_code)]
     pub subtitle_stream_index: Option<i32>,
-    #[allow(dead_code)]
     pub play_session_id: Option<String>,
+    #[serde(default)]
+    pub can_seek: bool,
+    #[serde(default)]
+    pub is_paused: bool,
+    #[serde(default)]
+    pub is_muted: bool,
+    pub volume_level: Option<i32>,
+    pub play_method: Option<String>,
+    pub repeat_mode: Option<String>,
+    pub playback_order: Option<String>,
+    #[serde(default)]
+    pub now_playing_queue: Vec<QueueItem>,
+}
+
+impl PlayingProgressRequest {
+    fn to_now_playing(&self, item_id: &str) -> NowPlaying {
+        NowPlaying {
+            item_id: item_id.to_string(),
+            media_source_id: self.media_source_id.clone(),
+            position_ticks: self.position_ticks,
+            can_seek: self.can_seek,
+            is_paused: self.is_paused,
+            is_muted: self.is_muted,
+            volume_level: self.volume_level,
+            audio_stream_index: self.audio_stream_index,
+            subtitle_stream_index: self.subtitle_stream_index,
+            play_method: self.play_method.clone(),
+            play_session_id: self.play_session_id.clone(),
+            repeat_mode: self.repeat_mode.clone(),
+            playback_order: self.playback_order.clone(),
+            queue: self.now_playing_queue.clone(),
+        }
+    }
 }
 
-/// POST /Sessions/Playing/Progress
-/// Updates playback progress for an item
+/// POST /Sessions/Playing and /Sessions/Playing/Progress
+/// Updates playback progress for an item and the now-playing state of the session
 pub async fn session_playing_progress(
     axum::Extension(user_id): axum::Extension<String>,
+    session_id: Option<axum::Extension<SessionId>>,
     State(state): State<AppState>,
     Json(progress): Json<PlayingProgressRequest>,
 ) -> Result<StatusCode, StatusCode> {
-    let item_id = match progress.item_id {
-        Some(id) => id,
+    let item_id = match &progress.item_id {
+        Some(id) => id.clone(),
         None => return Ok(StatusCode::BAD_REQUEST), // item_id is required
     };
 
+    if let Some(axum::Extension(SessionId(session_id))) = session_id {
+        state
+            .sessions
+            .set_now_playing(&session_id, progress.to_now_playing(&item_id));
+    }
+
+    save_position(&state, &user_id, &item_id, progress.position_ticks).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// POST /Sessions/Playing/Stopped
+pub async fn session_playing_stopped(
+    axum::Extension(user_id): axum::Extension<String>,
+    session_id: Option<axum::Extension<SessionId>>,
+    State(state): State<AppState>,
+    Json(progress): Json<PlayingProgressRequest>,
+) -> Result<StatusCode, StatusCode> {
+    let item_id = match &progress.item_id {
+        Some(id) => id.clone(),
+        None => return Ok(StatusCode::BAD_REQUEST), // item_id is required
+    };
+
+    if let Some(axum::Extension(SessionId(session_id))) = session_id {
+        state.sessions.clear_now_playing(&session_id, &item_id);
+    }
+
+    save_position(&state, &user_id, &item_id, progress.position_ticks).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn save_position(
+    state: &AppState,
+    user_id: &str,
+    item_id: &str,
+    position_ticks: i64,
+) -> Result<(), StatusCode> {
     let mut user_data = state
         .db
-        .get_user_data(&user_id, &item_id)
+        .get_user_data(user_id, item_id)
         .await
-        .unwrap_or_else(|_| get_default_db_user_data(&user_id, &item_id));
+        .unwrap_or_else(|_| get_default_db_user_data(user_id, item_id));
 
-    user_data.position = Some(progress.position_ticks);
+    user_data.position = Some(position_ticks);
     user_data.timestamp = Some(chrono::Utc::now());
 
     state
@@ -290,36 +377,28 @@ pub async fn session_playing_progress(
         .upsert_user_data(&user_data)
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-    Ok(StatusCode::NO_CONTENT)
+    notify_user_data_changed(state, &user_data);
+    Ok(())
 }
 
 pub async fn delete_playing_item(
     axum::Extension(user_id): axum::Extension<String>,
+    session_id: Option<axum::Extension<SessionId>>,
     State(state): State<AppState>,
     Path(item_id): Path<String>,
     Query(params): Query<QueryParams>,
 ) -> Result<StatusCode, StatusCode> {
+    if let Some(axum::Extension(SessionId(session_id))) = session_id {
+        state.sessions.clear_now_playing(&session_id, &item_id);
+    }
+
     // If positionTicks is provided, save it one last time
     let position_ticks = params
         .get("positionTicks")
         .and_then(|s| s.parse::<i64>().ok());
 
     if let Some(pos) = position_ticks {
-        let mut user_data = state
-            .db
-            .get_user_data(&user_id, &item_id)
-            .await
-            .unwrap_or_else(|_| get_default_db_user_data(&user_id, &item_id));
-
-        user_data.position = Some(pos);
-        user_data.timestamp = Some(chrono::Utc::now());
-
-        state
-            .db
-            .upsert_user_data(&user_data)
-            .await
-            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        save_position(&state, &user_id, &item_id, pos).await?;
     }
 
     Ok(StatusCode::NO_CONTENT)
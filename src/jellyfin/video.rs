@@ -8,6 +8,7 @@ use std::path::PathBuf;
 use tower::ServiceExt;
 use tower_http::services::ServeFile;
 
+use super::access::{get_library_access, LibraryAccess};
 use crate::collection::Item;
 use crate::server::AppState;
 
@@ -16,7 +17,8 @@ pub async fn stream_video_with_range(
     Path(item_id): Path<String>,
     req: Request,
 ) -> Result<Response, StatusCode> {
-    let (file_path, _file_size) = find_video_file(&state, &item_id).await?;
+    let access = get_library_access(&req);
+    let (file_path, _file_size) = find_video_file(&state, &access, &item_id).await?;
 
     let service = ServeFile::new(file_path);
     let response = service
@@ -30,8 +32,10 @@ pub async fn stream_video_with_range(
 pub async fn stream_subtitle(
     State(state): State<AppState>,
     Path((item_id, index)): Path<(String, usize)>,
+    req: Request,
 ) -> Result<Response, StatusCode> {
-    let subtitle_path = find_subtitle_file(&state, &item_id, index).await?;
+    let access = get_library_access(&req);
+    let subtitle_path = find_subtitle_file(&state, &access, &item_id, index).await?;
 
     let content = tokio::fs::read(&subtitle_path)
         .await
@@ -46,11 +50,15 @@ pub async fn stream_subtitle(
     Ok(([(header::CONTENT_TYPE, content_type)], content).into_response())
 }
 
-async fn find_video_file(state: &AppState, item_id: &str) -> Result<(PathBuf, u64), StatusCode> {
-    if let Some((_, item)) = state.collections.get_item(item_id) {
+async fn find_video_file(
+    state: &AppState,
+    access: &LibraryAccess,
+    item_id: &str,
+) -> Result<(PathBuf, u64), StatusCode> {
+    if let Some((_, item)) = access.get_item(&state.collections, item_id) {
         let media_sources = match item {
-            Item::Movie(m) => m.media_sources,
-            Item::Episode(e) => e.media_sources,
+            Item::Movie(m) => m.media_sources.clone(),
+            Item::Episode(e) => e.media_sources.clone(),
             _ => return Err(StatusCode::NOT_FOUND),
         };
 
@@ -67,13 +75,14 @@ async fn find_video_file(state: &AppState, item_id: &str) -> Result<(PathBuf, u6
 
 async fn find_subtitle_file(
     state: &AppState,
+    access: &LibraryAccess,
     item_id: &str,
     index: usize,
 ) -> Result<PathBuf, StatusCode> {
-    if let Some((_, item)) = state.collections.get_item(item_id) {
+    if let Some((_, item)) = access.get_item(&state.collections, item_id) {
         let media_sources = match item {
-            Item::Movie(m) => m.media_sources,
-            Item::Episode(e) => e.media_sources,
+            Item::Movie(m) => m.media_sources.clone(),
+            Item::Episode(e) => e.media_sources.clone(),
             _ => return Err(StatusCode::NOT_FOUND),
         };
 
@@ -0,0 +1,307 @@
+// Jellyfin WebSocket protocol:
+// https://jellyfin.org/docs/general/networking/#websockets (message types are
+// those of MediaBrowser.Model.Session.SessionMessageType).
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        State,
+    },
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::time::{interval, Duration, Instant, MissedTickBehavior};
+use tracing::{debug, warn};
+
+use super::access::LibraryAccess;
+use super::session::{session_to_dto, visible_sessions, SessionInfo};
+use super::sessionmanager::SessionId;
+use super::types::UserData;
+use crate::collection::LibraryChange;
+use crate::db::UserRepo;
+use crate::server::AppState;
+
+/// Clients are asked to send a KeepAlive at least this often (in seconds).
+const KEEPALIVE_SECS: u64 = 60;
+
+/// A message pushed to clients.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct OutboundMessage {
+    pub message_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+    pub message_id: String,
+}
+
+impl OutboundMessage {
+    pub fn new(message_type: &str, data: Option<Value>) -> Self {
+        Self {
+            message_type: message_type.to_string(),
+            data,
+            message_id: uuid::Uuid::new_v4().simple().to_string(),
+        }
+    }
+}
+
+/// A message sent by a client.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct InboundMessage {
+    message_type: String,
+    #[serde(default)]
+    data: Value,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct LibraryUpdateInfo {
+    folders_added_to: Vec<String>,
+    folders_removed_from: Vec<String>,
+    items_added: Vec<String>,
+    items_removed: Vec<String>,
+    items_updated: Vec<String>,
+    collection_folders: Vec<String>,
+    is_empty: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct UserDataChangeInfo {
+    user_id: String,
+    user_data_list: Vec<UserItemDataDto>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct UserItemDataDto {
+    #[serde(flatten)]
+    data: UserData,
+    item_id: String,
+}
+
+/// GET /socket
+pub async fn websocket_handler(
+    State(state): State<AppState>,
+    user_id: Option<axum::Extension<String>>,
+    session_id: Option<axum::Extension<SessionId>>,
+    access: Option<axum::Extension<LibraryAccess>>,
+    ws: WebSocketUpgrade,
+) -> Response {
+    let (
+        Some(axum::Extension(user_id)),
+        Some(axum::Extension(SessionId(session_id))),
+        Some(axum::Extension(access)),
+    ) = (user_id, session_id, access)
+    else {
+        return StatusCode::UNAUTHORIZED.into_response();
+    };
+
+    ws.on_upgrade(move |socket| handle_socket(state, socket, user_id, session_id, access))
+}
+
+async fn handle_socket(
+    state: AppState,
+    mut socket: WebSocket,
+    user_id: String,
+    session_id: String,
+    access: LibraryAccess,
+) {
+    let (socket_id, mut rx) = state.sessions.connect(&session_id, &user_id);
+    debug!(
+        "WebSocket {} connected for session {}",
+        socket_id, session_id
+    );
+
+    let force_keepalive = OutboundMessage::new("ForceKeepAlive", Some(KEEPALIVE_SECS.into()));
+    if send(&mut socket, &force_keepalive).await.is_err() {
+        state.sessions.disconnect(socket_id);
+        return;
+    }
+
+    let mut last_seen = Instant::now();
+    let mut idle_check = interval(Duration::from_secs(KEEPALIVE_SECS));
+    idle_check.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    // Sessions updates the client subscribed to with SessionsStart.
+    let mut sessions_timer = None;
+
+    loop {
+        tokio::select! {
+            msg = socket.recv() => {
+                let text = match msg {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(Message::Close(_))) | None | Some(Err(_)) => break,
+                    Some(Ok(_)) => continue,
+                };
+                last_seen = Instant::now();
+                state.sessions.keep_alive(&session_id);
+
+                let Ok(inbound) = serde_json::from_str::<InboundMessage>(&text) else {
+                    debug!("Ignoring malformed WebSocket message: {}", text);
+                    continue;
+                };
+                match inbound.message_type.as_str() {
+                    "KeepAlive" => {
+                        if send(&mut socket, &OutboundMessage::new("KeepAlive", None)).await.is_err() {
+                            break;
+                        }
+                    }
+                    "SessionsStart" => {
+                        sessions_timer = Some(subscription_timer(&inbound.data));
+                    }
+                    "SessionsStop" => {
+                        sessions_timer = None;
+                    }
+                    other => debug!("Ignoring WebSocket message type {}", other),
+                }
+            }
+            Some(message) = rx.recv() => {
+                if send(&mut socket, &message).await.is_err() {
+                    break;
+                }
+            }
+            _ = tick(&mut sessions_timer) => {
+                let mut sessions: Vec<SessionInfo> = Vec::new();
+                for session in visible_sessions(&state, &user_id).await {
+                    sessions.push(session_to_dto(&state, &access, &session).await);
+                }
+                let data = serde_json::to_value(sessions).unwrap_or_default();
+                if send(&mut socket, &OutboundMessage::new("Sessions", Some(data))).await.is_err() {
+                    break;
+                }
+            }
+            _ = idle_check.tick() => {
+                if last_seen.elapsed() > Duration::from_secs(KEEPALIVE_SECS * 2) {
+                    debug!("WebSocket {} timed out", socket_id);
+                    break;
+                }
+            }
+        }
+    }
+
+    state.sessions.disconnect(socket_id);
+    // A SyncPlay member that cannot receive commands would stall its group.
+    if !state.sessions.is_connected(&session_id) {
+        state.syncplay.remove_session(&session_id);
+    }
+    debug!("WebSocket {} disconnected", socket_id);
+}
+
+async fn send(socket: &mut WebSocket, message: &OutboundMessage) -> Result<(), axum::Error> {
+    let text = serde_json::to_string(message).unwrap_or_default();
+    socket.send(Message::Text(text)).await
+}
+
+/// Parse the "initialDelayMs,intervalMs" data of a *Start message.
+fn subscription_timer(data: &Value) -> tokio::time::Interval {
+    let text = data.as_str().unwrap_or_default();
+    let mut parts = text.split(',').map(|p| p.trim().parse::<u64>().ok());
+    let delay = parts.next().flatten().unwrap_or(0);
+    let period = parts.next().flatten().unwrap_or(1500).max(1000);
+
+    let mut timer = tokio::time::interval_at(
+        Instant::now() + Duration::from_millis(delay),
+        Duration::from_millis(period),
+    );
+    timer.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    timer
+}
+
+async fn tick(timer: &mut Option<tokio::time::Interval>) {
+    match timer {
+        Some(timer) => {
+            timer.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+/// Forward library changes from the collection scanner to the connected
+/// clients of users that can access the collection.
+pub fn start_library_notifications(state: &AppState) {
+    let mut changes = state.collections.subscribe();
+    let sessions = state.sessions.clone();
+    let db = state.db.clone();
+
+    tokio::spawn(async move {
+        loop {
+            let change = match changes.recv().await {
+                Ok(change) => change,
+                Err(RecvError::Lagged(n)) => {
+                    warn!("Dropped {} library change notifications", n);
+                    continue;
+                }
+                Err(RecvError::Closed) => break,
+            };
+            let users = match db.list_users().await {
+                Ok(users) => users,
+                Err(e) => {
+                    warn!("Cannot list users for library change notification: {}", e);
+                    continue;
+                }
+            };
+            let collection_id = change.collection_id.clone();
+            let data = serde_json::to_value(library_update_info(change)).unwrap_or_default();
+            let message = OutboundMessage::new("LibraryChanged", Some(data));
+            for user in users
+                .iter()
+                .filter(|u| LibraryAccess::for_user(u).allows(&collection_id))
+            {
+                sessions.send_to_user(&user.id, &message);
+            }
+        }
+    });
+}
+
+fn library_update_info(change: LibraryChange) -> LibraryUpdateInfo {
+    let folders_added_to = if change.added.is_empty() && change.updated.is_empty() {
+        vec![]
+    } else {
+        vec![change.collection_id.clone()]
+    };
+    let folders_removed_from = if change.removed.is_empty() {
+        vec![]
+    } else {
+        vec![change.collection_id.clone()]
+    };
+
+    LibraryUpdateInfo {
+        folders_added_to,
+        folders_removed_from,
+        items_added: change.added,
+        items_removed: change.removed,
+        items_updated: change.updated,
+        collection_folders: vec![change.collection_id],
+        is_empty: false,
+    }
+}
+
+/// Tell all connected clients of a user that its played state, position or
+/// favorite flag of an item changed.
+pub fn notify_user_data_changed(state: &AppState, user_data: &crate::db::UserData) {
+    let info = UserDataChangeInfo {
+        user_id: user_data.userid.clone(),
+        user_data_list: vec![UserItemDataDto {
+            data: UserData {
+                playback_position_ticks: user_data.position.unwrap_or(0),
+                played_percentage: user_data.playedpercentage.map(|p| p as f64).unwrap_or(0.0),
+                play_count: user_data.playcount.unwrap_or(0),
+                is_favorite: user_data.favorite.unwrap_or(false),
+                last_played_date: user_data.timestamp.map(|t| t.to_rfc3339()),
+                played: user_data.played.unwrap_or(false),
+                key: user_data.itemid.clone(),
+                unplayed_item_count: None,
+            },
+            item_id: user_data.itemid.clone(),
+        }],
+    };
+    let data = serde_json::to_value(info).unwrap_or_default();
+    state.sessions.send_to_user(
+        &user_data.userid,
+        &OutboundMessage::new("UserDataChanged", Some(data)),
+    );
+}
@@ -2,6 +2,7 @@ pub mod collection;
 pub mod config;
 pub mod db;
 pub mod jellyfin;
+pub mod logging;
 pub mod middleware;
 pub mod notflix;
 pub mod server;
@@ -11,7 +12,7 @@ use std::fs::File;
 use std::io::BufReader;
 use std::net::SocketAddr;
 use std::sync::Arc;
-use tracing::info;
+use tracing::{error, info, warn};
 
 #[derive(Debug, thiserror::Error)]
 pub enum ServerError {
@@ -43,7 +44,7 @@ pub async fn run(config_path: &str, debug_logs: bool) -> Result<(), ServerError>
     db.clone().start_background_tasks();
 
     let collection_repo =
-        Arc::new(collection::CollectionRepo::new().map_err(|e| {
+        Arc::new(collection::CollectionRepo::new(db.clone()).map_err(|e| {
             ServerError::Server(format!("Failed to create collection repo: {}", e))
         })?);
 
@@ -54,18 +55,45 @@ pub async fn run(config_path: &str, debug_logs: bool) -> Result<(), ServerError>
             .map_err(|e| ServerError::Server(format!("Failed to add collection: {}", e)))?;
     }
 
-    info!("Performing initial collection scan...");
+    // Serve what the previous run stored right away, and reconcile it with
+    // the filesystem in the background.
     collection_repo
-        .scan_all()
+        .load_from_db()
         .await
-        .map_err(|e| ServerError::Server(format!("Failed to scan collections: {}", e)))?;
+        .map_err(|e| ServerError::Server(format!("Failed to load collections: {}", e)))?;
 
-    collection_repo.clone().start_background_scan(3600);
+    let scan_repo = collection_repo.clone();
+    tokio::spawn(async move {
+        info!("Performing initial collection scan...");
+        if let Err(e) = scan_repo.scan_all().await {
+            error!("Initial collection scan failed: {}", e);
+        }
+
+        // Pick up changes as they happen; only fall back to hourly full rescans
+        // if the filesystem cannot be watched.
+        if let Err(e) = scan_repo.clone().start_watchers() {
+            warn!(
+                "Cannot watch collection directories, rescanning every hour: {}",
+                e
+            );
+            scan_repo.start_background_scan(3600);
+        }
+    });
 
-    let cache_dir = std::path::PathBuf::from("./cache/images");
+    let cache_dir = config.get_image_cache_dir();
+    info!("Caching resized images in {}", cache_dir.display());
     let image_resizer = Arc::new(
-        util::ImageResizer::new(cache_dir)
-            .map_err(|e| ServerError::Server(format!("Failed to create image resizer: {}", e)))?,
+        util::ImageResizer::new(
+            cache_dir,
+            config.imagecache.resize_workers(),
+            config.imagecache.resize_queue,
+        )
+        .map_err(|e| ServerError::Server(format!("Failed to create image resizer: {}", e)))?,
+    );
+    image_resizer.clone().start_background_cleanup(
+        config.imagecache.max_size(),
+        config.imagecache.max_age_days,
+        3600,
     );
 
     let address = config.listen.address.as_deref().unwrap_or("[::]");
@@ -77,6 +105,7 @@ pub async fn run(config_path: &str, debug_logs: bool) -> Result<(), ServerError>
     let has_tls = config.listen.tlscert.is_some() && config.listen.tlskey.is_some();
 
     let state = server::AppState::new(config.clone(), db, collection_repo, image_resizer);
+    jellyfin::websocket::start_library_notifications(&state);
     let app = server::build_router(state);
 
     if has_tls {
@@ -106,7 +135,7 @@ pub async fn run(config_path: &str, debug_logs: bool) -> Result<(), ServerError>
         info!("Serving HTTPS on {}", addr);
 
         axum_server::bind_rustls(addr, tls_config)
-            .serve(app.into_make_service())
+            .serve(app.into_make_service_with_connect_info::<SocketAddr>())
             .await
             .map_err(|e| ServerError::Server(format!("Server error: {}", e)))?;
     } else {
@@ -116,9 +145,12 @@ pub async fn run(config_path: &str, debug_logs: bool) -> Result<(), ServerError>
             .await
             .map_err(|e| ServerError::Server(format!("Failed to bind: {}", e)))?;
 
-        axum::serve(listener, app)
-            .await
-            .map_err(|e| ServerError::Server(format!("Server error: {}", e)))?;
+        axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .await
+        .map_err(|e| ServerError::Server(format!("Server error: {}", e)))?;
     }
 
     Ok(())
@@ -0,0 +1,419 @@
+use chrono::{DateTime, Local, NaiveDate};
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::field::{Field, Visit};
+use tracing::{info, Event, Subscriber};
+use tracing_subscriber::fmt::format::{FormatEvent, FormatFields, Writer};
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+use tracing_subscriber::fmt::FmtContext;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::{filter, layer::SubscriberExt, util::SubscriberInitExt, Layer};
+
+use crate::config::{AccessLogFormat, Config, LogRotateConfig};
+
+/// Target of the HTTP access log records written by `AccessRecord::log`.
+pub const ACCESS_LOG_TARGET: &str = "jellofin_rs::access";
+
+/// Set up the application log (`logfile`) and the access log
+/// (`logging.accesslog`). Log files are reopened on SIGHUP.
+pub fn init(config: &Config) -> io::Result<()> {
+    let rotate = &config.logging.rotate;
+    let mut files = Vec::new();
+
+    let app_writer = make_writer(&config.logfile, rotate, &mut files)?;
+    let access_writer = match &config.logging.access_log {
+        Some(destination) => Some(make_writer(destination, rotate, &mut files)?),
+        None => None,
+    };
+
+    let env_filter = tracing_subscriber::EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| "jellofin_rs=info,tower_http=info".into());
+
+    // With a separate access log, access records stay out of the application log.
+    let has_access_log = access_writer.is_some();
+    let app_layer = tracing_subscriber::fmt::layer()
+        .json()
+        .with_writer(app_writer)
+        .with_filter(filter::filter_fn(move |metadata| {
+            !has_access_log || metadata.target() != ACCESS_LOG_TARGET
+        }));
+
+    let access_layer = access_writer.map(|writer| {
+        let only_access = filter::filter_fn(|metadata| metadata.target() == ACCESS_LOG_TARGET);
+        match config.logging.access_log_format {
+            AccessLogFormat::Combined => tracing_subscriber::fmt::layer()
+                .event_format(MessageOnly)
+                .with_writer(writer)
+                .with_filter(only_access)
+                .boxed(),
+            AccessLogFormat::Json => tracing_subscriber::fmt::layer()
+                .json()
+                .flatten_event(true)
+                .with_writer(writer)
+                .with_filter(only_access)
+                .boxed(),
+        }
+    });
+
+    tracing_subscriber::registry()
+        .with(env_filter)
+        .with(app_layer)
+        .with(access_layer)
+        .init();
+
+    #[cfg(unix)]
+    reopen_on_sighup(files);
+
+    Ok(())
+}
+
+fn make_writer(
+    destination: &str,
+    rotate: &LogRotateConfig,
+    files: &mut Vec<Arc<LogFile>>,
+) -> io::Result<BoxMakeWriter> {
+    // The Go server's default is `/dev/stdout`, which cannot be rotated
+    // like a file. `none` discards the log.
+    Ok(match destination {
+        "stdout" | "/dev/stdout" | "" => BoxMakeWriter::new(io::stdout),
+        "stderr" | "/dev/stderr" => BoxMakeWriter::new(io::stderr),
+        "none" => BoxMakeWriter::new(io::sink),
+        path => {
+            let file = Arc::new(LogFile::open(Path::new(path), rotate.clone())?);
+            files.push(file.clone());
+            BoxMakeWriter::new(move || LogWriter(file.clone()))
+        }
+    })
+}
+
+#[cfg(unix)]
+fn reopen_on_sighup(files: Vec<Arc<LogFile>>) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    if files.is_empty() {
+        return;
+    }
+    tokio::spawn(async move {
+        let Ok(mut hangup) = signal(SignalKind::hangup()) else {
+            return;
+        };
+        while hangup.recv().await.is_some() {
+            for file in &files {
+                if let Err(e) = file.reopen() {
+                    eprintln!("Cannot reopen log file {}: {}", file.path.display(), e);
+                }
+            }
+            info!("Reopened log files");
+        }
+    });
+}
+
+/// A log file that rotates by size and/or date. Rotated files are renamed
+/// to `<path>.1`, `<path>.2`, ... with `.1` the most recent.
+pub struct LogFile {
+    path: PathBuf,
+    rotate: LogRotateConfig,
+    current: Mutex<OpenLogFile>,
+}
+
+struct OpenLogFile {
+    file: File,
+    size: u64,
+    /// Local date of the last write, for daily rotation.
+    date: NaiveDate,
+}
+
+impl LogFile {
+    pub fn open(path: &Path, rotate: LogRotateConfig) -> io::Result<Self> {
+        let current = open_append(path)
+            .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            rotate,
+            current: Mutex::new(current),
+        })
+    }
+
+    /// Reopen the file, for example after an external tool moved it away.
+    pub fn reopen(&self) -> io::Result<()> {
+        let reopened = open_append(&self.path)?;
+        *self.current.lock().unwrap() = reopened;
+        Ok(())
+    }
+
+    fn write_all(&self, buf: &[u8]) -> io::Result<()> {
+        let mut current = self.current.lock().unwrap();
+        let today = Local::now().date_naive();
+        let too_big = self.rotate.max_size > 0
+            && current.size > 0
+            && current.size + buf.len() as u64 > self.rotate.max_size;
+        let new_day = self.rotate.daily && current.date != today;
+        if too_big || new_day {
+            self.rotate_files()?;
+            *current = open_append(&self.path)?;
+        }
+        current.file.write_all(buf)?;
+        current.size += buf.len() as u64;
+        current.date = today;
+        Ok(())
+    }
+
+    fn rotate_files(&self) -> io::Result<()> {
+        let numbered = |n: usize| {
+            let mut name = self.path.clone().into_os_string();
+            name.push(format!(".{}", n));
+            PathBuf::from(name)
+        };
+        if self.rotate.keep == 0 {
+            return std::fs::remove_file(&self.path);
+        }
+        let _ = std::fs::remove_file(numbered(self.rotate.keep));
+        for n in (1..self.rotate.keep).rev() {
+            let from = numbered(n);
+            if from.exists() {
+                std::fs::rename(from, numbered(n + 1))?;
+            }
+        }
+        std::fs::rename(&self.path, numbered(1))
+    }
+}
+
+fn open_append(path: &Path) -> io::Result<OpenLogFile> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let metadata = file.metadata()?;
+    let date = metadata
+        .modified()
+        .map(|time| DateTime::<Local>::from(time).date_naive())
+        .unwrap_or_else(|_| Local::now().date_naive());
+    Ok(OpenLogFile {
+        file,
+        size: metadata.len(),
+        date,
+    })
+}
+
+struct LogWriter(Arc<LogFile>);
+
+impl Write for LogWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write_all(buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Writes just the message of an event, for preformatted access log lines.
+struct MessageOnly;
+
+impl<S, N> FormatEvent<S, N> for MessageOnly
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        _ctx: &FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> std::fmt::Result {
+        let mut visitor = MessageVisitor {
+            writer: &mut writer,
+            result: Ok(()),
+        };
+        event.record(&mut visitor);
+        visitor.result?;
+        writeln!(writer)
+    }
+}
+
+struct MessageVisitor<'a, 'w> {
+    writer: &'a mut Writer<'w>,
+    result: std::fmt::Result,
+}
+
+impl Visit for MessageVisitor<'_, '_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            self.result = write!(self.writer, "{:?}", value);
+        }
+    }
+}
+
+/// One request of the HTTP access log.
+pub struct AccessRecord<'a> {
+    pub remote_address: &'a str,
+    pub user: Option<&'a str>,
+    pub device: Option<&'a str>,
+    pub method: &'a str,
+    /// Path and query, with credentials redacted.
+    pub url: &'a str,
+    pub version: &'a str,
+    pub status: u16,
+    /// Body size, if known.
+    pub bytes: Option<u64>,
+    /// Time until the response headers were ready.
+    pub duration: Duration,
+    pub referer: Option<&'a str>,
+    pub user_agent: Option<&'a str>,
+}
+
+impl AccessRecord<'_> {
+    pub fn log(&self, format: AccessLogFormat) {
+        let duration_ms = self.duration.as_millis() as u64;
+        match format {
+            AccessLogFormat::Combined => {
+                let quoted = |value: Option<&str>| value.unwrap_or("-").replace('"', "\\\"");
+                info!(
+                    target: ACCESS_LOG_TARGET,
+                    "{} - {} [{}] \"{} {} {}\" {} {} \"{}\" \"{}\" \"{}\" {}",
+                    or_dash(self.remote_address),
+                    self.user.map_or("-".to_string(), |user| user.replace(' ', "_")),
+                    Local::now().format("%d/%b/%Y:%H:%M:%S %z"),
+                    self.method,
+                    self.url,
+                    self.version,
+                    self.status,
+                    self.bytes.map_or("-".to_string(), |bytes| bytes.to_string()),
+                    quoted(self.referer),
+                    quoted(self.user_agent),
+                    quoted(self.device),
+                    duration_ms
+                );
+            }
+            AccessLogFormat::Json => {
+                info!(
+                    target: ACCESS_LOG_TARGET,
+                    remote_address = self.remote_address,
+                    user = self.user,
+                    device = self.device,
+                    method = self.method,
+                    url = self.url,
+                    version = self.version,
+                    status = self.status,
+                    bytes = self.bytes,
+                    duration_ms = duration_ms,
+                    referer = self.referer,
+                    user_agent = self.user_agent,
+                    "HTTP request"
+                );
+            }
+        }
+    }
+}
+
+fn or_dash(value: &str) -> &str {
+    if value.is_empty() {
+        "-"
+    } else {
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new() -> Self {
+            let dir = std::env::temp_dir()
+                .join(format!("jellofin-test-{}", uuid::Uuid::new_v4().simple()));
+            std::fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn rotate(max_size: u64, keep: usize) -> LogRotateConfig {
+        LogRotateConfig {
+            max_size,
+            daily: false,
+            keep,
+        }
+    }
+
+    fn read(dir: &TempDir, name: &str) -> Option<String> {
+        std::fs::read_to_string(dir.0.join(name)).ok()
+    }
+
+    #[test]
+    fn test_rotate_by_size() {
+        let dir = TempDir::new();
+        let file = LogFile::open(&dir.0.join("app.log"), rotate(10, 2)).unwrap();
+        for line in ["one\n", "two\n", "three\n", "four\n", "five\n"] {
+            file.write_all(line.as_bytes()).unwrap();
+        }
+
+        // Every file holds what fits in 10 bytes; the oldest is dropped.
+        assert_eq!(read(&dir, "app.log").unwrap(), "four\nfive\n");
+        assert_eq!(read(&dir, "app.log.1").unwrap(), "three\n");
+        assert_eq!(read(&dir, "app.log.2").unwrap(), "one\ntwo\n");
+        assert_eq!(read(&dir, "app.log.3"), None);
+
+        file.write_all(b"six\n").unwrap();
+        assert_eq!(read(&dir, "app.log.2").unwrap(), "three\n");
+        assert_eq!(read(&dir, "app.log.3"), None);
+    }
+
+    #[test]
+    fn test_rotate_keep_none() {
+        let dir = TempDir::new();
+        let file = LogFile::open(&dir.0.join("app.log"), rotate(4, 0)).unwrap();
+        file.write_all(b"one\n").unwrap();
+        file.write_all(b"two\n").unwrap();
+
+        assert_eq!(read(&dir, "app.log").unwrap(), "two\n");
+        assert_eq!(read(&dir, "app.log.1"), None);
+    }
+
+    #[test]
+    fn test_rotate_daily() {
+        let dir = TempDir::new();
+        let config = LogRotateConfig {
+            daily: true,
+            ..rotate(0, 2)
+        };
+        let file = LogFile::open(&dir.0.join("app.log"), config).unwrap();
+        file.write_all(b"one\n").unwrap();
+        file.write_all(b"two\n").unwrap();
+        assert_eq!(read(&dir, "app.log").unwrap(), "one\ntwo\n");
+
+        file.current.lock().unwrap().date = Local::now().date_naive().pred_opt().unwrap();
+        file.write_all(b"three\n").unwrap();
+        assert_eq!(read(&dir, "app.log").unwrap(), "three\n");
+        assert_eq!(read(&dir, "app.log.1").unwrap(), "one\ntwo\n");
+    }
+
+    #[test]
+    fn test_append_to_existing() {
+        let dir = TempDir::new();
+        std::fs::write(dir.0.join("app.log"), "123456\n").unwrap();
+
+        // The size of the existing file counts.
+        let file = LogFile::open(&dir.0.join("app.log"), rotate(10, 1)).unwrap();
+        file.write_all(b"abc\n").unwrap();
+        assert_eq!(read(&dir, "app.log").unwrap(), "abc\n");
+        assert_eq!(read(&dir, "app.log.1").unwrap(), "123456\n");
+    }
+
+    #[test]
+    fn test_special_destinations() {
+        let mut files = Vec::new();
+        for destination in ["", "stdout", "/dev/stdout", "stderr", "/dev/stderr", "none"] {
+            make_writer(destination, &rotate(10, 1), &mut files).unwrap();
+        }
+        assert!(files.is_empty());
+    }
+}
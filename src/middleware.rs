@@ -1,6 +1,19 @@
-use axum::{extract::Request, http::uri::Uri, middleware::Next, response::Response};
+use axum::{
+    body::HttpBody,
+    extract::{ConnectInfo, Request},
+    http::{header, uri::Uri, HeaderMap},
+    middleware::Next,
+    response::Response,
+};
+use std::net::SocketAddr;
+use std::time::Instant;
 use tracing::info;
 
+use crate::config::LoggingConfig;
+use crate::jellyfin::auth::remote_address;
+use crate::logging::AccessRecord;
+use crate::util::redact::{redact_body, redact_header, redact_uri, redact_url, truncate};
+
 pub async fn normalize_path(mut req: Request, next: Next) -> Response {
     let uri = req.uri();
     let path = uri.path();
@@ -37,47 +50,72 @@ pub async fn normalize_path(mut req: Request, next: Next) -> Response {
     next.run(req).await
 }
 
+/// The user and device of an authenticated request, added to the response
+/// extensions by `auth_middleware` for the access log.
+#[derive(Debug, Clone)]
+pub struct RequestUser {
+    pub user_name: String,
+    pub device: String,
+}
+
 pub async fn log_request(
     axum::extract::State(state): axum::extract::State<crate::server::AppState>,
     req: Request,
     next: Next,
 ) -> Response {
+    let started = Instant::now();
     let debug_logs = state.config.debug_logs;
     let method = req.method().clone();
-    let uri = req.uri().clone();
-
-    // Log POST request body for debugging
-    let (parts, body) = req.into_parts();
-    let bytes = axum::body::to_bytes(body, usize::MAX)
-        .await
-        .unwrap_or_default();
+    let uri = redact_uri(req.uri());
+    let version = format!("{:?}", req.version());
+    let body_limit = body_log_limit(&state.config.logging, req.uri().path());
+    let peer = req
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| *addr);
+    let remote_address = remote_address(req.headers(), peer);
+    let referer = header_text(req.headers(), header::REFERER).map(|r| redact_url(&r));
+    let user_agent = header_text(req.headers(), header::USER_AGENT);
 
     if debug_logs {
         info!("Request: {} {}", method, uri);
-        for (name, value) in &parts.headers {
-            info!("Req Header: {}: {:?}", name, value);
+        for (name, value) in req.headers() {
+            info!("Req Header: {}: {}", name, redact_header(name, value));
         }
     }
 
-    if method == axum::http::Method::POST && !bytes.is_empty() {
+    // Log POST request body for debugging. Only small JSON and form bodies
+    // are read into memory; uploads and streams pass through.
+    let read_limit = if debug_logs {
+        body_limit.max(DEBUG_BODY_LIMIT)
+    } else {
+        body_limit
+    };
+    let req = if method == axum::http::Method::POST
+        && is_form_or_json(req.headers())
+        && fits(req.body(), read_limit)
+    {
+        let (parts, body) = req.into_parts();
+        let bytes = axum::body::to_bytes(body, read_limit)
+            .await
+            .unwrap_or_default();
         if let Ok(body_str) = std::str::from_utf8(&bytes) {
-            info!(
-                method = %method,
-                url = %uri,
-                body = %body_str,
-                "POST request"
-            );
+            if !body_str.is_empty() {
+                let body_str = redact_body(body_str);
+                info!(
+                    method = %method,
+                    url = %uri,
+                    body = %body_for_log(&body_str, body_limit, debug_logs),
+                    "POST request"
+                );
+            }
         }
-    } else if !debug_logs {
-        info!(
-            method = %method,
-            url = %uri,
-            "Request started"
-        );
-    }
+        // Reconstruct request with body
+        Request::from_parts(parts, axum::body::Body::from(bytes))
+    } else {
+        req
+    };
 
-    // Reconstruct request with body
-    let req = Request::from_parts(parts, axum::body::Body::from(bytes));
     let response = next.run(req).await;
 
     let status = response.status().as_u16();
@@ -85,14 +123,14 @@ pub async fn log_request(
     if debug_logs {
         info!("Response: {} {} Status: {}", method, uri, status);
         for (name, value) in response.headers() {
-            info!("Res Header: {}: {:?}", name, value);
+            info!("Res Header: {}: {}", name, redact_header(name, value));
         }
     }
 
     // Check Content-Type to decide whether to buffer body
     let content_type = response
         .headers()
-        .get(axum::http::header::CONTENT_TYPE)
+        .get(header::CONTENT_TYPE)
         .and_then(|v| v.to_str().ok())
         .map(|ct| ct.to_lowercase())
         .unwrap_or_default();
@@ -104,60 +142,122 @@ pub async fn log_request(
 
     if debug_logs {
         info!(
-            "Deciding body logging for Content-Type: '{}', is_text: {}",
-            content_type, is_text
+            "Deciding body logging for Content-Type: '{}', is_text: {}, limit: {}",
+            content_type, is_text, body_limit
         );
     }
 
-    if is_text {
+    let (response, length) = if is_text && fits(response.body(), read_limit) {
         // Buffer text/json responses for debugging logging
         let (parts, body) = response.into_parts();
-        let bytes = axum::body::to_bytes(body, usize::MAX)
+        let bytes = axum::body::to_bytes(body, read_limit)
             .await
             .unwrap_or_default();
-        let length = bytes.len();
 
-        let body_str_res = std::str::from_utf8(&bytes);
+        let body_str_res = std::str::from_utf8(&bytes).map(redact_body);
 
         if debug_logs {
-            match body_str_res {
+            match &body_str_res {
                 Ok(body_str) => info!("Res Body: {}", body_str),
                 Err(e) => info!("Res Body skipped: Invalid UTF-8 sequence: {}", e),
             }
         }
 
-        // Standard logging
-        if let Ok(body_str) = body_str_res {
-            info!(
-                method = %method,
-                url = %uri,
-                status = status,
-                length = length,
-                res_body = %body_str,
-                "HTTP request (Logged)"
-            );
-        } else {
+        if let Some(body_str) = body_str_res.as_ref().ok().filter(|_| body_limit > 0) {
             info!(
                 method = %method,
                 url = %uri,
                 status = status,
-                length = length,
-                "HTTP request"
+                res_body = %body_for_log(body_str, body_limit, false),
+                "Response body"
             );
         }
 
-        Response::from_parts(parts, axum::body::Body::from(bytes))
+        let length = bytes.len() as u64;
+        (
+            Response::from_parts(parts, axum::body::Body::from(bytes)),
+            Some(length),
+        )
     } else {
         // Do NOT buffer video/binary streams - pass through directly
-        info!(
-            method = %method,
-            url = %uri,
-            status = status,
-            type = %content_type,
-            "HTTP request (Streamed)"
-        );
-        response
+        let length = response
+            .headers()
+            .get(header::CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse().ok())
+            .or_else(|| response.body().size_hint().exact());
+        (response, length)
+    };
+
+    let user = response.extensions().get::<RequestUser>();
+    AccessRecord {
+        remote_address: &remote_address,
+        user: user.map(|u| u.user_name.as_str()),
+        device: user.map(|u| u.device.as_str()).filter(|d| !d.is_empty()),
+        method: method.as_str(),
+        url: &uri,
+        version: &version,
+        status,
+        bytes: length,
+        duration: started.elapsed(),
+        referer: referer.as_deref(),
+        user_agent: user_agent.as_deref(),
+    }
+    .log(state.config.logging.access_log_format);
+
+    response
+}
+
+/// Bodies read for `--debug-logs` are cut off at this size.
+const DEBUG_BODY_LIMIT: usize = 1024 * 1024;
+
+/// Whether a body is known to be at most `limit` bytes, so that it can be
+/// read into memory. Streams of unknown length are not.
+fn fits(body: &axum::body::Body, limit: usize) -> bool {
+    limit > 0
+        && body
+            .size_hint()
+            .upper()
+            .is_some_and(|size| size <= limit as u64)
+}
+
+fn is_form_or_json(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|ct| ct.to_lowercase())
+        .is_some_and(|ct| ct.contains("json") || ct.contains("application/x-www-form-urlencoded"))
+}
+
+fn header_text(headers: &HeaderMap, name: header::HeaderName) -> Option<String> {
+    headers
+        .get(name)
+        .map(|v| String::from_utf8_lossy(v.as_bytes()).into_owned())
+}
+
+/// Maximum body size to log for `path`: that of the body rule with the
+/// longest matching prefix, or 0 if none matches.
+fn body_log_limit(config: &LoggingConfig, path: &str) -> usize {
+    let path = path.strip_prefix("/emby").unwrap_or(path).to_lowercase();
+    config
+        .bodies
+        .iter()
+        .filter(|rule| path.starts_with(&rule.prefix.to_lowercase()))
+        .max_by_key(|rule| rule.prefix.len())
+        .map_or(0, |rule| rule.max_size)
+}
+
+/// A redacted body, cut to `limit` bytes unless debug logging is enabled.
+fn body_for_log(body: &str, limit: usize, debug_logs: bool) -> String {
+    if debug_logs || body.len() <= limit {
+        return body.to_string();
     }
+    let logged = truncate(body, limit);
+    format!(
+        "{}... ({} bytes truncated)",
+        logged,
+        body.len() - logged.len()
+    )
 }
 
 pub async fn add_cors_headers(req: Request, next: Next) -> Response {
@@ -257,7 +357,7 @@ pub async fn etag_validation(req: Request, next: Next) -> Response {
     response
 }
 
-fn etags_match(client_etag: &str, server_etag: &str) -> bool {
+pub(crate) fn etags_match(client_etag: &str, server_etag: &str) -> bool {
     // Handle multiple ETags in If-None-Match (comma-separated)
     for etag in client_etag.split(',') {
         let etag = etag.trim();
@@ -1,6 +1,9 @@
 use super::types::*;
 use crate::collection::sort_name::make_sort_name;
+use crate::collection::Collection;
+use crate::jellyfin::access::{public_library_access, LibraryAccess};
 use crate::server::AppState;
+use crate::util::ResizeOptions;
 use axum::{
     extract::{Path, Query, State},
     http::StatusCode,
@@ -9,11 +12,30 @@ use axum::{
 };
 use std::collections::HashMap;
 use std::os::unix::fs::MetadataExt;
+use std::path::Component;
 use tower::util::ServiceExt;
 use tower_http::services::ServeFile;
 
-pub async fn list_collections(State(state): State<AppState>) -> Json<Vec<CollectionInfo>> {
-    let collections = state.collections.list_collections().await;
+/// The caller's library access. The notflix API is used without logging in,
+/// in which case only what is public can be seen.
+async fn library_access(
+    state: &AppState,
+    access: Option<axum::Extension<LibraryAccess>>,
+) -> LibraryAccess {
+    match access {
+        Some(axum::Extension(access)) => access,
+        None => public_library_access(state).await,
+    }
+}
+
+pub async fn list_collections(
+    State(state): State<AppState>,
+    access: Option<axum::Extension<LibraryAccess>>,
+) -> Json<Vec<CollectionInfo>> {
+    let collections = library_access(&state, access)
+        .await
+        .list_collections(&state.collections)
+        .await;
 
     let infos: Vec<CollectionInfo> = collections
         .iter()
@@ -31,10 +53,11 @@ pub async fn list_collections(State(state): State<AppState>) -> Json<Vec<Collect
 pub async fn get_collection(
     State(state): State<AppState>,
     Path(collection_id): Path<String>,
+    access: Option<axum::Extension<LibraryAccess>>,
 ) -> Result<Json<CollectionInfo>, StatusCode> {
-    let collection = state
-        .collections
-        .get_collection(&collection_id)
+    let collection = library_access(&state, access)
+        .await
+        .get_collection(&state.collections, &collection_id)
         .await
         .ok_or(StatusCode::NOT_FOUND)?;
 
@@ -51,10 +74,11 @@ pub async fn get_collection(
 pub async fn get_collection_genres(
     State(state): State<AppState>,
     Path(collection_id): Path<String>,
+    access: Option<axum::Extension<LibraryAccess>>,
 ) -> Result<Json<Vec<GenreCount>>, StatusCode> {
-    let collection = state
-        .collections
-        .get_collection(&collection_id)
+    let collection = library_access(&state, access)
+        .await
+        .get_collection(&state.collections, &collection_id)
         .await
         .ok_or(StatusCode::NOT_FOUND)?;
 
@@ -69,10 +93,34 @@ pub async fn get_collection_genres(
     Ok(Json(genres))
 }
 
+/// The item type and rating of the movie or show whose directory holds
+/// `path`. The path must be normalized: `Path::starts_with` compares
+/// components and does not resolve `..`. Of nested directories the
+/// innermost one wins.
+fn rating_owner<'a>(
+    collection: &'a Collection,
+    path: &std::path::Path,
+) -> Option<(&'static str, Option<&'a str>)> {
+    let movies = collection
+        .movies
+        .values()
+        .map(|movie| (&movie.path, "Movie", movie.mpaa.as_deref()));
+    let shows = collection
+        .shows
+        .values()
+        .map(|show| (&show.path, "Series", show.mpaa.as_deref()));
+    movies
+        .chain(shows)
+        .filter(|(dir, _, _)| path.starts_with(dir))
+        .max_by_key(|(dir, _, _)| dir.components().count())
+        .map(|(_, item_type, rating)| (item_type, rating))
+}
+
 pub async fn serve_data_file(
     State(state): State<AppState>,
     Path(path_parts): Path<String>,
     Query(params): Query<HashMap<String, String>>,
+    access: Option<axum::Extension<LibraryAccess>>,
     req: axum::http::Request<axum::body::Body>,
 ) -> Result<Response, StatusCode> {
     let parts: Vec<&str> = path_parts.split('/').collect();
@@ -82,21 +130,18 @@ pub async fn serve_data_file(
 
     let source = parts[0];
     let file_path = parts[1..].join("/");
-
-    // Check if this is an HLS proxy request (contains .mp4/)
-    if file_path.contains(".mp4/") {
-        // Try HLS proxy
-        return crate::notflix::hls_proxy(
-            axum::extract::State(state),
-            axum::extract::Path((source.to_string(), file_path.clone())),
-            req,
-        )
-        .await;
+    // The path is percent-decoded: `%2e%2e` must not lead out of the
+    // collection.
+    if !std::path::Path::new(&file_path)
+        .components()
+        .all(|c| matches!(c, Component::Normal(_)))
+    {
+        return Err(StatusCode::FORBIDDEN);
     }
 
-    let collection = state
-        .collections
-        .get_collection(source)
+    let access = library_access(&state, access).await;
+    let collection = access
+        .get_collection(&state.collections, source)
         .await
         .ok_or(StatusCode::NOT_FOUND)?;
 
@@ -106,6 +151,18 @@ pub async fn serve_data_file(
         return Err(StatusCode::FORBIDDEN);
     }
 
+    // Files of a movie or show are subject to its rating.
+    if let Some((item_type, rating)) = rating_owner(&collection, &full_path) {
+        if !access.allows_rating(item_type, rating) {
+            return Err(StatusCode::NOT_FOUND);
+        }
+    }
+
+    // Check if this is an HLS proxy request (contains .mp4/)
+    if file_path.contains(".mp4/") {
+        return crate::notflix::hls_proxy(&collection, &file_path, req).await;
+    }
+
     if !full_path.exists() {
         return Err(StatusCode::NOT_FOUND);
     }
@@ -119,14 +176,17 @@ pub async fn serve_data_file(
     let serve_path = if is_image
         && (params.contains_key("w") || params.contains_key("h") || params.contains_key("q"))
     {
-        let width = params.get("w").and_then(|w| w.parse().ok());
-        let height = params.get("h").and_then(|h| h.parse().ok());
-        let quality = params.get("q").and_then(|q| q.parse().ok());
-
-        state
-            .image_resizer
-            .resize_image(&full_path, width, height, quality)
-            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        let options = ResizeOptions {
+            width: params.get("w").and_then(|w| w.parse().ok()),
+            height: params.get("h").and_then(|h| h.parse().ok()),
+            quality: params.get("q").and_then(|q| q.parse().ok()),
+            ..Default::default()
+        };
+
+        match state.image_resizer.resize(&full_path, &options).await {
+            Ok(path) => path,
+            Err(e) => return Ok(e.into_response()),
+        }
     } else {
         full_path
     };
@@ -168,12 +228,16 @@ pub async fn serve_data_file(
 pub async fn get_item(
     State(state): State<AppState>,
     Path((collection_id, item_id)): Path<(String, String)>,
+    access: Option<axum::Extension<LibraryAccess>>,
 ) -> Result<Response, StatusCode> {
-    let collection = state
-        .collections
-        .get_collection(&collection_id)
+    let access = library_access(&state, access).await;
+    let collection = access
+        .get_collection(&state.collections, &collection_id)
         .await
         .ok_or(StatusCode::NOT_FOUND)?;
+    access
+        .get_item(&state.collections, &item_id)
+        .ok_or(StatusCode::NOT_FOUND)?;
 
     // Check if it's a movie
     if let Some(movie) = collection.movies.get(&item_id) {
@@ -260,8 +324,8 @@ pub async fn get_item(
             },
             fanart: movie
                 .images
-                .backdrop
-                .as_ref()
+                .backdrops
+                .first()
                 .map(|_| "fanart.jpg".to_string()),
             poster: poster_filename,
             rating: movie.community_rating,
@@ -432,8 +496,8 @@ pub async fn get_item(
                 .map(|_| "banner.jpg".to_string()),
             fanart: show
                 .images
-                .backdrop
-                .as_ref()
+                .backdrops
+                .first()
                 .map(|_| "fanart.jpg".to_string()),
             poster: show
                 .images
@@ -456,16 +520,20 @@ pub async fn get_item(
 pub async fn get_collection_items(
     State(state): State<AppState>,
     Path(collection_id): Path<String>,
+    access: Option<axum::Extension<LibraryAccess>>,
 ) -> Result<Json<Vec<ItemSummary>>, StatusCode> {
-    let collection = state
-        .collections
-        .get_collection(&collection_id)
+    let access = library_access(&state, access).await;
+    let collection = access
+        .get_collection(&state.collections, &collection_id)
         .await
         .ok_or(StatusCode::NOT_FOUND)?;
 
     let mut items = Vec::new();
 
     for movie in collection.movies.values() {
+        if !access.allows_rating("Movie", movie.mpaa.as_deref()) {
+            continue;
+        }
         items.push(ItemSummary {
             id: movie.id.clone(),
             name: movie.name.clone(),
@@ -481,8 +549,8 @@ pub async fn get_collection_items(
             banner: None,
             fanart: movie
                 .images
-                .backdrop
-                .as_ref()
+                .backdrops
+                .first()
                 .map(|_| "fanart.jpg".to_string()),
             poster: movie
                 .images
@@ -497,6 +565,9 @@ pub async fn get_collection_items(
     }
 
     for show in collection.shows.values() {
+        if !access.allows_rating("Series", show.mpaa.as_deref()) {
+            continue;
+        }
         let mut first_video = i64::MAX;
         let mut last_video = i64::MIN;
 
@@ -538,8 +609,8 @@ pub async fn get_collection_items(
                 .map(|_| "banner.jpg".to_string()),
             fanart: show
                 .images
-                .backdrop
-                .as_ref()
+                .backdrops
+                .first()
                 .map(|_| "fanart.jpg".to_string()),
             poster: show
                 .images
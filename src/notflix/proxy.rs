@@ -1,13 +1,12 @@
 use axum::{
     body::Body,
-    extract::{Path, State},
     http::{header, HeaderMap, Request, StatusCode},
     response::Response,
 };
 use reqwest;
 use std::time::Duration;
 
-use crate::server::AppState;
+use crate::collection::Collection;
 
 // Hop-by-hop headers that should be removed when proxying
 const HOP_HEADERS: &[&str] = &[
@@ -21,9 +20,11 @@ const HOP_HEADERS: &[&str] = &[
     "Upgrade",
 ];
 
+/// Proxy an HLS request for a file of `collection` to its HLS server. The
+/// caller checks that the user may access the file.
 pub async fn hls_proxy(
-    State(state): State<AppState>,
-    Path((source, path)): Path<(String, String)>,
+    collection: &Collection,
+    path: &str,
     req: Request<Body>,
 ) -> Result<Response, StatusCode> {
     // Check if this is an HLS request (contains .mp4/)
@@ -31,13 +32,6 @@ pub async fn hls_proxy(
         return Err(StatusCode::NOT_FOUND);
     }
 
-    // Get the collection
-    let collections = state.collections.list_collections().await;
-    let collection = collections
-        .iter()
-        .find(|c| c.id == source)
-        .ok_or(StatusCode::NOT_FOUND)?;
-
     // Get HLS server URL
     let hls_server = collection
         .hls_server
@@ -45,7 +39,7 @@ pub async fn hls_proxy(
         .ok_or(StatusCode::NOT_FOUND)?;
 
     // Build the target URL
-    let target_url = build_url(hls_server, &path).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let target_url = build_url(hls_server, path).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
     // Create HTTP client with timeout
     let client = reqwest::Client::builder()
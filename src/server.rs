@@ -10,6 +10,11 @@ use tower_http::{
 use crate::collection::CollectionRepo;
 use crate::config::Config;
 use crate::db::SqliteRepository;
+use crate::jellyfin::access::PublicAccessCache;
+use crate::jellyfin::loginattemptmanager::LoginAttemptManager;
+use crate::jellyfin::quickconnectmanager::QuickConnectManager;
+use crate::jellyfin::sessionmanager::SessionManager;
+use crate::jellyfin::syncplaymanager::SyncPlayManager;
 use crate::util::ImageResizer;
 
 #[derive(Clone)]
@@ -18,6 +23,11 @@ pub struct AppState {
     pub db: Arc<SqliteRepository>,
     pub collections: Arc<CollectionRepo>,
     pub image_resizer: Arc<ImageResizer>,
+    pub sessions: Arc<SessionManager>,
+    pub quick_connect: Arc<QuickConnectManager>,
+    pub login_attempts: Arc<LoginAttemptManager>,
+    pub syncplay: Arc<SyncPlayManager>,
+    pub public_access: Arc<PublicAccessCache>,
 }
 
 impl AppState {
@@ -27,11 +37,17 @@ impl AppState {
         collections: Arc<CollectionRepo>,
         image_resizer: Arc<ImageResizer>,
     ) -> Self {
+        let sessions = Arc::new(SessionManager::new());
         Self {
             config: Arc::new(config),
             db,
             collections,
             image_resizer,
+            quick_connect: Arc::new(QuickConnectManager::new()),
+            login_attempts: Arc::new(LoginAttemptManager::new()),
+            syncplay: Arc::new(SyncPlayManager::new(sessions.clone())),
+            public_access: Arc::new(PublicAccessCache::new()),
+            sessions,
         }
     }
 }
@@ -52,7 +68,12 @@ pub fn build_router(state: AppState) -> Router {
             "/api/collection/:coll_id/item/:item_id",
             get(crate::notflix::get_item),
         )
-        .route("/data/*path", get(crate::notflix::serve_data_file));
+        .route("/data/*path", get(crate::notflix::serve_data_file))
+        // Recognize logged-in users, so that they see their own libraries.
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            crate::jellyfin::auth::auth_middleware,
+        ));
 
     let jellyfin_routes = crate::jellyfin::jellyfin::build_jellyfin_router(state.clone());
 
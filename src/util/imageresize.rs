@@ -1,38 +1,352 @@
-use image::{imageops::FilterType, DynamicImage, GenericImageView, ImageFormat};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use image::{imageops::FilterType, DynamicImage, GenericImageView, ImageFormat, Rgba, RgbaImage};
 use sha2::{Digest, Sha256};
+use std::collections::HashMap;
 use std::fs;
 use std::io::Cursor;
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, SystemTime};
-use tracing::{debug, error};
+use tokio::sync::{watch, Semaphore};
+use tracing::{debug, error, info};
+
+use crate::util::Placeholder;
+
+/// What to make of an image, from the request parameters. Sizes are in
+/// pixels.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ResizeOptions {
+    /// Size to fit within; with only one of them, the other follows from
+    /// the aspect ratio.
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// Scale down, keeping the aspect ratio, to fit within these.
+    pub max_width: Option<u32>,
+    pub max_height: Option<u32>,
+    /// Scale to cover this box and crop what sticks out. With only one of
+    /// them it is a maximum.
+    pub fill_width: Option<u32>,
+    pub fill_height: Option<u32>,
+    /// Encoder quality, 1-100, for JPEG, WebP and AVIF.
+    pub quality: Option<u32>,
+    /// None keeps the source format.
+    pub format: Option<OutputFormat>,
+    /// Blur sigma.
+    pub blur: Option<u32>,
+    /// RGBA color behind transparent areas.
+    pub background_color: Option<[u8; 4]>,
+    /// Crop transparent borders, as Jellyfin does for logos.
+    pub crop_whitespace: bool,
+}
+
+impl ResizeOptions {
+    /// Whether the original image is served as is.
+    pub fn is_empty(&self) -> bool {
+        *self == ResizeOptions::default()
+    }
+
+    /// The extension of the image `resize` produces for a source image
+    /// with the given extension. Known without opening the file.
+    pub fn extension<'a>(&self, source_extension: &'a str) -> &'a str {
+        match self.format {
+            Some(format) => format.extension(),
+            None => source_extension,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Jpeg,
+    Png,
+    Webp,
+    Avif,
+}
+
+impl OutputFormat {
+    /// A Jellyfin `format` parameter: `Jpg`, `Png`, `Webp` or `Avif`.
+    pub fn parse(format: &str) -> Option<Self> {
+        match format.to_ascii_lowercase().as_str() {
+            "jpg" | "jpeg" => Some(OutputFormat::Jpeg),
+            "png" => Some(OutputFormat::Png),
+            "webp" => Some(OutputFormat::Webp),
+            "avif" => Some(OutputFormat::Avif),
+            _ => None,
+        }
+    }
+
+    /// The format to send a client with this `Accept` header: WebP if it
+    /// takes it, otherwise AVIF, which is smaller but far slower to encode.
+    pub fn from_accept(accept: &str) -> Option<Self> {
+        let accepts = |mime: &str| {
+            accept.split(',').any(|part| {
+                let mut params = part.split(';').map(str::trim);
+                params.next().is_some_and(|m| m.eq_ignore_ascii_case(mime))
+                    && !params.any(|p| p.replace(' ', "") == "q=0")
+            })
+        };
+        if accepts("image/webp") {
+            Some(OutputFormat::Webp)
+        } else if accepts("image/avif") {
+            Some(OutputFormat::Avif)
+        } else {
+            None
+        }
+    }
+
+    fn image_format(self) -> ImageFormat {
+        match self {
+            OutputFormat::Jpeg => ImageFormat::Jpeg,
+            OutputFormat::Png => ImageFormat::Png,
+            OutputFormat::Webp => ImageFormat::WebP,
+            OutputFormat::Avif => ImageFormat::Avif,
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Jpeg => "jpg",
+            OutputFormat::Png => "png",
+            OutputFormat::Webp => "webp",
+            OutputFormat::Avif => "avif",
+        }
+    }
+}
+
+/// A CSS-style hex color, `#RGB`, `#RRGGBB`, or with alpha first as in
+/// Jellyfin, `#ARGB` or `#AARRGGBB`. Returns RGBA.
+pub fn parse_color(color: &str) -> Option<[u8; 4]> {
+    let hex = color.trim().trim_start_matches('#');
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let digits: Vec<u8> = match hex.len() {
+        // Expand the short forms: `f80` is `ff8800`.
+        3 | 4 => hex
+            .chars()
+            .map(|c| u8::from_str_radix(&c.to_string().repeat(2), 16).ok())
+            .collect::<Option<_>>()?,
+        6 | 8 => (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+            .collect::<Option<_>>()?,
+        _ => return None,
+    };
+    match digits[..] {
+        [r, g, b] => Some([r, g, b, 255]),
+        [a, r, g, b] => Some([r, g, b, a]),
+        _ => None,
+    }
+}
+
+/// Largest width or height that may be asked for.
+const MAX_DIMENSION: u32 = 8192;
+
+/// A requested size, limited to 1..=`MAX_DIMENSION`.
+fn cap(size: Option<u32>) -> Option<u32> {
+    size.map(|size| size.clamp(1, MAX_DIMENSION))
+}
+
+/// Scale `width` x `height` down, keeping the aspect ratio, to fit within
+/// the maximum sizes.
+fn shrink_to_fit(
+    width: u32,
+    height: u32,
+    max_width: Option<u32>,
+    max_height: Option<u32>,
+) -> (u32, u32) {
+    let factor = f64::min(
+        max_width.map_or(1.0, |max| max as f64 / width.max(1) as f64),
+        max_height.map_or(1.0, |max| max as f64 / height.max(1) as f64),
+    );
+    if factor >= 1.0 {
+        return (width, height);
+    }
+    (
+        ((width as f64 * factor).round() as u32).max(1),
+        ((height as f64 * factor).round() as u32).max(1),
+    )
+}
+
+/// Crop fully transparent rows and columns from the edges.
+fn crop_transparent(img: DynamicImage) -> DynamicImage {
+    if !img.color().has_alpha() {
+        return img;
+    }
+    let rgba = img.to_rgba8();
+    let opaque = |x: u32, y: u32| rgba.get_pixel(x, y)[3] != 0;
+    let (width, height) = rgba.dimensions();
+    let rows: Vec<u32> = (0..height)
+        .filter(|&y| (0..width).any(|x| opaque(x, y)))
+        .collect();
+    let cols: Vec<u32> = (0..width)
+        .filter(|&x| (0..height).any(|y| opaque(x, y)))
+        .collect();
+    match (rows.first(), rows.last(), cols.first(), cols.last()) {
+        (Some(&top), Some(&bottom), Some(&left), Some(&right)) => {
+            img.crop_imm(left, top, right - left + 1, bottom - top + 1)
+        }
+        // Nothing but transparency.
+        _ => img,
+    }
+}
+
+/// Outcome of a resize, shared with all requests waiting for it.
+type ResizeResult = Option<Result<PathBuf, String>>;
 
 pub struct ImageResizer {
     cache_dir: PathBuf,
+    /// Limits the number of images decoded and resized at the same time.
+    workers: Arc<Semaphore>,
+    /// Resizes and placeholder renders waiting for or holding a worker, by
+    /// cache key.
+    pending: Mutex<HashMap<String, watch::Receiver<ResizeResult>>>,
+    max_pending: usize,
 }
 
 impl ImageResizer {
-    pub fn new(cache_dir: PathBuf) -> Result<Self, ImageResizerError> {
+    pub fn new(
+        cache_dir: PathBuf,
+        workers: usize,
+        max_pending: usize,
+    ) -> Result<Self, ImageResizerError> {
         fs::create_dir_all(&cache_dir)?;
-        Ok(Self { cache_dir })
+        Ok(Self {
+            cache_dir,
+            workers: Arc::new(Semaphore::new(workers)),
+            pending: Mutex::new(HashMap::new()),
+            max_pending,
+        })
+    }
+
+    /// `resize_image` on the blocking thread pool, for async handlers.
+    /// Concurrent requests for the same image share one resize; when too
+    /// many images are pending, fails with `ImageResizerError::Busy`.
+    pub async fn resize(
+        self: &Arc<Self>,
+        source_path: &Path,
+        options: &ResizeOptions,
+    ) -> Result<PathBuf, ImageResizerError> {
+        if options.is_empty() {
+            return Ok(source_path.to_path_buf());
+        }
+
+        let cache_key = self.generate_cache_key(source_path, options);
+        let cache_path = self.cache_dir.join(&cache_key);
+        if cache_path.exists() {
+            mark_used(&cache_path);
+            return Ok(cache_path);
+        }
+
+        let source_path = source_path.to_path_buf();
+        let options = options.clone();
+        self.run_pending(cache_key, move |resizer| {
+            resizer.resize_image(&source_path, &options)
+        })
+        .await
+    }
+
+    /// Render placeholder artwork into the cache directory, once, so it
+    /// can be passed to `resize` like any source image.
+    pub async fn placeholder(
+        self: &Arc<Self>,
+        placeholder: &Placeholder,
+    ) -> Result<PathBuf, ImageResizerError> {
+        let key = format!("placeholder-{}.png", placeholder.tag());
+        let path = self.cache_dir.join(&key);
+        if path.exists() {
+            mark_used(&path);
+            return Ok(path);
+        }
+
+        let placeholder = placeholder.clone();
+        self.run_pending(key, move |_| {
+            debug!("Rendering placeholder image: {:?}", path);
+            // Write and rename, so that a concurrent request never serves
+            // a partial file.
+            let partial = path.with_extension(format!("{}.tmp", uuid::Uuid::new_v4()));
+            placeholder
+                .render()
+                .save_with_format(&partial, ImageFormat::Png)?;
+            fs::rename(&partial, &path)?;
+            Ok(path)
+        })
+        .await
+    }
+
+    /// Run `job` on the blocking thread pool when a worker is free.
+    /// Concurrent calls with the same `key` share one run; when too many
+    /// jobs are pending, fails with `ImageResizerError::Busy`.
+    async fn run_pending(
+        self: &Arc<Self>,
+        key: String,
+        job: impl FnOnce(&ImageResizer) -> Result<PathBuf, ImageResizerError> + Send + 'static,
+    ) -> Result<PathBuf, ImageResizerError> {
+        let (mut result, sender) = {
+            let mut pending = self.pending.lock().unwrap();
+            match pending.get(&key) {
+                Some(result) => (result.clone(), None),
+                None if pending.len() >= self.max_pending => {
+                    return Err(ImageResizerError::Busy);
+                }
+                None => {
+                    let (sender, result) = watch::channel(None);
+                    pending.insert(key.clone(), result.clone());
+                    (result, Some(sender))
+                }
+            }
+        };
+
+        if let Some(sender) = sender {
+            // A separate task, so that the job completes for the other
+            // waiters even if this request is cancelled.
+            let resizer = self.clone();
+            tokio::spawn(async move {
+                let outcome = match resizer.workers.clone().acquire_owned().await {
+                    Ok(permit) => {
+                        let worker = resizer.clone();
+                        tokio::task::spawn_blocking(move || {
+                            let _permit = permit;
+                            job(&worker)
+                        })
+                        .await
+                        .map_err(|e| e.to_string())
+                        .and_then(|r| r.map_err(|e| e.to_string()))
+                    }
+                    Err(e) => Err(e.to_string()),
+                };
+                resizer.pending.lock().unwrap().remove(&key);
+                let _ = sender.send(Some(outcome));
+            });
+        }
+
+        let outcome = result
+            .wait_for(Option::is_some)
+            .await
+            .map_err(|_| ImageResizerError::Failed("resize task ended".to_string()))?
+            .clone();
+        outcome
+            .unwrap_or_else(|| Err("no result".to_string()))
+            .map_err(ImageResizerError::Failed)
     }
 
     pub fn resize_image(
         &self,
         source_path: &Path,
-        width: Option<u32>,
-        height: Option<u32>,
-        quality: Option<u32>,
+        options: &ResizeOptions,
     ) -> Result<PathBuf, ImageResizerError> {
         // If no resize parameters, return original path
-        if width.is_none() && height.is_none() && quality.is_none() {
+        if options.is_empty() {
             return Ok(source_path.to_path_buf());
         }
 
-        let cache_key = self.generate_cache_key(source_path, width, height, quality);
+        let cache_key = self.generate_cache_key(source_path, options);
         let cache_path = self.cache_dir.join(&cache_key);
 
         if cache_path.exists() {
             debug!("Serving cached image: {}", cache_key);
+            mark_used(&cache_path);
             return Ok(cache_path);
         }
 
@@ -48,13 +362,26 @@ impl ImageResizer {
         };
 
         // Detect format from file content
-        let format = match image::guess_format(&file_bytes) {
+        let source_format = match image::guess_format(&file_bytes) {
             Ok(fmt) => fmt,
             Err(e) => {
                 error!("Failed to detect format for {:?}: {}", source_path, e);
                 return Ok(source_path.to_path_buf());
             }
         };
+        let format = options
+            .format
+            .map(OutputFormat::image_format)
+            .unwrap_or(source_format);
+
+        // Only asked for the format it already has.
+        let unchanged = ResizeOptions {
+            format: None,
+            ..options.clone()
+        };
+        if unchanged.is_empty() && format == source_format {
+            return Ok(source_path.to_path_buf());
+        }
 
         // Load the image
         let img = match image::load_from_memory(&file_bytes) {
@@ -65,18 +392,9 @@ impl ImageResizer {
             }
         };
 
-        let (orig_width, orig_height) = img.dimensions();
-
-        let (target_width, target_height) =
-            self.calculate_dimensions(orig_width, orig_height, width, height);
+        let img = self.transform(img, options);
 
-        let resized = if target_width == orig_width && target_height == orig_height {
-            img
-        } else {
-            img.resize(target_width, target_height, FilterType::Lanczos3)
-        };
-
-        let encoded = match self.encode_image(resized, format, quality) {
+        let encoded = match self.encode_image(img, format, options.quality) {
             Ok(data) => data,
             Err(e) => {
                 error!("Failed to encode image {:?}: {}", source_path, e);
@@ -84,8 +402,14 @@ impl ImageResizer {
             }
         };
 
-        if let Err(e) = fs::write(&cache_path, &encoded) {
+        // Write and rename, so that a concurrent request never serves a
+        // partial file.
+        let partial = cache_path.with_extension(format!("{}.tmp", uuid::Uuid::new_v4()));
+        if let Err(e) =
+            fs::write(&partial, &encoded).and_then(|()| fs::rename(&partial, &cache_path))
+        {
             error!("Failed to write cache file {}: {}", cache_key, e);
+            let _ = fs::remove_file(&partial);
             return Ok(source_path.to_path_buf());
         }
 
@@ -102,6 +426,71 @@ impl ImageResizer {
         Ok(cache_path)
     }
 
+    /// Crop, scale, blur and fill in the background, in that order.
+    fn transform(&self, mut img: DynamicImage, options: &ResizeOptions) -> DynamicImage {
+        if options.crop_whitespace {
+            img = crop_transparent(img);
+        }
+
+        let (orig_width, orig_height) = img.dimensions();
+
+        let (fill_width, fill_height) = (cap(options.fill_width), cap(options.fill_height));
+
+        // A single fill dimension is just a maximum.
+        let (max_width, max_height) = match (fill_width, fill_height) {
+            (Some(_), Some(_)) => (cap(options.max_width), cap(options.max_height)),
+            (fill_width, fill_height) => (
+                cap(options.max_width).or(fill_width),
+                cap(options.max_height).or(fill_height),
+            ),
+        };
+
+        if let (Some(fill_width), Some(fill_height)) = (fill_width, fill_height) {
+            let (mut width, mut height) =
+                shrink_to_fit(fill_width, fill_height, max_width, max_height);
+            // Never scale up: use the largest box of this shape the image covers.
+            let cover = f64::max(
+                width as f64 / orig_width as f64,
+                height as f64 / orig_height as f64,
+            );
+            if cover > 1.0 {
+                width = ((width as f64 / cover).round() as u32).max(1);
+                height = ((height as f64 / cover).round() as u32).max(1);
+            }
+            if (width, height) != (orig_width, orig_height) {
+                img = img.resize_to_fill(width, height, FilterType::Lanczos3);
+            }
+        } else {
+            let (width, height) = self.calculate_dimensions(
+                orig_width,
+                orig_height,
+                cap(options.width),
+                cap(options.height),
+            );
+            let (width, height) = shrink_to_fit(width, height, max_width, max_height);
+            // Never scale up.
+            let (width, height) = shrink_to_fit(width, height, Some(orig_width), Some(orig_height));
+            if (width, height) != (orig_width, orig_height) {
+                img = img.resize(width, height, FilterType::Lanczos3);
+            }
+        }
+
+        if let Some(sigma) = options.blur.filter(|sigma| *sigma > 0) {
+            img = img.fast_blur(sigma as f32);
+        }
+
+        if let Some(color) = options.background_color {
+            if img.color().has_alpha() {
+                let (width, height) = img.dimensions();
+                let mut canvas = RgbaImage::from_pixel(width, height, Rgba(color));
+                image::imageops::overlay(&mut canvas, &img.to_rgba8(), 0, 0);
+                img = DynamicImage::ImageRgba8(canvas);
+            }
+        }
+
+        img
+    }
+
     fn calculate_dimensions(
         &self,
         orig_width: u32,
@@ -138,6 +527,31 @@ impl ImageResizer {
                 let quality = quality.unwrap_or(90).clamp(1, 100);
                 let encoder =
                     image::codecs::jpeg::JpegEncoder::new_with_quality(&mut buffer, quality as u8);
+                // JPEG has no alpha channel.
+                DynamicImage::ImageRgb8(img.to_rgb8()).write_with_encoder(encoder)?;
+            }
+            ImageFormat::WebP => {
+                // The image crate only encodes lossless WebP, which is
+                // larger than the JPEG it would replace.
+                let quality = quality.unwrap_or(80).clamp(1, 100) as f32;
+                let (width, height) = img.dimensions();
+                let encoded = if img.color().has_alpha() {
+                    let rgba = img.to_rgba8();
+                    webp::Encoder::from_rgba(&rgba, width, height).encode(quality)
+                } else {
+                    let rgb = img.to_rgb8();
+                    webp::Encoder::from_rgb(&rgb, width, height).encode(quality)
+                };
+                return Ok(encoded.to_vec());
+            }
+            ImageFormat::Avif => {
+                let quality = quality.unwrap_or(70).clamp(1, 100);
+                // Speed 8 of 10: AVIF is slow to encode even so.
+                let encoder = image::codecs::avif::AvifEncoder::new_with_speed_quality(
+                    &mut buffer,
+                    8,
+                    quality as u8,
+                );
                 img.write_with_encoder(encoder)?;
             }
             _ => {
@@ -148,18 +562,12 @@ impl ImageResizer {
         Ok(buffer.into_inner())
     }
 
-    fn generate_cache_key(
-        &self,
-        source_path: &Path,
-        width: Option<u32>,
-        height: Option<u32>,
-        quality: Option<u32>,
-    ) -> String {
+    /// Cache file name: `<source>-<variant>.<ext>`, where `<source>` only
+    /// depends on the source path so `invalidate` can find all variants.
+    fn generate_cache_key(&self, source_path: &Path, options: &ResizeOptions) -> String {
         let mut hasher = Sha256::new();
-        hasher.update(source_path.to_string_lossy().as_bytes());
-        hasher.update(width.unwrap_or(0).to_le_bytes());
-        hasher.update(height.unwrap_or(0).to_le_bytes());
-        hasher.update(quality.unwrap_or(0).to_le_bytes());
+        // Any change in the options gives a different key.
+        hasher.update(format!("{:?}", options).as_bytes());
 
         if let Ok(metadata) = fs::metadata(source_path) {
             if let Ok(modified) = metadata.modified() {
@@ -171,13 +579,36 @@ impl ImageResizer {
 
         let result = hasher.finalize();
         let hash = hex::encode(result);
+        let source_hash = source_cache_prefix(source_path);
 
-        let extension = source_path
-            .extension()
-            .and_then(|e| e.to_str())
-            .unwrap_or("jpg");
+        let extension = options.extension(
+            source_path
+                .extension()
+                .and_then(|e| e.to_str())
+                .unwrap_or("jpg"),
+        );
 
-        format!("{}.{}", hash, extension)
+        format!("{}{}.{}", source_hash, hash, extension)
+    }
+
+    /// Remove the cached variants of a source image, for when it was
+    /// replaced or deleted.
+    pub fn invalidate(&self, source_path: &Path) -> Result<usize, ImageResizerError> {
+        if !self.cache_dir.exists() {
+            return Ok(0);
+        }
+
+        let prefix = source_cache_prefix(source_path);
+        let mut removed = 0;
+        for entry in fs::read_dir(&self.cache_dir)? {
+            let entry = entry?;
+            if entry.file_name().to_string_lossy().starts_with(&prefix) {
+                fs::remove_file(entry.path())?;
+                removed += 1;
+            }
+        }
+
+        Ok(removed)
     }
 
     pub fn clear_cache(&self) -> Result<(), ImageResizerError> {
@@ -251,6 +682,80 @@ impl ImageResizer {
         Ok(removed)
     }
 
+    /// Remove the least recently used files until the cache is no larger
+    /// than `max_size` bytes.
+    pub fn evict_lru(&self, max_size: u64) -> Result<usize, ImageResizerError> {
+        if !self.cache_dir.exists() {
+            return Ok(0);
+        }
+
+        let mut files = Vec::new();
+        let mut total_size = 0u64;
+        for entry in fs::read_dir(&self.cache_dir)? {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            if metadata.is_file() {
+                let last_used = metadata
+                    .accessed()
+                    .or_else(|_| metadata.modified())
+                    .unwrap_or(SystemTime::UNIX_EPOCH);
+                total_size += metadata.len();
+                files.push((last_used, metadata.len(), entry.path()));
+            }
+        }
+
+        files.sort_by_key(|(last_used, _, _)| *last_used);
+        let mut removed = 0;
+        for (_, size, path) in files {
+            if total_size <= max_size {
+                break;
+            }
+            fs::remove_file(path)?;
+            total_size -= size;
+            removed += 1;
+        }
+
+        Ok(removed)
+    }
+
+    /// Periodically remove cached images older than `max_age_days`, then
+    /// the least recently used ones beyond `max_size` bytes.
+    pub fn start_background_cleanup(
+        self: Arc<Self>,
+        max_size: Option<u64>,
+        max_age_days: Option<u64>,
+        interval_secs: u64,
+    ) {
+        if max_size.is_none() && max_age_days.is_none() {
+            return;
+        }
+        tokio::spawn(async move {
+            let mut interval =
+                tokio::time::interval(tokio::time::Duration::from_secs(interval_secs));
+            loop {
+                interval.tick().await;
+                let resizer = self.clone();
+                let result = tokio::task::spawn_blocking(move || {
+                    let mut removed = 0;
+                    if let Some(days) = max_age_days {
+                        removed += resizer.cleanup_old_cache(days)?;
+                    }
+                    if let Some(max_size) = max_size {
+                        removed += resizer.evict_lru(max_size)?;
+                    }
+                    Ok::<_, ImageResizerError>(removed)
+                })
+                .await;
+                match result {
+                    Ok(Ok(0)) => {}
+                    Ok(Ok(removed)) => info!("Removed {} images from the image cache", removed),
+                    Ok(Err(e)) => error!("Image cache cleanup failed: {}", e),
+                    Err(e) => error!("Image cache cleanup panicked: {}", e),
+                }
+            }
+        });
+    }
+
     pub fn get_cache_size(&self) -> Result<u64, ImageResizerError> {
         let mut total_size = 0u64;
 
@@ -271,6 +776,20 @@ impl ImageResizer {
     }
 }
 
+/// Start of the cache file names of all variants of a source image.
+fn source_cache_prefix(source_path: &Path) -> String {
+    let hash = Sha256::digest(source_path.to_string_lossy().as_bytes());
+    format!("{}-", hex::encode(&hash[..8]))
+}
+
+/// Record a cache hit in the file's access time, which `evict_lru` goes
+/// by. Filesystems mounted with `noatime` or `relatime` would not.
+fn mark_used(path: &Path) {
+    if let Ok(file) = fs::File::open(path) {
+        let _ = file.set_times(fs::FileTimes::new().set_accessed(SystemTime::now()));
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct CacheStats {
     pub total_files: usize,
@@ -287,4 +806,333 @@ pub enum ImageResizerError {
     Image(#[from] image::ImageError),
     #[error("Unsupported format: {0}")]
     UnsupportedFormat(String),
+    #[error("Too many images waiting to be resized")]
+    Busy,
+    #[error("Resize failed: {0}")]
+    Failed(String),
+}
+
+impl IntoResponse for ImageResizerError {
+    fn into_response(self) -> Response {
+        match self {
+            // Clients retry image requests, so there is no need to queue
+            // without limit.
+            ImageResizerError::Busy => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                [(header::RETRY_AFTER, "1")],
+            )
+                .into_response(),
+            e => {
+                error!("Failed to resize image: {}", e);
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A directory that is removed with everything in it when dropped.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new() -> Self {
+            let dir = std::env::temp_dir()
+                .join(format!("jellofin-test-{}", uuid::Uuid::new_v4().simple()));
+            fs::create_dir_all(&dir).unwrap();
+            TempDir(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn test_resizer(dir: &TempDir) -> Arc<ImageResizer> {
+        Arc::new(ImageResizer::new(dir.0.join("cache"), 1, 10).unwrap())
+    }
+
+    fn test_image(width: u32, height: u32) -> DynamicImage {
+        DynamicImage::ImageRgb8(image::RgbImage::from_pixel(
+            width,
+            height,
+            image::Rgb([200, 100, 50]),
+        ))
+    }
+
+    #[test]
+    fn test_parse_color() {
+        assert_eq!(parse_color("#f80"), Some([255, 136, 0, 255]));
+        assert_eq!(parse_color("#8f80"), Some([255, 136, 0, 136]));
+        assert_eq!(parse_color("#ff8800"), Some([255, 136, 0, 255]));
+        assert_eq!(parse_color("ff8800"), Some([255, 136, 0, 255]));
+        assert_eq!(parse_color("#80ff8800"), Some([255, 136, 0, 128]));
+        assert_eq!(parse_color("#00000000"), Some([0, 0, 0, 0]));
+        assert_eq!(parse_color(""), None);
+        assert_eq!(parse_color("red"), None);
+        assert_eq!(parse_color("#12345"), None);
+        assert_eq!(parse_color("#gggggg"), None);
+        assert_eq!(parse_color("#ffé"), None);
+    }
+
+    #[test]
+    fn test_from_accept() {
+        // WebP wins wherever it is in the list.
+        assert_eq!(
+            OutputFormat::from_accept("image/avif,image/webp,*/*;q=0.8"),
+            Some(OutputFormat::Webp)
+        );
+        assert_eq!(
+            OutputFormat::from_accept("image/avif,*/*"),
+            Some(OutputFormat::Avif)
+        );
+        assert_eq!(
+            OutputFormat::from_accept("IMAGE/WEBP"),
+            Some(OutputFormat::Webp)
+        );
+        assert_eq!(
+            OutputFormat::from_accept("image/webp;q=0.5"),
+            Some(OutputFormat::Webp)
+        );
+        // q=0 means not acceptable.
+        assert_eq!(
+            OutputFormat::from_accept("image/webp;q=0, image/avif"),
+            Some(OutputFormat::Avif)
+        );
+        assert_eq!(OutputFormat::from_accept("image/webp; q = 0"), None);
+        assert_eq!(OutputFormat::from_accept("image/*,*/*"), None);
+        assert_eq!(OutputFormat::from_accept(""), None);
+    }
+
+    #[test]
+    fn test_shrink_to_fit() {
+        assert_eq!(shrink_to_fit(400, 200, None, None), (400, 200));
+        assert_eq!(shrink_to_fit(400, 200, Some(100), None), (100, 50));
+        assert_eq!(shrink_to_fit(400, 200, None, Some(50)), (100, 50));
+        assert_eq!(shrink_to_fit(400, 200, Some(100), Some(10)), (20, 10));
+        // Never scales up.
+        assert_eq!(shrink_to_fit(400, 200, Some(800), Some(800)), (400, 200));
+        // Never down to nothing.
+        assert_eq!(shrink_to_fit(1000, 1, Some(10), None), (10, 1));
+    }
+
+    #[test]
+    fn test_cap() {
+        assert_eq!(cap(None), None);
+        assert_eq!(cap(Some(0)), Some(1));
+        assert_eq!(cap(Some(300)), Some(300));
+        assert_eq!(cap(Some(u32::MAX)), Some(MAX_DIMENSION));
+    }
+
+    #[test]
+    fn test_transform_size() {
+        let dir = TempDir::new();
+        let resizer = test_resizer(&dir);
+        let size = |options: ResizeOptions| {
+            resizer
+                .transform(test_image(400, 200), &options)
+                .dimensions()
+        };
+
+        let width = |width| ResizeOptions {
+            width: Some(width),
+            ..Default::default()
+        };
+        assert_eq!(size(width(200)), (200, 100));
+        // Never scaled up, however large the request.
+        assert_eq!(size(width(800)), (400, 200));
+        assert_eq!(size(width(u32::MAX)), (400, 200));
+        assert_eq!(
+            size(ResizeOptions {
+                height: Some(400),
+                ..Default::default()
+            }),
+            (400, 200)
+        );
+        assert_eq!(
+            size(ResizeOptions {
+                width: Some(200),
+                max_height: Some(50),
+                ..Default::default()
+            }),
+            (100, 50)
+        );
+        assert_eq!(
+            size(ResizeOptions {
+                max_width: Some(100),
+                ..Default::default()
+            }),
+            (100, 50)
+        );
+    }
+
+    #[test]
+    fn test_transform_fill() {
+        let dir = TempDir::new();
+        let resizer = test_resizer(&dir);
+        let size = |fill_width, fill_height| {
+            let options = ResizeOptions {
+                fill_width,
+                fill_height,
+                ..Default::default()
+            };
+            resizer
+                .transform(test_image(400, 200), &options)
+                .dimensions()
+        };
+
+        assert_eq!(size(Some(100), Some(100)), (100, 100));
+        assert_eq!(size(Some(300), Some(150)), (300, 150));
+        // Larger than the image: the largest box of that shape it covers.
+        assert_eq!(size(Some(300), Some(300)), (200, 200));
+        assert_eq!(size(Some(1000), Some(500)), (400, 200));
+        // A single fill dimension is a maximum.
+        assert_eq!(size(Some(100), None), (100, 50));
+        assert_eq!(size(None, Some(400)), (400, 200));
+    }
+
+    #[test]
+    fn test_transform_background() {
+        let dir = TempDir::new();
+        let resizer = test_resizer(&dir);
+        let mut img = RgbaImage::from_pixel(2, 2, Rgba([255, 0, 0, 255]));
+        img.put_pixel(0, 0, Rgba([0, 0, 0, 0]));
+        let options = ResizeOptions {
+            background_color: Some([0, 0, 255, 255]),
+            ..Default::default()
+        };
+        let img = resizer.transform(DynamicImage::ImageRgba8(img), &options);
+        assert_eq!(img.get_pixel(0, 0), Rgba([0, 0, 255, 255]));
+        assert_eq!(img.get_pixel(1, 1), Rgba([255, 0, 0, 255]));
+    }
+
+    #[tokio::test]
+    async fn test_resize_is_cached() {
+        let dir = TempDir::new();
+        let resizer = test_resizer(&dir);
+        let source = dir.0.join("poster.png");
+        test_image(400, 200).save(&source).unwrap();
+
+        let options = ResizeOptions {
+            width: Some(100),
+            ..Default::default()
+        };
+        let (a, b) = tokio::join!(
+            resizer.resize(&source, &options),
+            resizer.resize(&source, &options)
+        );
+        let path = a.unwrap();
+        assert_eq!(path, b.unwrap());
+        assert_eq!(image::open(&path).unwrap().dimensions(), (100, 50));
+        assert!(resizer.pending.lock().unwrap().is_empty());
+
+        // Without options the source is served.
+        let path = resizer.resize(&source, &ResizeOptions::default()).await;
+        assert_eq!(path.unwrap(), source);
+
+        // The extension is known up front, for the ETag.
+        let options = ResizeOptions {
+            width: Some(100),
+            format: Some(OutputFormat::Webp),
+            ..Default::default()
+        };
+        let path = resizer.resize(&source, &options).await.unwrap();
+        assert_eq!(path.extension().unwrap(), options.extension("png"));
+        assert_eq!(options.extension("png"), "webp");
+    }
+
+    #[tokio::test]
+    async fn test_requests_are_coalesced() {
+        let dir = TempDir::new();
+        let resizer = test_resizer(&dir);
+        let runs = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+
+        let job = |path: &str| {
+            let runs = runs.clone();
+            let path = PathBuf::from(path);
+            move |_: &ImageResizer| {
+                runs.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                std::thread::sleep(Duration::from_millis(50));
+                Ok(path)
+            }
+        };
+        let (a, b, c) = tokio::join!(
+            resizer.run_pending("key".to_string(), job("a")),
+            resizer.run_pending("key".to_string(), job("b")),
+            resizer.run_pending("other".to_string(), job("c")),
+        );
+        // The second request waited for the first one's result.
+        assert_eq!(a.unwrap(), PathBuf::from("a"));
+        assert_eq!(b.unwrap(), PathBuf::from("a"));
+        assert_eq!(c.unwrap(), PathBuf::from("c"));
+        assert_eq!(runs.load(std::sync::atomic::Ordering::SeqCst), 2);
+        assert!(resizer.pending.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_busy() {
+        let dir = TempDir::new();
+        let resizer = Arc::new(ImageResizer::new(dir.0.join("cache"), 1, 2).unwrap());
+
+        // Two jobs hold the only worker and the queue.
+        let (release, wait) = std::sync::mpsc::channel::<()>();
+        let blocked = {
+            let resizer = resizer.clone();
+            tokio::spawn(async move {
+                resizer
+                    .run_pending("a".to_string(), move |_| {
+                        let _ = wait.recv();
+                        Ok(PathBuf::from("a"))
+                    })
+                    .await
+            })
+        };
+        let queued = {
+            let resizer = resizer.clone();
+            tokio::spawn(async move {
+                resizer
+                    .run_pending("b".to_string(), |_| Ok(PathBuf::from("b")))
+                    .await
+            })
+        };
+        while resizer.pending.lock().unwrap().len() < 2 {
+            tokio::task::yield_now().await;
+        }
+
+        // A third image has to wait, and so does a placeholder.
+        let result = resizer
+            .run_pending("c".to_string(), |_| Ok(PathBuf::from("c")))
+            .await;
+        assert!(matches!(result, Err(ImageResizerError::Busy)));
+        let placeholder = Placeholder {
+            id: "item".to_string(),
+            title: "Title".to_string(),
+            subtitle: None,
+            shape: crate::util::PlaceholderShape::Poster,
+        };
+        let result = resizer.placeholder(&placeholder).await;
+        assert!(matches!(result, Err(ImageResizerError::Busy)));
+        // A request for a pending image joins it.
+        let joined = {
+            let resizer = resizer.clone();
+            tokio::spawn(async move {
+                resizer
+                    .run_pending("a".to_string(), |_| Ok(PathBuf::from("x")))
+                    .await
+            })
+        };
+
+        release.send(()).unwrap();
+        assert_eq!(blocked.await.unwrap().unwrap(), PathBuf::from("a"));
+        assert_eq!(queued.await.unwrap().unwrap(), PathBuf::from("b"));
+        assert_eq!(joined.await.unwrap().unwrap(), PathBuf::from("a"));
+
+        // There is room again.
+        let path = resizer.placeholder(&placeholder).await.unwrap();
+        assert!(path.exists());
+    }
 }
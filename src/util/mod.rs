@@ -1,7 +1,10 @@
 mod generate_id;
 mod imageresize;
+mod placeholder;
 mod query;
+pub mod redact;
 
 pub use generate_id::generate_id;
-pub use imageresize::ImageResizer;
+pub use imageresize::{parse_color, ImageResizer, OutputFormat, ResizeOptions};
+pub use placeholder::{Placeholder, PlaceholderShape};
 pub use query::QueryParams;
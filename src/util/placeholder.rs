@@ -0,0 +1,218 @@
+use ab_glyph::{point, Font, FontRef, PxScale, PxScaleFont, ScaleFont};
+use image::{Rgba, RgbaImage};
+use sha2::{Digest, Sha256};
+
+/// DejaVu Sans Bold, see `fonts/LICENSE`.
+const FONT: &[u8] = include_bytes!("fonts/DejaVuSans-Bold.ttf");
+
+/// Part of the tag; bump it when the rendering changes so clients and
+/// the image cache pick up the new artwork.
+const RENDER_VERSION: u32 = 1;
+
+/// Room around the text, as a fraction of the image width.
+const MARGIN: f32 = 0.08;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlaceholderShape {
+    /// 2:3, for movies, shows and seasons.
+    Poster,
+    /// 16:9, for episodes.
+    Thumb,
+}
+
+impl PlaceholderShape {
+    fn size(self) -> (u32, u32) {
+        match self {
+            PlaceholderShape::Poster => (600, 900),
+            PlaceholderShape::Thumb => (960, 540),
+        }
+    }
+
+    fn max_title_lines(self) -> usize {
+        match self {
+            PlaceholderShape::Poster => 5,
+            PlaceholderShape::Thumb => 2,
+        }
+    }
+}
+
+/// Generated artwork for an item without a primary image: its title on a
+/// background color derived from its ID.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Placeholder {
+    pub id: String,
+    pub title: String,
+    /// Year, or season and episode number.
+    pub subtitle: Option<String>,
+    pub shape: PlaceholderShape,
+}
+
+impl Placeholder {
+    /// Image tag: changes with anything that is drawn.
+    pub fn tag(&self) -> String {
+        let hash = Sha256::digest(format!("{} {:?}", RENDER_VERSION, self).as_bytes());
+        hex::encode(&hash[..16])
+    }
+
+    pub fn render(&self) -> RgbaImage {
+        let (width, height) = self.shape.size();
+        let [red, green, blue] = background_color(&self.id);
+        // Darker towards the bottom.
+        let mut img = RgbaImage::from_fn(width, height, |_, y| {
+            let shade = 1.0 - 0.4 * y as f32 / height as f32;
+            let channel = |c: u8| (c as f32 * shade) as u8;
+            Rgba([channel(red), channel(green), channel(blue), 255])
+        });
+
+        let font = FontRef::try_from_slice(FONT).expect("embedded font is valid");
+        let title_size = match self.shape {
+            PlaceholderShape::Poster => width as f32 / 9.0,
+            PlaceholderShape::Thumb => height as f32 / 7.0,
+        };
+        let title_font = font.as_scaled(PxScale::from(title_size));
+        let subtitle_font = font.as_scaled(PxScale::from(title_size * 0.6));
+        let max_width = width as f32 * (1.0 - 2.0 * MARGIN);
+
+        let lines = wrap(
+            &title_font,
+            &self.title,
+            max_width,
+            self.shape.max_title_lines(),
+        );
+        let line_height = title_font.height() + title_font.line_gap();
+        let subtitle_gap = subtitle_font.height() * 0.5;
+        let subtitle_height = match &self.subtitle {
+            Some(_) => subtitle_gap + subtitle_font.height(),
+            None => 0.0,
+        };
+
+        // Center the title and subtitle together.
+        let mut top = (height as f32 - lines.len() as f32 * line_height - subtitle_height) / 2.0;
+        for line in &lines {
+            draw_line(&mut img, &title_font, line, top, 255);
+            top += line_height;
+        }
+        if let Some(subtitle) = &self.subtitle {
+            let subtitle = fit(&subtitle_font, subtitle, max_width);
+            draw_line(&mut img, &subtitle_font, &subtitle, top + subtitle_gap, 200);
+        }
+
+        img
+    }
+}
+
+/// A muted color picked by hashing the ID, so an item always looks the same.
+fn background_color(id: &str) -> [u8; 3] {
+    let hash = Sha256::digest(id.as_bytes());
+    let hue = u16::from_le_bytes([hash[0], hash[1]]) % 360;
+    hsl_to_rgb(hue as f32, 0.45, 0.35)
+}
+
+fn hsl_to_rgb(hue: f32, saturation: f32, lightness: f32) -> [u8; 3] {
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    let sector = hue / 60.0;
+    let x = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+    let (r, g, b) = match sector as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = lightness - chroma / 2.0;
+    let channel = |c: f32| ((c + m) * 255.0).round() as u8;
+    [channel(r), channel(g), channel(b)]
+}
+
+fn text_width(font: &PxScaleFont<&FontRef>, text: &str) -> f32 {
+    let mut width = 0.0;
+    let mut previous = None;
+    for c in text.chars() {
+        let glyph = font.glyph_id(c);
+        if let Some(previous) = previous {
+            width += font.kern(previous, glyph);
+        }
+        width += font.h_advance(glyph);
+        previous = Some(glyph);
+    }
+    width
+}
+
+/// Break `text` into lines no wider than `max_width`, at most `max_lines`
+/// of them; what does not fit is cut off with an ellipsis.
+fn wrap(font: &PxScaleFont<&FontRef>, text: &str, max_width: f32, max_lines: usize) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    let mut current = String::new();
+    for word in text.split_whitespace() {
+        let candidate = if current.is_empty() {
+            word.to_string()
+        } else {
+            format!("{} {}", current, word)
+        };
+        if current.is_empty() || text_width(font, &candidate) <= max_width {
+            current = candidate;
+            continue;
+        }
+        if lines.len() + 1 == max_lines {
+            lines.push(fit(font, &format!("{}\u{2026}", current), max_width));
+            return lines;
+        }
+        lines.push(fit(font, &current, max_width));
+        current = word.to_string();
+    }
+    if !current.is_empty() {
+        lines.push(fit(font, &current, max_width));
+    }
+    lines
+}
+
+/// Shorten `text` with an ellipsis until it is no wider than `max_width`.
+fn fit(font: &PxScaleFont<&FontRef>, text: &str, max_width: f32) -> String {
+    if text_width(font, text) <= max_width {
+        return text.to_string();
+    }
+    let mut chars: Vec<char> = text.trim_end_matches('\u{2026}').chars().collect();
+    while !chars.is_empty() {
+        chars.pop();
+        let shortened = format!("{}\u{2026}", chars.iter().collect::<String>().trim_end());
+        if text_width(font, &shortened) <= max_width {
+            return shortened;
+        }
+    }
+    String::new()
+}
+
+/// Draw a horizontally centered line of white text, `alpha` opaque.
+fn draw_line(img: &mut RgbaImage, font: &PxScaleFont<&FontRef>, text: &str, top: f32, alpha: u8) {
+    let (width, height) = img.dimensions();
+    let mut x = (width as f32 - text_width(font, text)) / 2.0;
+    let baseline = top + font.ascent();
+    let mut previous = None;
+    for c in text.chars() {
+        let glyph_id = font.glyph_id(c);
+        if let Some(previous) = previous {
+            x += font.kern(previous, glyph_id);
+        }
+        let glyph = glyph_id.with_scale_and_position(font.scale(), point(x, baseline));
+        x += font.h_advance(glyph_id);
+        previous = Some(glyph_id);
+
+        let Some(outlined) = font.outline_glyph(glyph) else {
+            continue;
+        };
+        let bounds = outlined.px_bounds();
+        outlined.draw(|gx, gy, coverage| {
+            let px = bounds.min.x as i32 + gx as i32;
+            let py = bounds.min.y as i32 + gy as i32;
+            if px < 0 || py < 0 || px >= width as i32 || py >= height as i32 {
+                return;
+            }
+            let opacity = coverage.clamp(0.0, 1.0) * alpha as f32 / 255.0;
+            let pixel = img.get_pixel_mut(px as u32, py as u32);
+            for channel in &mut pixel.0[..3] {
+                *channel = (*channel as f32 + (255.0 - *channel as f32) * opacity) as u8;
+            }
+        });
+    }
+}
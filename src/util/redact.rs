@@ -0,0 +1,237 @@
+use axum::http::{HeaderName, HeaderValue, Uri};
+
+/// Replacement for redacted values.
+const REDACTED: &str = "***";
+
+/// JSON fields, query parameters and form fields holding credentials,
+/// compared case-insensitively.
+const SENSITIVE_FIELDS: &[&str] = &[
+    "pw",
+    "password",
+    "currentpw",
+    "currentpassword",
+    "newpw",
+    "accesstoken",
+    "token",
+    "api_key",
+    "apikey",
+    "secret",
+];
+
+/// Headers that are redacted as a whole.
+const SENSITIVE_HEADERS: &[&str] = &[
+    "x-emby-token",
+    "x-mediabrowser-token",
+    "cookie",
+    "set-cookie",
+];
+
+/// Headers in the Emby `MediaBrowser Client="...", Token="..."` format;
+/// only their `Token` is redacted.
+const EMBY_AUTH_HEADERS: &[&str] = &["authorization", "x-emby-authorization"];
+
+fn is_sensitive(name: &str) -> bool {
+    SENSITIVE_FIELDS
+        .iter()
+        .any(|field| field.eq_ignore_ascii_case(name.trim()))
+}
+
+/// Replacement for request and response bodies that cannot be redacted.
+const UNPARSEABLE_BODY: &str = "<unparseable body redacted>";
+
+/// Paths whose remaining segments are credentials, compared
+/// case-insensitively.
+const SENSITIVE_PATHS: &[&str] = &["/auth/keys/"];
+
+/// The path and query of a URI with credentials redacted.
+pub fn redact_uri(uri: &Uri) -> String {
+    match uri.path_and_query() {
+        Some(path_and_query) => redact_url(path_and_query.as_str()),
+        None => redact_url(uri.path()),
+    }
+}
+
+/// A URL, absolute or not, with credentials in the path and query
+/// redacted.
+pub fn redact_url(url: &str) -> String {
+    match url.split_once('?') {
+        Some((path, query)) => format!("{}?{}", redact_path(path), redact_pairs(query)),
+        None => redact_path(url),
+    }
+}
+
+fn redact_path(path: &str) -> String {
+    let lower = path.to_ascii_lowercase();
+    for prefix in SENSITIVE_PATHS {
+        if let Some(pos) = lower.find(prefix) {
+            let end = pos + prefix.len();
+            if end < path.len() {
+                return format!("{}{}", &path[..end], REDACTED);
+            }
+        }
+    }
+    path.to_string()
+}
+
+/// A header value for logging.
+pub fn redact_header(name: &HeaderName, value: &HeaderValue) -> String {
+    let name = name.as_str();
+    if SENSITIVE_HEADERS.contains(&name) {
+        return REDACTED.to_string();
+    }
+    let value = String::from_utf8_lossy(value.as_bytes());
+    if name == "referer" {
+        return redact_url(&value);
+    }
+    if EMBY_AUTH_HEADERS.contains(&name) {
+        if !value.contains('=') {
+            // Bearer, Basic and the like.
+            return REDACTED.to_string();
+        }
+        return value
+            .split(',')
+            .map(|part| match part.split_once('=') {
+                // The first key includes the scheme: `MediaBrowser Token`.
+                Some((key, _))
+                    if key
+                        .split_whitespace()
+                        .last()
+                        .is_some_and(|k| k.eq_ignore_ascii_case("token")) =>
+                {
+                    format!("{}=\"{}\"", key, REDACTED)
+                }
+                _ => part.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+    }
+    value.into_owned()
+}
+
+/// A request or response body for logging: sensitive JSON fields, or
+/// form fields, are redacted. Other bodies are not logged at all.
+pub fn redact_body(body: &str) -> String {
+    if body.is_empty() {
+        return String::new();
+    }
+    if let Ok(mut json) = serde_json::from_str::<serde_json::Value>(body) {
+        redact_json(&mut json);
+        return json.to_string();
+    }
+    if body.contains('=') && !body.contains(char::is_whitespace) {
+        return redact_pairs(body);
+    }
+    UNPARSEABLE_BODY.to_string()
+}
+
+fn redact_json(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                if is_sensitive(key) && !value.is_null() {
+                    *value = serde_json::Value::String(REDACTED.to_string());
+                } else {
+                    redact_json(value);
+                }
+            }
+        }
+        serde_json::Value::Array(values) => values.iter_mut().for_each(redact_json),
+        _ => {}
+    }
+}
+
+/// Redact the values of `key=value&...` pairs.
+fn redact_pairs(pairs: &str) -> String {
+    pairs
+        .split('&')
+        .map(|pair| match pair.split_once('=') {
+            Some((key, _)) if is_sensitive(key) => format!("{}={}", key, REDACTED),
+            _ => pair.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// Cut `text` to at most `max_len` bytes, on a character boundary.
+pub fn truncate(text: &str, max_len: usize) -> &str {
+    if text.len() <= max_len {
+        return text;
+    }
+    let mut end = max_len;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_uri() {
+        let uri: Uri = "/Items/1/Images/Primary?api_key=secret&maxWidth=300"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            redact_uri(&uri),
+            "/Items/1/Images/Primary?api_key=***&maxWidth=300"
+        );
+        let uri: Uri = "/Auth/Keys/0123abcd".parse().unwrap();
+        assert_eq!(redact_uri(&uri), "/Auth/Keys/***");
+        let uri: Uri = "/emby/auth/keys/0123abcd?x=1".parse().unwrap();
+        assert_eq!(redact_uri(&uri), "/emby/auth/keys/***?x=1");
+        let uri: Uri = "/Auth/Keys?app=test".parse().unwrap();
+        assert_eq!(redact_uri(&uri), "/Auth/Keys?app=test");
+    }
+
+    #[test]
+    fn test_redact_header() {
+        let name = HeaderName::from_static("x-emby-token");
+        let value = HeaderValue::from_static("secret");
+        assert_eq!(redact_header(&name, &value), REDACTED);
+
+        let name = HeaderName::from_static("x-emby-authorization");
+        let value =
+            HeaderValue::from_static(r#"MediaBrowser Client="Web", Token="secret", Version="1""#);
+        assert_eq!(
+            redact_header(&name, &value),
+            r#"MediaBrowser Client="Web", Token="***", Version="1""#
+        );
+        let name = HeaderName::from_static("authorization");
+        let value = HeaderValue::from_static("Bearer secret");
+        assert_eq!(redact_header(&name, &value), REDACTED);
+
+        let name = HeaderName::from_static("referer");
+        let value = HeaderValue::from_static("https://example.com/web/index.html?api_key=secret");
+        assert_eq!(
+            redact_header(&name, &value),
+            "https://example.com/web/index.html?api_key=***"
+        );
+
+        let name = HeaderName::from_static("user-agent");
+        let value = HeaderValue::from_static("curl/8.0");
+        assert_eq!(redact_header(&name, &value), "curl/8.0");
+    }
+
+    #[test]
+    fn test_redact_body() {
+        assert_eq!(
+            redact_body(r#"{"Username":"joe","Pw":"secret","Nested":[{"AccessToken":"t"}]}"#),
+            r#"{"Nested":[{"AccessToken":"***"}],"Pw":"***","Username":"joe"}"#
+        );
+        assert_eq!(
+            redact_body("user=joe&password=secret"),
+            "user=joe&password=***"
+        );
+        assert_eq!(redact_body("password: secret"), UNPARSEABLE_BODY);
+        assert_eq!(redact_body(""), "");
+    }
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(truncate("hello", 10), "hello");
+        assert_eq!(truncate("hello", 3), "hel");
+        assert_eq!(truncate("héllo", 2), "h");
+    }
+}